@@ -5,10 +5,13 @@ use crate::{
     graphemes, movement::Direction, Range, Rope, RopeGraphemes, Selection, Tendril, Transaction,
 };
 use log::debug;
+use serde::{Deserialize, Deserializer};
 use smallvec::SmallVec;
+use std::collections::HashMap;
 
 // Heavily based on https://github.com/codemirror/closebrackets/
 
+/// The pairs used when a language doesn't configure its own.
 pub const PAIRS: &[(char, char)] = &[
     ('(', ')'),
     ('{', '}'),
@@ -18,9 +21,256 @@ pub const PAIRS: &[(char, char)] = &[
     ('`', '`'),
 ];
 
-// [TODO] build this dynamically in language config. see #992
-const OPEN_BEFORE: &str = "([{'\":;,> \n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{2029}";
-const CLOSE_BEFORE: &str = ")]}'\":;,> \n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{2029}"; // includes space and newlines
+/// Characters that, along with the pair tokens, allow inserting a whole pair when they
+/// precede (`OPEN_BEFORE`) or follow (`CLOSE_BEFORE`) the cursor.
+const SEPARATORS: &str = ":;,> \n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{2029}"; // includes space and newlines
+
+/// An opening and a closing token, such as `(` and `)`, or `{%` and `%}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    pub open: String,
+    pub close: String,
+}
+
+impl Pair {
+    pub fn new(open: impl Into<String>, close: impl Into<String>) -> Self {
+        Self {
+            open: open.into(),
+            close: close.into(),
+        }
+    }
+
+    /// Returns the tokens if they are both a single char.
+    pub fn chars(&self) -> Option<(char, char)> {
+        let mut open = self.open.chars();
+        let mut close = self.close.chars();
+        match (open.next(), open.next(), close.next(), close.next()) {
+            (Some(open), None, Some(close), None) => Some((open, close)),
+            _ => None,
+        }
+    }
+}
+
+/// The set of pairs that are auto-closed in a document. It's empty when auto pairs are
+/// disabled for the language.
+///
+/// In languages.toml, `auto-pairs` is either a boolean that enables or disables the
+/// default pairs, or a table mapping opening tokens to closing tokens:
+///
+/// ```toml
+/// auto-pairs = { '(' = ')', '"' = '"', '"""' = '"""', '{%' = '%}' }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoPairs {
+    /// Sorted by decreasing length of the opening token, so that `"""` takes
+    /// precedence over `"`.
+    pairs: Vec<Pair>,
+    open_before: String,
+    close_before: String,
+}
+
+impl AutoPairs {
+    pub fn new(pairs: impl IntoIterator<Item = Pair>) -> Self {
+        let mut pairs: Vec<_> = pairs
+            .into_iter()
+            .filter(|pair| !pair.open.is_empty() && !pair.close.is_empty())
+            .collect();
+        pairs.sort_by(|a, b| {
+            (b.open.chars().count(), &a.open).cmp(&(a.open.chars().count(), &b.open))
+        });
+
+        let mut open_before: String = pairs
+            .iter()
+            .filter_map(|pair| pair.open.chars().last())
+            .collect();
+        let mut close_before: String = pairs
+            .iter()
+            .filter_map(|pair| pair.close.chars().next())
+            .collect();
+        open_before.push_str(SEPARATORS);
+        close_before.push_str(SEPARATORS);
+
+        Self {
+            pairs,
+            open_before,
+            close_before,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pair> {
+        self.pairs.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Whether `open` and `close` are the tokens of a single char pair.
+    pub fn is_pair(&self, open: char, close: char) -> bool {
+        self.pairs
+            .iter()
+            .any(|pair| pair.chars() == Some((open, close)))
+    }
+
+    /// Whether a closing token can be inserted before `next_char`.
+    fn can_close_before(&self, next_char: Option<char>) -> bool {
+        match next_char {
+            Some(c) => self.close_before.contains(c),
+            None => true,
+        }
+    }
+
+    /// Whether a pair of equal tokens can be inserted after `prev_char`.
+    fn can_open_after(&self, prev_char: Option<char>) -> bool {
+        match prev_char {
+            Some(c) => self.open_before.contains(c),
+            None => true,
+        }
+    }
+
+    /// When deleting backward at `cursor` within an empty pair, returns the range to
+    /// delete instead of the previous char: the whole pair, `(|)` -> `|`, or one
+    /// whitespace char on both sides of a padded pair, `{ | }` -> `{|}`.
+    pub fn delete_backward(&self, doc: &Rope, cursor: usize) -> Option<(usize, usize)> {
+        let is_padding = |c| c == ' ' || c == '\t';
+
+        if matches!(prev_char(doc, cursor), Some(' ' | '\t'))
+            && matches!(doc.get_char(cursor), Some(' ' | '\t'))
+        {
+            let start = (0..cursor)
+                .rev()
+                .find(|&i| !is_padding(doc.char(i)))
+                .map_or(0, |i| i + 1);
+            let end = (cursor..doc.len_chars())
+                .find(|&i| !is_padding(doc.char(i)))
+                .unwrap_or_else(|| doc.len_chars());
+            let padded = self.pairs.iter().any(|pair| {
+                text_before_is(doc, start, &pair.open) && text_after_is(doc, end, &pair.close)
+            });
+            return if padded {
+                Some((cursor - 1, cursor + 1))
+            } else {
+                None
+            };
+        }
+
+        self.pairs
+            .iter()
+            .find(|pair| {
+                text_before_is(doc, cursor, &pair.open) && text_after_is(doc, cursor, &pair.close)
+            })
+            .map(|pair| {
+                (
+                    cursor - pair.open.chars().count(),
+                    cursor + pair.close.chars().count(),
+                )
+            })
+    }
+
+    /// Whether typing `ch` can insert or move over a token.
+    fn handles(&self, ch: char) -> bool {
+        self.pairs
+            .iter()
+            .any(|pair| pair.open.contains(ch) || pair.close.contains(ch))
+    }
+
+    /// Returns the text to insert at `cursor` when typing `ch`, or `None` to move over
+    /// the next char instead.
+    fn insertion(&self, doc: &Rope, cursor: usize, ch: char) -> Option<Tendril> {
+        let next_char = doc.get_char(cursor);
+        let prev_char = prev_char(doc, cursor);
+
+        for pair in &self.pairs {
+            match pair.chars() {
+                Some((open, close)) if open == ch && open == close => {
+                    // move over the closing token
+                    if next_char == Some(ch) {
+                        return None;
+                    }
+                    // for equal pairs, don't insert both open and close if either
+                    // side has a non-pair char
+                    let insert_pair =
+                        self.can_close_before(next_char) && self.can_open_after(prev_char);
+                    if insert_pair {
+                        return Some(Tendril::from_iter([ch, ch]));
+                    }
+                    return Some(Tendril::from_iter([ch]));
+                }
+                Some((open, close)) if open == ch => {
+                    if self.can_close_before(next_char) {
+                        return Some(Tendril::from_iter([open, close]));
+                    }
+                    return Some(Tendril::from_iter([open]));
+                }
+                Some((_, close)) if close == ch => {
+                    if next_char == Some(close) {
+                        return None;
+                    }
+                    return Some(Tendril::from_iter([close]));
+                }
+                Some(_) => (),
+                None => {
+                    // move over the closing token, when the cursor is within it
+                    for (i, c) in pair.close.char_indices() {
+                        if c == ch
+                            && text_after_is(doc, cursor, &pair.close[i..])
+                            && text_before_is(doc, cursor, &pair.close[..i])
+                        {
+                            return None;
+                        }
+                    }
+
+                    // complete the opening token, and insert the part of the closing
+                    // token that doesn't follow the cursor yet: {|} -> {%|%}
+                    let prefix = match pair.open.strip_suffix(ch) {
+                        Some(prefix) if text_before_is(doc, cursor, prefix) => prefix,
+                        _ => continue,
+                    };
+                    debug!("completing {:?} after {:?}", pair.open, prefix);
+                    let present = (0..pair.close.len())
+                        .filter(|&i| pair.close.is_char_boundary(i))
+                        .find(|&i| text_after_is(doc, cursor, &pair.close[i..]))
+                        .unwrap_or(pair.close.len());
+                    let mut tendril = Tendril::from_iter([ch]);
+                    tendril.push_str(&pair.close[..present]);
+                    return Some(tendril);
+                }
+            }
+        }
+
+        Some(Tendril::from_iter([ch]))
+    }
+}
+
+impl Default for AutoPairs {
+    fn default() -> Self {
+        Self::new(PAIRS.iter().map(|&(open, close)| Pair::new(open, close)))
+    }
+}
+
+impl<'de> Deserialize<'de> for AutoPairs {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum AutoPairConfig {
+            Enable(bool),
+            Pairs(HashMap<String, String>),
+        }
+
+        Ok(match AutoPairConfig::deserialize(deserializer)? {
+            AutoPairConfig::Enable(true) => Self::default(),
+            AutoPairConfig::Enable(false) => Self::new([]),
+            AutoPairConfig::Pairs(pairs) => Self::new(
+                pairs
+                    .into_iter()
+                    .map(|(open, close)| Pair::new(open, close)),
+            ),
+        })
+    }
+}
 
 // insert hook:
 // Fn(doc, selection, char) => Option<Transaction>
@@ -30,31 +280,46 @@ const CLOSE_BEFORE: &str = ")]}'\":;,> \n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{20
 //
 // to simplify, maybe return Option<Transaction> and just reimplement the default
 
-// [TODO]
-// * delete implementation where it erases the whole bracket (|) -> |
-// * change to multi character pairs to handle cases like placing the cursor in the
-//   middle of triple quotes, and more exotic pairs like Jinja's {% %}
-
 #[must_use]
-pub fn hook(doc: &Rope, selection: &Selection, ch: char) -> Option<Transaction> {
+pub fn hook(doc: &Rope, selection: &Selection, ch: char, pairs: &AutoPairs) -> Option<Transaction> {
     debug!("autopairs hook selection: {:#?}", selection);
 
-    for &(open, close) in PAIRS {
-        if open == ch {
-            if open == close {
-                return Some(handle_same(doc, selection, open, CLOSE_BEFORE, OPEN_BEFORE));
-            } else {
-                return Some(handle_open(doc, selection, open, close, CLOSE_BEFORE));
-            }
-        }
-
-        if close == ch {
-            // && char_at pos == close
-            return Some(handle_close(doc, selection, open, close));
-        }
+    if !pairs.handles(ch) {
+        return None;
     }
 
-    None
+    let mut end_ranges = SmallVec::with_capacity(selection.len());
+    let mut offs = 0;
+
+    let transaction = Transaction::change_by_selection(doc, selection, |start_range| {
+        let cursor = start_range.cursor(doc.slice(..));
+        let insertion = pairs.insertion(doc, cursor, ch);
+        let len_inserted = insertion
+            .as_ref()
+            .map_or(0, |insertion| insertion.chars().count());
+
+        let next_range = get_next_range(doc, start_range, offs, ch, len_inserted);
+        end_ranges.push(next_range);
+        offs += len_inserted;
+
+        (cursor, cursor, insertion)
+    });
+
+    let t = transaction.with_selection(Selection::new(end_ranges, selection.primary_index()));
+    debug!("auto pair transaction: {:#?}", t);
+    Some(t)
+}
+
+/// Whether the text right before `pos` is `s`.
+fn text_before_is(doc: &Rope, pos: usize, s: &str) -> bool {
+    let len = s.chars().count();
+    len <= pos && doc.slice(pos - len..pos) == s
+}
+
+/// Whether the text right after `pos` is `s`.
+fn text_after_is(doc: &Rope, pos: usize, s: &str) -> bool {
+    let len = s.chars().count();
+    pos + len <= doc.len_chars() && doc.slice(pos..pos + len) == s
 }
 
 fn prev_char(doc: &Rope, pos: usize) -> Option<char> {
@@ -196,131 +461,6 @@ fn get_next_range(
     Range::new(end_anchor, end_head)
 }
 
-fn handle_open(
-    doc: &Rope,
-    selection: &Selection,
-    open: char,
-    close: char,
-    close_before: &str,
-) -> Transaction {
-    let mut end_ranges = SmallVec::with_capacity(selection.len());
-    let mut offs = 0;
-
-    let transaction = Transaction::change_by_selection(doc, selection, |start_range| {
-        let cursor = start_range.cursor(doc.slice(..));
-        let next_char = doc.get_char(cursor);
-        let len_inserted;
-
-        let change = match next_char {
-            Some(ch) if !close_before.contains(ch) => {
-                len_inserted = open.len_utf8();
-                let mut tendril = Tendril::new();
-                tendril.push(open);
-                (cursor, cursor, Some(tendril))
-            }
-            // None | Some(ch) if close_before.contains(ch) => {}
-            _ => {
-                // insert open & close
-                let pair = Tendril::from_iter([open, close]);
-                len_inserted = open.len_utf8() + close.len_utf8();
-                (cursor, cursor, Some(pair))
-            }
-        };
-
-        let next_range = get_next_range(doc, start_range, offs, open, len_inserted);
-        end_ranges.push(next_range);
-        offs += len_inserted;
-
-        change
-    });
-
-    let t = transaction.with_selection(Selection::new(end_ranges, selection.primary_index()));
-    debug!("auto pair transaction: {:#?}", t);
-    t
-}
-
-fn handle_close(doc: &Rope, selection: &Selection, _open: char, close: char) -> Transaction {
-    let mut end_ranges = SmallVec::with_capacity(selection.len());
-
-    let mut offs = 0;
-
-    let transaction = Transaction::change_by_selection(doc, selection, |start_range| {
-        let cursor = start_range.cursor(doc.slice(..));
-        let next_char = doc.get_char(cursor);
-        let mut len_inserted = 0;
-
-        let change = if next_char == Some(close) {
-            // return transaction that moves past close
-            (cursor, cursor, None) // no-op
-        } else {
-            len_inserted += close.len_utf8();
-            let mut tendril = Tendril::new();
-            tendril.push(close);
-            (cursor, cursor, Some(tendril))
-        };
-
-        let next_range = get_next_range(doc, start_range, offs, close, len_inserted);
-        end_ranges.push(next_range);
-        offs += len_inserted;
-
-        change
-    });
-
-    let t = transaction.with_selection(Selection::new(end_ranges, selection.primary_index()));
-    debug!("auto pair transaction: {:#?}", t);
-    t
-}
-
-/// handle cases where open and close is the same, or in triples ("""docstring""")
-fn handle_same(
-    doc: &Rope,
-    selection: &Selection,
-    token: char,
-    close_before: &str,
-    open_before: &str,
-) -> Transaction {
-    let mut end_ranges = SmallVec::with_capacity(selection.len());
-
-    let mut offs = 0;
-
-    let transaction = Transaction::change_by_selection(doc, selection, |start_range| {
-        let cursor = start_range.cursor(doc.slice(..));
-        let mut len_inserted = 0;
-
-        let next_char = doc.get_char(cursor);
-        let prev_char = prev_char(doc, cursor);
-
-        let change = if next_char == Some(token) {
-            //  return transaction that moves past close
-            (cursor, cursor, None) // no-op
-        } else {
-            let mut pair = Tendril::new();
-            pair.push(token);
-
-            // for equal pairs, don't insert both open and close if either
-            // side has a non-pair char
-            if (next_char.is_none() || close_before.contains(next_char.unwrap()))
-                && (prev_char.is_none() || open_before.contains(prev_char.unwrap()))
-            {
-                pair.push(token);
-            }
-
-            len_inserted += pair.len();
-            (cursor, cursor, Some(pair))
-        };
-
-        let next_range = get_next_range(doc, start_range, offs, token, len_inserted);
-        end_ranges.push(next_range);
-        offs += len_inserted;
-
-        change
-    });
-
-    let t = transaction.with_selection(Selection::new(end_ranges, selection.primary_index()));
-    debug!("auto pair transaction: {:#?}", t);
-    t
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -343,7 +483,7 @@ mod test {
         expected_doc: &Rope,
         expected_sel: &Selection,
     ) {
-        let trans = hook(in_doc, in_sel, ch).unwrap();
+        let trans = hook(in_doc, in_sel, ch, &AutoPairs::default()).unwrap();
         let mut actual_doc = in_doc.clone();
         assert!(trans.apply(&mut actual_doc));
         assert_eq!(expected_doc, &actual_doc);
@@ -783,4 +923,98 @@ mod test {
             &expected_sel,
         );
     }
+
+    /// Types `chars` one by one, starting with a cursor at the start of `doc`.
+    fn type_chars(doc: &str, chars: &str, pairs: &AutoPairs) -> (Rope, usize) {
+        let mut doc = Rope::from(doc);
+        let mut selection = Selection::single(1, 0);
+        for ch in chars.chars() {
+            match hook(&doc, &selection, ch, pairs) {
+                Some(transaction) => {
+                    assert!(transaction.apply(&mut doc));
+                    selection = transaction.selection().unwrap().clone();
+                }
+                None => {
+                    let cursors = selection.clone().cursors(doc.slice(..));
+                    let transaction = Transaction::insert(&doc, &cursors, Tendril::from_iter([ch]));
+                    assert!(transaction.apply(&mut doc));
+                    selection = selection.map(transaction.changes());
+                }
+            }
+        }
+        let cursor = selection.primary().cursor(doc.slice(..));
+        (doc, cursor)
+    }
+
+    #[test]
+    fn test_multi_char_pairs() {
+        let pairs = AutoPairs::new([
+            Pair::new("{", "}"),
+            Pair::new("\"", "\""),
+            Pair::new("\"\"\"", "\"\"\""),
+            Pair::new("{%", "%}"),
+            Pair::new("<!--", "-->"),
+        ]);
+
+        // {|} -> {%|%} -> {%%}|
+        assert_eq!(type_chars("\n", "{%", &pairs), (Rope::from("{%%}\n"), 2));
+        assert_eq!(type_chars("\n", "{%%}", &pairs), (Rope::from("{%%}\n"), 4));
+
+        assert_eq!(
+            type_chars("\n", "\"\"\"", &pairs),
+            (Rope::from("\"\"\"\"\"\"\n"), 3)
+        );
+        assert_eq!(
+            type_chars("\n", "\"\"\"a\"\"\"", &pairs),
+            (Rope::from("\"\"\"a\"\"\"\n"), 7)
+        );
+
+        assert_eq!(
+            type_chars("\n", "<!--a-->", &pairs),
+            (Rope::from("<!--a-->\n"), 8)
+        );
+
+        // chars of multi-char pairs are inserted as is elsewhere
+        assert_eq!(type_chars("\n", "%", &pairs), (Rope::from("%\n"), 1));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(rename = "auto-pairs")]
+            auto_pairs: AutoPairs,
+        }
+        let parse = |s| toml::from_str::<Config>(s).unwrap().auto_pairs;
+
+        assert_eq!(parse("auto-pairs = true"), AutoPairs::default());
+        assert!(parse("auto-pairs = false").is_empty());
+        assert_eq!(
+            parse("auto-pairs = { '(' = ')', '{%' = '%}' }"),
+            AutoPairs::new([Pair::new("{%", "%}"), Pair::new("(", ")")])
+        );
+    }
+
+    #[test]
+    fn test_delete_backward() {
+        let pairs = AutoPairs::new([
+            Pair::new("(", ")"),
+            Pair::new("{", "}"),
+            Pair::new("\"", "\""),
+            Pair::new("{%", "%}"),
+        ]);
+        let delete = |doc: &str, cursor| pairs.delete_backward(&Rope::from(doc), cursor);
+
+        assert_eq!(delete("foo()\n", 4), Some((3, 5)));
+        assert_eq!(delete("\"\"", 1), Some((0, 2)));
+        assert_eq!(delete("{%%}", 2), Some((0, 4)));
+        assert_eq!(delete("{  }", 2), Some((1, 3)));
+        assert_eq!(delete("{\t }", 2), Some((1, 3)));
+
+        // not within an empty pair
+        assert_eq!(delete("(a)", 2), None);
+        assert_eq!(delete("()", 0), None);
+        assert_eq!(delete("(  a)", 2), None);
+        assert_eq!(delete("a  b", 2), None);
+    }
 }
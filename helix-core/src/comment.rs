@@ -1,11 +1,29 @@
 //! This module contains the functionality toggle comments on lines over the selection
-//! using the comment character defined in the user's `languages.toml`
+//! using the comment character defined in the user's `languages.toml`, and to wrap
+//! selections in the block comment tokens defined there.
 
 use crate::{
-    find_first_non_whitespace_char, Change, Rope, RopeSlice, Selection, Tendril, Transaction,
+    find_first_non_whitespace_char, Change, Range, Rope, RopeSlice, Selection, Tendril, Transaction,
 };
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
+/// The start and end tokens of a block comment, such as `/*` and `*/`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCommentToken {
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for BlockCommentToken {
+    fn default() -> Self {
+        Self {
+            start: "/*".to_string(),
+            end: "*/".to_string(),
+        }
+    }
+}
+
 /// Given text, a comment token, and a set of line indices, returns the following:
 /// - Whether the given lines should be considered commented
 ///     - If any of the lines are uncommented, all lines are considered as such.
@@ -94,6 +112,143 @@ pub fn toggle_line_comments(doc: &Rope, selection: &Selection, token: Option<&st
     Transaction::change(doc, changes.into_iter())
 }
 
+/// A block comment found within a range, as char positions in the document.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockComment {
+    /// Start of the start token.
+    start: usize,
+    /// End of the end token.
+    end: usize,
+    /// Margin to the right of the start token, `0` or `1`.
+    start_margin: usize,
+    /// Margin to the left of the end token, `0` or `1`.
+    end_margin: usize,
+}
+
+/// Returns the range's bounds with leading and trailing whitespace trimmed, or `None` if
+/// the range only contains whitespace.
+fn trim_range(text: RopeSlice, range: &Range) -> Option<(usize, usize)> {
+    let mut start = range.from();
+    let mut end = range.to();
+    while start < end && text.char(start).is_whitespace() {
+        start += 1;
+    }
+    while end > start && text.char(end - 1).is_whitespace() {
+        end -= 1;
+    }
+    if start == end {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+/// Returns the block comment that spans the trimmed range, if there is one.
+fn find_block_comment(
+    tokens: &BlockCommentToken,
+    text: RopeSlice,
+    start: usize,
+    end: usize,
+) -> Option<BlockComment> {
+    let start_len = tokens.start.chars().count();
+    let end_len = tokens.end.chars().count();
+    if end - start < start_len + end_len
+        || text.slice(start..start + start_len) != tokens.start.as_str()
+        || text.slice(end - end_len..end) != tokens.end.as_str()
+    {
+        return None;
+    }
+
+    // the margins can't overlap, e.g. in `/* */`
+    let inner = end - end_len - (start + start_len);
+    let start_margin = (inner > 0 && text.char(start + start_len) == ' ') as usize;
+    let end_margin = (inner > start_margin && text.char(end - end_len - 1) == ' ') as usize;
+
+    Some(BlockComment {
+        start,
+        end,
+        start_margin,
+        end_margin,
+    })
+}
+
+/// Wraps each range of the selection in the block comment `tokens`, or unwraps them if every
+/// range is already wrapped. Whitespace around the ranges is left outside the comments, and
+/// ranges that only contain whitespace are left untouched. The returned transaction selects
+/// the resulting text, comment tokens included.
+#[must_use]
+pub fn toggle_block_comments(
+    doc: &Rope,
+    selection: &Selection,
+    tokens: &BlockCommentToken,
+) -> Transaction {
+    let text = doc.slice(..);
+
+    let trimmed: Vec<_> = selection
+        .iter()
+        .map(|range| trim_range(text, range))
+        .collect();
+    let comments: Vec<_> = trimmed
+        .iter()
+        .map(|bounds| bounds.and_then(|(start, end)| find_block_comment(tokens, text, start, end)))
+        .collect();
+    let commented = trimmed.iter().any(Option::is_some)
+        && trimmed
+            .iter()
+            .zip(&comments)
+            .all(|(bounds, comment)| bounds.is_none() || comment.is_some());
+
+    let start_len = tokens.start.chars().count();
+    let end_len = tokens.end.chars().count();
+    let start_token = Tendril::from(format!("{} ", tokens.start));
+    let end_token = Tendril::from(format!(" {}", tokens.end));
+
+    let mut changes: Vec<Change> = Vec::with_capacity(selection.len() * 2);
+    let mut ranges = Vec::with_capacity(selection.len());
+    // offset of the ranges caused by the previous changes
+    let mut offs = 0isize;
+    let shift = |pos: usize, offs: isize| (pos as isize + offs) as usize;
+
+    for ((range, bounds), comment) in selection.iter().zip(trimmed).zip(comments) {
+        let (start, end) = match bounds {
+            Some(bounds) => bounds,
+            None => {
+                ranges.push(Range::new(
+                    shift(range.anchor, offs),
+                    shift(range.head, offs),
+                ));
+                continue;
+            }
+        };
+
+        let (new_start, new_end) = if commented {
+            let comment = comment.unwrap();
+            let removed_start = start_len + comment.start_margin;
+            let removed_end = end_len + comment.end_margin;
+            changes.push((start, start + removed_start, None));
+            changes.push((end - removed_end, end, None));
+            let new_start = shift(start, offs);
+            offs -= (removed_start + removed_end) as isize;
+            (new_start, shift(end, offs))
+        } else {
+            changes.push((start, start, Some(start_token.clone())));
+            changes.push((end, end, Some(end_token.clone())));
+            let new_start = shift(start, offs);
+            offs += (start_len + end_len + 2) as isize;
+            (new_start, shift(end, offs))
+        };
+
+        ranges.push(if range.anchor <= range.head {
+            Range::new(new_start, new_end)
+        } else {
+            Range::new(new_end, new_start)
+        });
+    }
+
+    Transaction::change(doc, changes.into_iter())
+        .with_selection(Selection::new(ranges.into(), selection.primary_index()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -140,4 +295,42 @@ mod test {
 
         // TODO: account for uncommenting with uneven comment indentation
     }
+
+    #[test]
+    fn test_toggle_block_comments() {
+        let tokens = BlockCommentToken::default();
+        let mut doc = Rope::from("  a + b\n  c\n");
+        // the first line and its indent and line ending, and the `c`
+        let selection = Selection::new(vec![Range::new(0, 8), Range::new(10, 11)].into(), 0);
+
+        // comment
+        let transaction = toggle_block_comments(&doc, &selection, &tokens);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "  /* a + b */\n  /* c */\n");
+        let selection = transaction.selection().unwrap().clone();
+        assert_eq!(selection.ranges(), [Range::new(2, 13), Range::new(16, 23)]);
+
+        // uncomment
+        let transaction = toggle_block_comments(&doc, &selection, &tokens);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "  a + b\n  c\n");
+        let selection = transaction.selection().unwrap().clone();
+        assert_eq!(selection.ranges(), [Range::new(2, 7), Range::new(10, 11)]);
+
+        // comments without margins, and ranges mixed with uncommented ones get commented
+        let mut doc = Rope::from("/*a*/ b");
+        let selection = Selection::new(vec![Range::new(0, 5), Range::new(6, 7)].into(), 0);
+        let transaction = toggle_block_comments(&doc, &selection, &tokens);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "/* /*a*/ */ /* b */");
+
+        let mut doc = Rope::from("<!--a-->");
+        let tokens = BlockCommentToken {
+            start: "<!--".to_string(),
+            end: "-->".to_string(),
+        };
+        let transaction = toggle_block_comments(&doc, &Selection::single(0, 8), &tokens);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "a");
+    }
 }
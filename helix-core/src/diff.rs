@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use crate::{Rope, Transaction};
 
 /// Compares `old` and `new` to generate a [`Transaction`] describing
@@ -49,6 +51,56 @@ pub fn compare_ropes(old: &Rope, new: &Rope) -> Transaction {
     )
 }
 
+/// A group of changed lines between two versions of a text. `before` is the range of lines
+/// in the old version and `after` the range of lines replacing them in the new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub before: Range<usize>,
+    pub after: Range<usize>,
+}
+
+impl Hunk {
+    /// Whether the hunk only adds lines.
+    pub fn is_pure_insertion(&self) -> bool {
+        self.before.is_empty()
+    }
+
+    /// Whether the hunk only removes lines.
+    pub fn is_pure_removal(&self) -> bool {
+        self.after.is_empty()
+    }
+}
+
+/// Compares the lines of `old` and `new`, returning the [`Hunk`]s of lines that changed,
+/// ordered by position.
+pub fn compare_lines(old: &Rope, new: &Rope) -> Vec<Hunk> {
+    let old_converted = old.to_string();
+    let new_converted = new.to_string();
+
+    // A short timeout so the hunks of a changed document don't lag far behind its text, at
+    // the cost of approximating the diff of large or very different texts.
+    let mut config = similar::TextDiff::configure();
+    config.timeout(std::time::Duration::from_millis(200));
+
+    let diff = config.diff_lines(&old_converted, &new_converted);
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for (tag, before, after) in diff.ops().iter().map(|op| op.as_tag_tuple()) {
+        if tag == similar::DiffTag::Equal {
+            continue;
+        }
+        // a deletion directly followed by an insertion is a single modification
+        match hunks.last_mut() {
+            Some(last) if last.before.end == before.start && last.after.end == after.start => {
+                last.before.end = before.end;
+                last.after.end = after.end;
+            }
+            _ => hunks.push(Hunk { before, after }),
+        }
+    }
+    hunks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,4 +113,32 @@ mod tests {
             old == new
         }
     }
+
+    #[test]
+    fn test_compare_lines() {
+        let old = Rope::from("a\nb\nc\nd\ne\n");
+        let new = Rope::from("a\nB\nc\ne\nf\ng\n");
+
+        let hunks = compare_lines(&old, &new);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk {
+                    before: 1..2,
+                    after: 1..2
+                },
+                Hunk {
+                    before: 3..4,
+                    after: 3..3
+                },
+                Hunk {
+                    before: 5..5,
+                    after: 4..6
+                },
+            ]
+        );
+        assert!(hunks[1].is_pure_removal());
+        assert!(hunks[2].is_pure_insertion());
+        assert!(compare_lines(&old, &old).is_empty());
+    }
 }
@@ -0,0 +1,259 @@
+use std::ops::Range;
+
+use tree_sitter::QueryCursor;
+
+use crate::{
+    syntax::{Syntax, TextObjectQuery},
+    Assoc, ChangeSet, RopeSlice,
+};
+
+/// Returns the char ranges of the nodes captured with `@fold` by a folds query that span
+/// more than one line, ordered by start.
+pub fn fold_ranges(syntax: &Syntax, query: &TextObjectQuery, text: RopeSlice) -> Vec<Range<usize>> {
+    let mut cursor = QueryCursor::new();
+    let nodes = match query.capture_nodes("fold", syntax.tree().root_node(), text, &mut cursor) {
+        Some(nodes) => nodes,
+        None => return Vec::new(),
+    };
+
+    let mut ranges: Vec<_> = nodes
+        .filter(|node| node.start_position().row < node.end_position().row)
+        .map(|node| text.byte_to_char(node.start_byte())..text.byte_to_char(node.end_byte()))
+        .collect();
+    ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+    ranges.dedup();
+    ranges
+}
+
+/// The folded ranges of a document in a view. A fold keeps the first line of its range
+/// visible and hides the lines after it, up to the end of the range. Folds may nest.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Folds {
+    /// Char ranges, ordered by start and outermost first.
+    folds: Vec<Range<usize>>,
+}
+
+impl Folds {
+    pub const fn new() -> Self {
+        Self { folds: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.folds
+    }
+
+    /// Folds `range`. Returns false if it is already folded.
+    pub fn add(&mut self, range: Range<usize>) -> bool {
+        let key = |range: &Range<usize>| (range.start, std::cmp::Reverse(range.end));
+        match self.folds.binary_search_by_key(&key(&range), key) {
+            Ok(_) => false,
+            Err(idx) => {
+                self.folds.insert(idx, range);
+                true
+            }
+        }
+    }
+
+    /// Unfolds the outermost fold that contains `line`. Returns false if there is none.
+    pub fn remove_at_line(&mut self, text: RopeSlice, line: usize) -> bool {
+        let idx = self.folds.iter().position(|range| {
+            let (start, end) = Self::lines(text, range);
+            (start..=end).contains(&line)
+        });
+        match idx {
+            Some(idx) => {
+                self.folds.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Unfolds every fold that hides `line`.
+    pub fn reveal(&mut self, text: RopeSlice, line: usize) {
+        self.folds.retain(|range| {
+            let (start, end) = Self::lines(text, range);
+            !(start < line && line <= end)
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Maps every fold through `changes`. `text` is the document after the changes are
+    /// applied. Folds that no longer span more than one line are dropped.
+    pub fn map(&mut self, changes: &ChangeSet, text: RopeSlice) {
+        for range in &mut self.folds {
+            let start = changes.map_pos(range.start, Assoc::After);
+            let end = changes.map_pos(range.end, Assoc::Before);
+            *range = start..end.max(start);
+        }
+        self.folds.retain(|range| {
+            let (start, end) = Self::lines(text, range);
+            start < end
+        });
+        self.folds
+            .sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+        self.folds.dedup();
+    }
+
+    /// The first and the last line of a fold.
+    fn lines(text: RopeSlice, range: &Range<usize>) -> (usize, usize) {
+        let start = text.char_to_line(range.start);
+        // the range is exclusive, a fold ending at the start of a line doesn't include it
+        let end = text.char_to_line(range.end.saturating_sub(1).max(range.start));
+        (start, end)
+    }
+
+    /// The line ranges hidden by the folds, ordered and merged.
+    pub fn hidden_lines(&self, text: RopeSlice) -> Vec<Range<usize>> {
+        let mut hidden: Vec<Range<usize>> = Vec::new();
+        for range in &self.folds {
+            let (start, end) = Self::lines(text, range);
+            let lines = start + 1..end + 1;
+            match hidden.last_mut() {
+                Some(last) if lines.start <= last.end => last.end = last.end.max(lines.end),
+                _ => hidden.push(lines),
+            }
+        }
+        hidden
+    }
+
+    /// The lines of `text` as they are displayed with these folds.
+    pub fn visible_lines(&self, text: RopeSlice) -> VisibleLines {
+        VisibleLines {
+            hidden: self.hidden_lines(text),
+            len_lines: text.len_lines(),
+        }
+    }
+}
+
+/// The lines of a document that are not hidden by [`Folds`]. Used to convert between
+/// lines of the document and rows on screen.
+#[derive(Debug, Clone)]
+pub struct VisibleLines {
+    hidden: Vec<Range<usize>>,
+    len_lines: usize,
+}
+
+impl VisibleLines {
+    /// The hidden range that contains `line`.
+    fn hidden_range(&self, line: usize) -> Option<&Range<usize>> {
+        let idx = self.hidden.partition_point(|range| range.end <= line);
+        self.hidden.get(idx).filter(|range| range.start <= line)
+    }
+
+    pub fn is_hidden(&self, line: usize) -> bool {
+        self.hidden_range(line).is_some()
+    }
+
+    /// Returns `line` if it is visible, or else the first line of the fold that hides it.
+    pub fn visible(&self, line: usize) -> usize {
+        match self.hidden_range(line) {
+            Some(range) => range.start - 1,
+            None => line,
+        }
+    }
+
+    /// The first lines of the folds, which stay visible.
+    pub fn fold_lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.hidden.iter().map(|range| range.start - 1)
+    }
+
+    /// The char ranges of the hidden lines.
+    pub fn hidden_chars(&self, text: RopeSlice) -> Vec<Range<usize>> {
+        self.hidden
+            .iter()
+            .map(|lines| text.line_to_char(lines.start)..text.line_to_char(lines.end))
+            .collect()
+    }
+
+    /// Iterates forward over the visible lines, starting from `line`.
+    pub fn iter_from(&self, line: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut line = line;
+        std::iter::from_fn(move || {
+            if let Some(range) = self.hidden_range(line) {
+                line = range.end;
+            }
+            (line < self.len_lines).then(|| {
+                line += 1;
+                line - 1
+            })
+        })
+    }
+
+    /// Iterates backward over the visible lines, starting from `line`.
+    pub fn iter_back_from(&self, line: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut line = Some(line.min(self.len_lines.saturating_sub(1)));
+        std::iter::from_fn(move || {
+            let current = self.visible(line?);
+            line = current.checked_sub(1);
+            Some(current)
+        })
+    }
+
+    /// The number of visible lines in `from..to`.
+    pub fn distance(&self, from: usize, to: usize) -> usize {
+        let hidden: usize = self
+            .hidden
+            .iter()
+            .map(|range| range.end.min(to).saturating_sub(range.start.max(from)))
+            .sum();
+        to.saturating_sub(from) - hidden
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rope, Transaction};
+
+    #[test]
+    fn test_visible_lines() {
+        let text = Rope::from("0\n1\n2\n3\n4\n5\n6\n7\n");
+        let text = text.slice(..);
+        let mut folds = Folds::default();
+        // lines 1..=3, 2..=3 and 5..=6
+        folds.add(text.line_to_char(1)..text.line_to_char(4));
+        folds.add(text.line_to_char(2)..text.line_to_char(3) + 1);
+        folds.add(text.line_to_char(5)..text.line_to_char(6) + 1);
+
+        let lines = folds.visible_lines(text);
+        assert_eq!(folds.hidden_lines(text), [2..4, 6..7]);
+        assert_eq!(lines.iter_from(0).collect::<Vec<_>>(), [0, 1, 4, 5, 7, 8]);
+        assert_eq!(lines.iter_from(3).collect::<Vec<_>>(), [4, 5, 7, 8]);
+        assert_eq!(lines.iter_back_from(7).collect::<Vec<_>>(), [7, 5, 4, 1, 0]);
+        assert_eq!(lines.iter_back_from(3).collect::<Vec<_>>(), [1, 0]);
+        assert_eq!(lines.visible(3), 1);
+        assert!(lines.is_hidden(6) && !lines.is_hidden(5));
+        assert_eq!(lines.distance(0, 7), 4);
+        assert_eq!(lines.distance(3, 5), 1);
+
+        assert!(folds.remove_at_line(text, 2));
+        assert_eq!(folds.hidden_lines(text), [3..4, 6..7]);
+        folds.reveal(text, 6);
+        assert_eq!(folds.hidden_lines(text), vec![3..4]);
+    }
+
+    #[test]
+    fn test_map() {
+        let doc = Rope::from("a\nb\nc\nd\n");
+        let mut folds = Folds::default();
+        folds.add(0..5);
+        folds.add(4..7);
+
+        // join the lines "c" and "d"
+        let transaction = Transaction::change(&doc, [(5, 6, None)].into_iter());
+        let mut new_doc = doc.clone();
+        transaction.apply(&mut new_doc);
+        folds.map(transaction.changes(), new_doc.slice(..));
+
+        assert_eq!(new_doc, "a\nb\ncd\n");
+        assert_eq!(folds.ranges(), vec![0..5]);
+    }
+}
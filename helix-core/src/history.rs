@@ -1,7 +1,10 @@
-use crate::{Assoc, ChangeSet, Range, Rope, State, Transaction};
+use crate::{Assoc, ChangeSet, Operation, Range, Rope, RopeSlice, Selection, State, Transaction};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 /// Stores the history of changes to a buffer.
@@ -32,33 +35,149 @@ use std::time::{Duration, Instant};
 /// jump the given number of revisions in the vector.
 ///
 /// Limitations:
-///  * Changes in selections currently don't commit history changes. The selection
-///    will only be updated to the state after a commited buffer change.
-///  * The vector of history revisions is currently unbounded. This might
+///  * Changes in selections don't commit history changes. The selection will only
+///    be updated to the state after a commited buffer change. Selection changes are
+///    instead recorded separately, per view, in a [SelectionHistory].
+///  * The vector of history revisions is unbounded by default. This might
 ///    cause the memory consumption to grow significantly large during long
-///    editing sessions.
+///    editing sessions. A [Limits] can be set to prune the oldest revisions:
+///    the root advances towards the current revision, dropping every revision
+///    that is no longer a descendant of it.
 ///  * Because delete transactions currently don't store the text that they
 ///    delete, we also store an inversion of the transaction.
 ///
+/// The history can be persisted between sessions with [History::serialize]. It is
+/// stored together with a hash of the document text at the current revision, and
+/// [History::deserialize] will only restore it if the text still matches.
+///
 /// Using time to navigate the history: <https://github.com/helix-editor/helix/pull/194>
 #[derive(Debug)]
 pub struct History {
     revisions: Vec<Revision>,
     current: usize,
+    limits: Limits,
+}
+
+/// Bounds on the size of a [History]. Once a bound is exceeded, the oldest revisions
+/// are pruned.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Limits {
+    /// Maximum number of revisions to keep, not counting the root.
+    pub max_revisions: Option<usize>,
+    /// Approximate maximum number of bytes used by the revisions.
+    pub max_bytes: Option<usize>,
 }
 
 /// A single point in history. See [History] for more information.
-#[derive(Debug)]
-struct Revision {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Revision {
     parent: usize,
     last_child: Option<NonZeroUsize>,
     transaction: Transaction,
     // We need an inversion for undos because delete transactions don't store
     // the deleted text.
     inversion: Transaction,
+    #[serde(with = "timestamp")]
     timestamp: Instant,
 }
 
+impl Revision {
+    /// The revision this one was committed on top of. The root is its own parent.
+    pub fn parent(&self) -> usize {
+        self.parent
+    }
+
+    /// The child that is restored when redoing from this revision.
+    pub fn last_child(&self) -> Option<usize> {
+        self.last_child.map(NonZeroUsize::get)
+    }
+
+    /// The transaction that transitions the parent revision to this one.
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+
+    /// Approximate memory used by the revision, in bytes.
+    fn size(&self) -> usize {
+        fn transaction_size(transaction: &Transaction) -> usize {
+            let changes = transaction.changes().changes();
+            let inserted: usize = changes
+                .iter()
+                .map(|change| match change {
+                    Operation::Insert(text) => text.len(),
+                    _ => 0,
+                })
+                .sum();
+            let selection = transaction.selection().map_or(0, |selection| {
+                selection.len() * std::mem::size_of::<Range>()
+            });
+            std::mem::size_of_val(changes) + inserted + selection
+        }
+
+        std::mem::size_of::<Self>()
+            + transaction_size(&self.transaction)
+            + transaction_size(&self.inversion)
+    }
+}
+
+/// The on-disk representation of a [History].
+#[derive(Serialize, Deserialize)]
+struct PersistedHistory<R> {
+    /// Hash of the document text at the `current` revision.
+    text_hash: u64,
+    current: usize,
+    revisions: R,
+}
+
+/// [Instant]s are opaque and only meaningful within a single process, so they're
+/// persisted as wall-clock time instead.
+mod timestamp {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::{Instant, SystemTime};
+
+    pub fn serialize<S: Serializer>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+        let age = Instant::now().saturating_duration_since(*instant);
+        let time = SystemTime::now()
+            .checked_sub(age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        time.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+        let time = SystemTime::deserialize(deserializer)?;
+        let age = SystemTime::now().duration_since(time).unwrap_or_default();
+        let now = Instant::now();
+        Ok(now.checked_sub(age).unwrap_or(now))
+    }
+}
+
+/// Returns the file the history for the document at `path` is persisted to.
+pub fn cache_path(path: &Path) -> PathBuf {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    path.hash(&mut hasher);
+    crate::cache_dir()
+        .join("history")
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+/// Hashes the contents of `text`, independently of how the rope is chunked.
+///
+/// The hash is not guaranteed to be stable across compiler versions, which at
+/// worst means a persisted history is discarded.
+fn hash_text(text: &Rope) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for chunk in text.chunks() {
+        hasher.write(chunk.as_bytes());
+    }
+    hasher.write_usize(text.len_bytes());
+    hasher.finish()
+}
+
 impl Default for History {
     fn default() -> Self {
         // Add a dummy root revision with empty transaction
@@ -71,6 +190,7 @@ impl Default for History {
                 timestamp: Instant::now(),
             }],
             current: 0,
+            limits: Limits::default(),
         }
     }
 }
@@ -103,11 +223,197 @@ impl History {
         self.current = new_current;
     }
 
+    /// Serializes the revision tree. `text` has to be the document text at the
+    /// current revision.
+    pub fn serialize(&self, text: &Rope) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&PersistedHistory {
+            text_hash: hash_text(text),
+            current: self.current,
+            revisions: &self.revisions,
+        })
+    }
+
+    /// Restores a history written by [History::serialize]. Returns `None` if the
+    /// data is malformed or was saved for a text other than `text`.
+    pub fn deserialize(bytes: &[u8], text: &Rope) -> Option<Self> {
+        let persisted: PersistedHistory<Vec<Revision>> = match serde_json::from_slice(bytes) {
+            Ok(persisted) => persisted,
+            Err(err) => {
+                log::warn!("failed to parse persisted history: {}", err);
+                return None;
+            }
+        };
+
+        if persisted.text_hash != hash_text(text) {
+            return None;
+        }
+
+        let PersistedHistory {
+            current,
+            mut revisions,
+            ..
+        } = persisted;
+        let len = revisions.len();
+        let valid = current < len
+            && revisions.iter().all(|revision| {
+                revision.parent < len
+                    && !matches!(revision.last_child, Some(child) if child.get() >= len)
+            });
+        if !valid {
+            log::warn!("discarding malformed persisted history");
+            return None;
+        }
+
+        // Timestamps that couldn't be represented as an `Instant` are clamped, so
+        // keep them ordered for the binary search in `jump_instant`.
+        for i in 1..len {
+            if revisions[i].timestamp < revisions[i - 1].timestamp {
+                revisions[i].timestamp = revisions[i - 1].timestamp;
+            }
+        }
+
+        Some(Self {
+            revisions,
+            current,
+            limits: Limits::default(),
+        })
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Sets the bounds on the size of the history. See [History::prune].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Drops the oldest revisions until the history fits within its [Limits].
+    ///
+    /// The root is moved along the path towards the current revision, so the current
+    /// revision and everything that can be redone from it stays reachable. Revisions
+    /// keep their relative order, which keeps timestamp navigation working.
+    ///
+    /// If anything was dropped, returns the new index of every revision indexed by
+    /// its old one, or `None` for revisions that were pruned.
+    pub fn prune(&mut self) -> Option<Vec<Option<usize>>> {
+        let Limits {
+            max_revisions,
+            max_bytes,
+        } = self.limits;
+        if max_revisions.is_none() && max_bytes.is_none() {
+            return None;
+        }
+
+        let len = self.revisions.len();
+
+        // Accumulate the size of every subtree. Children always come after their
+        // parent, so a single backward pass is enough.
+        let mut counts = vec![1; len];
+        let mut sizes: Vec<usize> = self.revisions.iter().map(Revision::size).collect();
+        for i in (1..len).rev() {
+            let parent = self.revisions[i].parent;
+            counts[parent] += counts[i];
+            sizes[parent] += sizes[i];
+        }
+
+        // The root doesn't count towards the limits since its transactions are
+        // never applied.
+        let fits = |i: usize| {
+            counts[i] - 1 <= max_revisions.unwrap_or(usize::MAX)
+                && sizes[i] - self.revisions[i].size() <= max_bytes.unwrap_or(usize::MAX)
+        };
+
+        if fits(0) {
+            return None;
+        }
+
+        let root = self
+            .path_up(self.current, 0)
+            .into_iter()
+            .rev()
+            .find(|&i| fits(i))
+            .unwrap_or(self.current);
+
+        // Keep the subtree of the new root.
+        let mut keep = vec![false; len];
+        keep[root] = true;
+        for i in root + 1..len {
+            keep[i] = keep[self.revisions[i].parent];
+        }
+
+        let mut mapping = vec![None; len];
+        let mut next = 0;
+        for (i, kept) in keep.iter().enumerate() {
+            if *kept {
+                mapping[i] = Some(next);
+                next += 1;
+            }
+        }
+
+        let revisions = std::mem::take(&mut self.revisions);
+        self.revisions = revisions
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, kept)| *kept)
+            .map(|(mut revision, _)| {
+                revision.parent = mapping[revision.parent].unwrap_or(0);
+                revision.last_child = revision
+                    .last_child
+                    .and_then(|child| NonZeroUsize::new(mapping[child.get()]?));
+                revision
+            })
+            .collect();
+
+        // The new root becomes the starting state of the buffer.
+        let root = &mut self.revisions[0];
+        root.parent = 0;
+        root.transaction = Transaction::from(ChangeSet::new(&Rope::new()));
+        root.inversion = Transaction::from(ChangeSet::new(&Rope::new()));
+
+        self.current = mapping[self.current].expect("current revision is always kept");
+
+        Some(mapping)
+    }
+
     #[inline]
     pub fn current_revision(&self) -> usize {
         self.current
     }
 
+    /// All revisions, indexed by revision number. The root is always the first one.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    /// Reconstructs the text at every revision, indexed by revision number. `text`
+    /// has to be the text at the current revision.
+    pub fn texts(&self, text: &Rope) -> Vec<Rope> {
+        let mut texts = vec![None; self.revisions.len()];
+        texts[self.current] = Some(text.clone());
+
+        // Walk up to the root by undoing...
+        let mut n = self.current;
+        while n != 0 {
+            let mut text = texts[n].clone().unwrap();
+            self.revisions[n].inversion.apply(&mut text);
+            n = self.revisions[n].parent;
+            texts[n] = Some(text);
+        }
+
+        // ...and fill in the remaining revisions by redoing. Parents always come before
+        // their children, so their text is already known.
+        for i in 1..self.revisions.len() {
+            if texts[i].is_none() {
+                let mut text = texts[self.revisions[i].parent].clone().unwrap();
+                self.revisions[i].transaction.apply(&mut text);
+                texts[i] = Some(text);
+            }
+        }
+
+        texts.into_iter().map(Option::unwrap).collect()
+    }
+
     #[inline]
     pub const fn at_root(&self) -> bool {
         self.current == 0
@@ -189,7 +495,7 @@ impl History {
     }
 
     /// Create a [`Transaction`] that will jump to a specific revision in the history.
-    fn jump_to(&mut self, to: usize) -> Vec<Transaction> {
+    pub fn jump_to(&mut self, to: usize) -> Vec<Transaction> {
         let lca = self.lowest_common_ancestor(self.current, to);
         let up = self.path_up(self.current, lca);
         let down = self.path_up(to, lca);
@@ -363,6 +669,79 @@ impl std::str::FromStr for UndoKind {
     }
 }
 
+/// A jumplist of the selections a view went through. The selection of the view is recorded
+/// once per command, so that the intermediate selections of a command or of a prompt aren't
+/// pushed to the list, which can then be navigated like the jumplist of a view. Recording a
+/// selection after going back discards the selections that were ahead of it.
+///
+/// The recorded selections aren't part of the [History]: undoing a change doesn't
+/// affect them, but they are mapped through every change applied to the document.
+#[derive(Debug, Clone, Default)]
+pub struct SelectionHistory {
+    selections: Vec<Selection>,
+    current: usize,
+    /// The selection of the view when it was last recorded, pushed once the view leaves it.
+    recorded: Option<Selection>,
+}
+
+impl SelectionHistory {
+    /// Maximum number of selections kept, the oldest ones are dropped first.
+    pub const MAX_LEN: usize = 100;
+
+    /// Records `selection`, the current selection of the view. The selection recorded before
+    /// it is pushed to the list if the view left it since.
+    pub fn record(&mut self, selection: &Selection) {
+        if self.recorded.as_ref() == Some(selection) {
+            return;
+        }
+        if let Some(recorded) = self.recorded.replace(selection.clone()) {
+            self.push(recorded);
+        }
+    }
+
+    fn push(&mut self, selection: Selection) {
+        self.selections.truncate(self.current);
+        if self.selections.last() != Some(&selection) {
+            self.selections.push(selection);
+        }
+        if self.selections.len() > Self::MAX_LEN {
+            self.selections.remove(0);
+        }
+        self.current = self.selections.len();
+    }
+
+    /// Steps `count` selections back. `selection` is the current selection of the view,
+    /// which is recorded so that [SelectionHistory::forward] can return to it.
+    pub fn backward(&mut self, selection: &Selection, count: usize) -> Option<&Selection> {
+        self.record(selection);
+        if self.current == self.selections.len() {
+            self.push(selection.clone());
+            self.current -= 1;
+        }
+        self.current = self.current.checked_sub(count)?;
+        let selection = self.selections.get(self.current)?;
+        self.recorded = Some(selection.clone());
+        Some(selection)
+    }
+
+    /// Steps `count` selections forward.
+    pub fn forward(&mut self, count: usize) -> Option<&Selection> {
+        let current = self.current + count;
+        let selection = self.selections.get(current)?;
+        self.current = current;
+        self.recorded = Some(selection.clone());
+        Some(selection)
+    }
+
+    /// Maps the recorded selections through `changes`, so that they remain valid once
+    /// the changes are applied.
+    pub fn map(&mut self, changes: &ChangeSet, text: RopeSlice) {
+        for selection in self.selections.iter_mut().chain(&mut self.recorded) {
+            *selection = selection.clone().map(changes).ensure_invariants(text);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -518,6 +897,151 @@ mod test {
         assert_eq!("a\n", state.doc);
     }
 
+    #[test]
+    fn test_texts() {
+        let mut history = History::default();
+        let mut state = State::new(Rope::from("a"));
+
+        let commit = |history: &mut History, state: &mut State, change| {
+            let txn = Transaction::change(&state.doc, vec![change].into_iter());
+            history.commit_revision(&txn, state);
+            txn.apply(&mut state.doc);
+        };
+
+        commit(&mut history, &mut state, (1, 1, Some("b".into())));
+        commit(&mut history, &mut state, (2, 2, Some("c".into())));
+        history.undo().unwrap().clone().apply(&mut state.doc);
+        commit(&mut history, &mut state, (2, 2, Some("d".into())));
+        history.undo().unwrap().clone().apply(&mut state.doc);
+        assert_eq!("ab", state.doc);
+
+        let texts = history.texts(&state.doc);
+        assert_eq!(texts, ["a", "ab", "abc", "abd"]);
+
+        for txn in history.jump_to(2) {
+            txn.apply(&mut state.doc);
+        }
+        assert_eq!("abc", state.doc);
+        assert_eq!(history.revisions()[3].parent(), 1);
+    }
+
+    #[test]
+    fn test_selection_history() {
+        let mut history = SelectionHistory::default();
+        let (a, b, c) = (
+            Selection::point(0),
+            Selection::point(1),
+            Selection::point(2),
+        );
+
+        history.record(&a);
+        history.record(&b);
+        // selections are only pushed once the view leaves them
+        history.record(&b);
+        assert_eq!(history.backward(&c, 1), Some(&b));
+        assert_eq!(history.backward(&b, 1), Some(&a));
+        assert_eq!(history.backward(&a, 1), None);
+        assert_eq!(history.forward(2), Some(&c));
+        assert_eq!(history.forward(1), None);
+
+        // recording a selection discards the ones ahead
+        history.backward(&c, 2);
+        history.record(&b);
+        assert_eq!(history.forward(1), None);
+        assert_eq!(history.backward(&b, 1), Some(&a));
+
+        // selections are mapped through changes
+        let text = Rope::from("hello");
+        let transaction = Transaction::change(&text, vec![(0, 0, Some("xx".into()))].into_iter());
+        history.map(transaction.changes(), Rope::from("xxhello").slice(..));
+        assert_eq!(
+            history
+                .forward(1)
+                .map(|selection| selection.primary().from()),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut history = History::default();
+        history.set_limits(Limits {
+            max_revisions: Some(2),
+            max_bytes: None,
+        });
+        let mut state = State::new(Rope::from("a\n"));
+
+        let t0 = Instant::now();
+        let t = |n| t0.checked_add(Duration::from_secs(n)).unwrap();
+
+        fn commit(
+            history: &mut History,
+            state: &mut State,
+            change: crate::transaction::Change,
+            instant: Instant,
+        ) {
+            let txn = Transaction::change(&state.doc, vec![change].into_iter());
+            history.commit_revision_at_timestamp(&txn, state, instant);
+            history.prune();
+            txn.apply(&mut state.doc);
+        }
+
+        commit(&mut history, &mut state, (1, 1, Some(" b".into())), t(0));
+        commit(&mut history, &mut state, (3, 3, Some(" c".into())), t(10));
+        assert_eq!("a b c\n", state.doc);
+
+        let txn = history.undo().unwrap().clone();
+        txn.apply(&mut state.doc);
+        assert_eq!("a b\n", state.doc);
+
+        // branching off "a b" makes it the new root, so " b" can no longer be undone
+        commit(&mut history, &mut state, (3, 3, Some(" d".into())), t(20));
+        assert_eq!("a b d\n", state.doc);
+        assert_eq!(history.revisions.len(), 3);
+
+        // the " c" branch isn't a descendant of the new root " d" and gets dropped
+        commit(&mut history, &mut state, (5, 5, Some(" e".into())), t(30));
+        assert_eq!("a b d e\n", state.doc);
+        assert_eq!(history.revisions.len(), 2);
+        assert_eq!(history.current_revision(), 1);
+
+        for txn in history.earlier(UndoKind::Steps(10)) {
+            txn.apply(&mut state.doc);
+        }
+        assert_eq!("a b d\n", state.doc);
+        assert!(history.at_root());
+
+        for txn in history.later(UndoKind::TimePeriod(Duration::from_secs(15))) {
+            txn.apply(&mut state.doc);
+        }
+        assert_eq!("a b d e\n", state.doc);
+    }
+
+    #[test]
+    fn test_persist() {
+        let mut history = History::default();
+        let mut state = State::new(Rope::from("hello"));
+
+        let transaction =
+            Transaction::change(&state.doc, vec![(5, 5, Some(" world!".into()))].into_iter());
+        history.commit_revision(&transaction, &state);
+        transaction.apply(&mut state.doc);
+
+        let bytes = history.serialize(&state.doc).unwrap();
+
+        // the text changed since the history was saved
+        assert!(History::deserialize(&bytes, &Rope::from("hello")).is_none());
+        assert!(History::deserialize(b"garbage", &state.doc).is_none());
+
+        let mut history = History::deserialize(&bytes, &state.doc).unwrap();
+        assert_eq!(history.current_revision(), 1);
+
+        history.undo().unwrap().apply(&mut state.doc);
+        assert_eq!("hello", state.doc);
+        history.redo().unwrap().apply(&mut state.doc);
+        assert_eq!("hello world!", state.doc);
+    }
+
     #[test]
     fn test_parse_undo_kind() {
         use UndoKind::*;
@@ -0,0 +1,150 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ropey::RopeSlice;
+
+use std::borrow::Cow;
+
+use super::Increment;
+use crate::{Range, Tendril};
+
+static COLOR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"#[[:xdigit:]]+").unwrap());
+
+/// Increments a channel of a hex color such as `#ff8000` or `#f80`, including the alpha channel
+/// of `#ff8000ff` and `#f80f`. On the `#`, all the color channels are incremented. Short colors
+/// made only of decimal digits, such as `#123`, are left to the number incrementor, as they are
+/// more likely issue references.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ColorIncrementor {
+    range: Range,
+    channels: Vec<u8>,
+    /// The width of a channel, one or two hex digits.
+    width: usize,
+    /// The channel to increment, or `None` for every color channel.
+    channel: Option<usize>,
+    uppercase: bool,
+}
+
+impl ColorIncrementor {
+    pub fn from_range(text: RopeSlice, range: Range) -> Option<ColorIncrementor> {
+        let line = text.char_to_line(range.from());
+        let line_start = text.line_to_char(line);
+        let line: Cow<str> = text.line(line).into();
+
+        // the cursor covers at least one char
+        let cursor_from = range.from() - line_start;
+        let cursor_to = (range.to() - line_start).max(cursor_from + 1);
+
+        COLOR_REGEX.find_iter(&line).find_map(|color| {
+            let start = line[..color.start()].chars().count();
+            let end = start + color.as_str().len();
+            if start > cursor_from || end < cursor_to {
+                return None;
+            }
+            if matches!(line[color.end()..].chars().next(), Some(ch) if ch.is_alphanumeric() || ch == '_')
+            {
+                return None;
+            }
+
+            let digits = &color.as_str()[1..];
+            let width = match digits.len() {
+                3 | 4 if digits.chars().any(|ch| ch.is_ascii_alphabetic()) => 1,
+                6 | 8 => 2,
+                _ => return None,
+            };
+            let channels = (0..digits.len())
+                .step_by(width)
+                .map(|i| u8::from_str_radix(&digits[i..i + width], 16).ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            let channel = if cursor_from == start {
+                if cursor_to > start + 1 {
+                    return None;
+                }
+                None
+            } else {
+                let channel = (cursor_from - start - 1) / width;
+                if (cursor_to - start - 2) / width != channel {
+                    return None;
+                }
+                Some(channel)
+            };
+
+            Some(ColorIncrementor {
+                range: Range::new(line_start + start, line_start + end),
+                channels,
+                width,
+                channel,
+                uppercase: digits.chars().any(|ch| ch.is_ascii_uppercase()),
+            })
+        })
+    }
+}
+
+impl Increment for ColorIncrementor {
+    fn increment(&self, amount: i64) -> (Range, Tendril) {
+        let max = if self.width == 1 { 0xf } else { 0xff };
+        let mut channels = self.channels.clone();
+        let to_change = match self.channel {
+            Some(channel) => channel..channel + 1,
+            // the alpha channel isn't a color
+            None => 0..3,
+        };
+        for channel in &mut channels[to_change] {
+            *channel = (*channel as i64 + amount).clamp(0, max) as u8;
+        }
+
+        let mut color = String::from("#");
+        for channel in channels {
+            let digits = if self.uppercase {
+                format!("{:01$X}", channel, self.width)
+            } else {
+                format!("{:01$x}", channel, self.width)
+            };
+            color.push_str(&digits);
+        }
+        (self.range, color.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    fn increment(text: &str, pos: usize, amount: i64) -> Option<String> {
+        let rope = Rope::from_str(text);
+        let incrementor = ColorIncrementor::from_range(rope.slice(..), Range::point(pos))?;
+        let (range, new_text) = incrementor.increment(amount);
+
+        let mut rope = rope;
+        rope.remove(range.from()..range.to());
+        rope.insert(range.from(), &new_text);
+        Some(rope.to_string())
+    }
+
+    #[test]
+    fn test_increment_colors() {
+        let color = "color: #ff8000;";
+        assert_eq!(increment(color, 8, 1).as_deref(), Some("color: #ff8000;"));
+        assert_eq!(increment(color, 10, 1).as_deref(), Some("color: #ff8100;"));
+        assert_eq!(increment(color, 13, -1).as_deref(), Some("color: #ff8000;"));
+        assert_eq!(increment(color, 7, 16).as_deref(), Some("color: #ff9010;"));
+        assert_eq!(increment("#F80", 2, 1).as_deref(), Some("#F90"));
+        assert_eq!(increment("#f80f", 4, -1).as_deref(), Some("#f80e"));
+    }
+
+    #[test]
+    fn test_invalid_colors() {
+        assert_eq!(increment("#ff800", 1, 1), None);
+        assert_eq!(increment("#ff8000g", 1, 1), None);
+        assert_eq!(increment("ff8000", 1, 1), None);
+    }
+
+    #[test]
+    fn test_issue_references() {
+        assert_eq!(increment("fixes #123", 7, 1), None);
+        assert_eq!(increment("#42", 1, 1), None);
+        assert_eq!(increment("#1234", 1, 1), None);
+        assert_eq!(increment("#123456", 1, 1).as_deref(), Some("#133456"));
+    }
+}
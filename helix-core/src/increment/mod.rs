@@ -1,5 +1,8 @@
+pub mod color;
 pub mod date_time;
 pub mod number;
+pub mod version;
+pub mod word;
 
 use crate::{Range, Tendril};
 
@@ -171,6 +171,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_issue_reference_at_point() {
+        let rope = Rope::from_str("fixes #123");
+        let range = Range::point(7);
+        assert_eq!(
+            NumberIncrementor::from_range(rope.slice(..), range),
+            Some(NumberIncrementor {
+                range: Range::new(7, 10),
+                value: 123,
+                radix: 10,
+                text: rope.slice(..),
+            })
+        );
+    }
+
     #[test]
     fn test_uppercase_hexadecimal_at_point() {
         let rope = Rope::from_str("Test text 0x123ABCDEF more text.");
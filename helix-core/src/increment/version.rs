@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ropey::RopeSlice;
+
+use std::borrow::Cow;
+
+use super::Increment;
+use crate::{Range, Tendril};
+
+static VERSION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)\.(\d+)\.(\d+)").unwrap());
+
+/// Increments a component of a semantic version such as `1.2.3`. The components after it are
+/// reset to zero, and any pre-release or build metadata is kept.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VersionIncrementor {
+    range: Range,
+    components: [u64; 3],
+    /// The index of the component to increment.
+    component: usize,
+}
+
+impl VersionIncrementor {
+    pub fn from_range(text: RopeSlice, range: Range) -> Option<VersionIncrementor> {
+        let line = text.char_to_line(range.from());
+        let line_start = text.line_to_char(line);
+        let line: Cow<str> = text.line(line).into();
+
+        // the cursor covers at least one char
+        let cursor_from = range.from() - line_start;
+        let cursor_to = (range.to() - line_start).max(cursor_from + 1);
+
+        VERSION_REGEX.captures_iter(&line).find_map(|captures| {
+            let version = captures.get(0)?;
+            let start = line[..version.start()].chars().count();
+            let end = start + version.as_str().chars().count();
+            if start > cursor_from || end < cursor_to {
+                return None;
+            }
+
+            // skip parts of longer dotted numbers, such as IP addresses
+            let before = line[..version.start()].chars().next_back();
+            let mut after = line[version.end()..].chars();
+            if matches!(before, Some(ch) if ch.is_ascii_digit() || ch == '.')
+                || matches!((after.next(), after.next()), (Some('.'), Some(ch)) if ch.is_ascii_digit())
+            {
+                return None;
+            }
+
+            let mut components = [0; 3];
+            let mut component = None;
+            for (i, value) in components.iter_mut().enumerate() {
+                let capture = captures.get(i + 1)?;
+                *value = capture.as_str().parse().ok()?;
+
+                let capture_start = line[..capture.start()].chars().count();
+                let capture_end = capture_start + capture.as_str().len();
+                if capture_start <= cursor_from && cursor_to <= capture_end {
+                    component = Some(i);
+                }
+            }
+
+            Some(VersionIncrementor {
+                range: Range::new(line_start + start, line_start + end),
+                components,
+                // on a dot, increment the component before it
+                component: component.or_else(|| {
+                    (0..3).rev().find(|&i| {
+                        let capture = captures.get(i + 1).unwrap();
+                        line[..capture.start()].chars().count() <= cursor_from
+                    })
+                })?,
+            })
+        })
+    }
+}
+
+impl Increment for VersionIncrementor {
+    fn increment(&self, amount: i64) -> (Range, Tendril) {
+        let mut components = self.components;
+        let component = &mut components[self.component];
+        *component = if amount.is_negative() {
+            component.saturating_sub(amount.unsigned_abs())
+        } else {
+            component.saturating_add(amount as u64)
+        };
+        for component in &mut components[self.component + 1..] {
+            *component = 0;
+        }
+
+        let [major, minor, patch] = components;
+        (self.range, format!("{}.{}.{}", major, minor, patch).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    fn increment(text: &str, pos: usize, amount: i64) -> Option<String> {
+        let rope = Rope::from_str(text);
+        let incrementor = VersionIncrementor::from_range(rope.slice(..), Range::point(pos))?;
+        let (range, new_text) = incrementor.increment(amount);
+
+        let mut rope = rope;
+        rope.remove(range.from()..range.to());
+        rope.insert(range.from(), &new_text);
+        Some(rope.to_string())
+    }
+
+    #[test]
+    fn test_increment_versions() {
+        let version = "version = \"1.12.3\"";
+        assert_eq!(
+            increment(version, 11, 1).as_deref(),
+            Some("version = \"2.0.0\"")
+        );
+        assert_eq!(
+            increment(version, 14, 1).as_deref(),
+            Some("version = \"1.13.0\"")
+        );
+        assert_eq!(
+            increment(version, 16, -1).as_deref(),
+            Some("version = \"1.12.2\"")
+        );
+        // on a dot
+        assert_eq!(
+            increment(version, 15, 2).as_deref(),
+            Some("version = \"1.14.0\"")
+        );
+        // pre-release and build metadata are kept
+        assert_eq!(
+            increment("v0.1.0-alpha+001", 5, 1).as_deref(),
+            Some("v0.1.1-alpha+001")
+        );
+        // components don't go below zero
+        assert_eq!(increment("1.0.0", 2, -1).as_deref(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        assert_eq!(increment("1.2", 0, 1), None);
+        assert_eq!(increment("192.168.1.1", 0, 1), None);
+        assert_eq!(increment("192.168.1.1", 10, 1), None);
+        assert_eq!(increment("a 1.2.3", 0, 1), None);
+    }
+}
@@ -0,0 +1,242 @@
+use ropey::RopeSlice;
+
+use std::borrow::Cow;
+
+use super::Increment;
+use crate::{Range, Tendril};
+
+/// Groups of words cycled in every language. Language specific groups are configured with
+/// `increment-groups` in `languages.toml`.
+pub const DEFAULT_GROUPS: &[&[&str]] = &[
+    &["true", "false"],
+    &["yes", "no"],
+    &["on", "off"],
+    &[
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ],
+    &["mon", "tue", "wed", "thu", "fri", "sat", "sun"],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Lower,
+    Upper,
+    Capitalized,
+    /// Any other capitalization, the words are inserted as configured.
+    Verbatim,
+}
+
+impl Case {
+    fn of(word: &str) -> Self {
+        let mut cased = word.chars().filter(|ch| ch.is_alphabetic());
+        let first = match cased.next() {
+            Some(first) => first,
+            None => return Case::Verbatim,
+        };
+        let rest: Vec<_> = cased.collect();
+
+        if first.is_lowercase() && rest.iter().all(|ch| ch.is_lowercase()) {
+            Case::Lower
+        } else if first.is_uppercase() && rest.iter().all(|ch| ch.is_uppercase()) {
+            // a single capital letter is capitalized rather than uppercase
+            if rest.is_empty() {
+                Case::Capitalized
+            } else {
+                Case::Upper
+            }
+        } else if first.is_uppercase() && rest.iter().all(|ch| ch.is_lowercase()) {
+            Case::Capitalized
+        } else {
+            Case::Verbatim
+        }
+    }
+
+    fn apply(self, word: &str) -> String {
+        match self {
+            Case::Lower => word.to_lowercase(),
+            Case::Upper => word.to_uppercase(),
+            Case::Capitalized => {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| {
+                        first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                    })
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            }
+            Case::Verbatim => word.to_string(),
+        }
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Cycles through a group of words, such as `true` and `false`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WordIncrementor<'a> {
+    range: Range,
+    words: &'a [String],
+    index: usize,
+    case: Case,
+}
+
+impl<'a> WordIncrementor<'a> {
+    /// Finds the longest word of the `groups` under the range, ignoring its capitalization.
+    /// Earlier groups take precedence over later ones.
+    pub fn from_range(
+        text: RopeSlice,
+        range: Range,
+        groups: &[&'a [String]],
+    ) -> Option<WordIncrementor<'a>> {
+        let line = text.char_to_line(range.from());
+        let line_start = text.line_to_char(line);
+        let line: Cow<str> = text.line(line).into();
+        let lowercase_line = line.to_ascii_lowercase();
+
+        // the cursor covers at least one char
+        let cursor_from = range.from() - line_start;
+        let cursor_to = (range.to() - line_start).max(cursor_from + 1);
+
+        let mut best: Option<WordIncrementor> = None;
+        for words in groups {
+            for (index, word) in words.iter().enumerate() {
+                let lowercase_word = word.to_ascii_lowercase();
+                if lowercase_word.is_empty() {
+                    continue;
+                }
+                let word_len = word.chars().count();
+
+                for (byte_start, _) in lowercase_line.match_indices(&lowercase_word) {
+                    let start = line[..byte_start].chars().count();
+                    let end = start + word_len;
+                    if start > cursor_from || end < cursor_to {
+                        continue;
+                    }
+
+                    // don't match within another word
+                    let before = line[..byte_start].chars().next_back();
+                    let after = line[byte_start + word.len()..].chars().next();
+                    let starts_word = matches!(word.chars().next(), Some(ch) if is_word_char(ch));
+                    let ends_word =
+                        matches!(word.chars().next_back(), Some(ch) if is_word_char(ch));
+                    if (starts_word && matches!(before, Some(ch) if is_word_char(ch)))
+                        || (ends_word && matches!(after, Some(ch) if is_word_char(ch)))
+                    {
+                        continue;
+                    }
+
+                    let longer = match &best {
+                        Some(best) => word_len > best.range.len(),
+                        None => true,
+                    };
+                    if longer {
+                        best = Some(WordIncrementor {
+                            range: Range::new(line_start + start, line_start + end),
+                            words,
+                            index,
+                            case: Case::of(&line[byte_start..byte_start + word.len()]),
+                        });
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+impl<'a> Increment for WordIncrementor<'a> {
+    fn increment(&self, amount: i64) -> (Range, Tendril) {
+        let len = self.words.len() as i64;
+        let index = (self.index as i64 + amount).rem_euclid(len) as usize;
+
+        (self.range, self.case.apply(&self.words[index]).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    fn groups(groups: &[&[&str]]) -> Vec<Vec<String>> {
+        groups
+            .iter()
+            .map(|words| words.iter().map(|word| word.to_string()).collect())
+            .collect()
+    }
+
+    fn increment(text: &str, pos: usize, groups: &[Vec<String>], amount: i64) -> Option<String> {
+        let rope = Rope::from_str(text);
+        let groups: Vec<_> = groups.iter().map(Vec::as_slice).collect();
+        let incrementor = WordIncrementor::from_range(rope.slice(..), Range::point(pos), &groups)?;
+        let (range, new_text) = incrementor.increment(amount);
+
+        let mut rope = rope;
+        rope.remove(range.from()..range.to());
+        rope.insert(range.from(), &new_text);
+        Some(rope.to_string())
+    }
+
+    #[test]
+    fn test_cycle_words() {
+        let defaults = groups(DEFAULT_GROUPS);
+        assert_eq!(
+            increment("let a = true;", 9, &defaults, 1).as_deref(),
+            Some("let a = false;")
+        );
+        assert_eq!(
+            increment("let a = false;", 12, &defaults, 1).as_deref(),
+            Some("let a = true;")
+        );
+        assert_eq!(
+            increment("monday", 0, &defaults, -1).as_deref(),
+            Some("sunday")
+        );
+        assert_eq!(
+            increment("monday", 0, &defaults, 9).as_deref(),
+            Some("wednesday")
+        );
+        // words within other words don't match
+        assert_eq!(increment("untrue", 3, &defaults, 1), None);
+        assert_eq!(increment("true", 0, &[], 1), None);
+    }
+
+    #[test]
+    fn test_keep_capitalization() {
+        let defaults = groups(DEFAULT_GROUPS);
+        assert_eq!(increment("True", 1, &defaults, 1).as_deref(), Some("False"));
+        assert_eq!(increment("TRUE", 1, &defaults, 1).as_deref(), Some("FALSE"));
+        assert_eq!(increment("Fri", 0, &defaults, 1).as_deref(), Some("Sat"));
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let rust = groups(&[&["pub", "pub(crate)"], &["let", "let mut"]]);
+        assert_eq!(
+            increment("pub(crate) fn a()", 1, &rust, 1).as_deref(),
+            Some("pub fn a()")
+        );
+        assert_eq!(
+            increment("pub fn a()", 1, &rust, 1).as_deref(),
+            Some("pub(crate) fn a()")
+        );
+        assert_eq!(
+            increment("let mut a = 1;", 0, &rust, 1).as_deref(),
+            Some("let a = 1;")
+        );
+        // the cursor has to be on the word
+        assert_eq!(increment("let a = 1;", 4, &rust, 1), None);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
     chars::{char_is_line_ending, char_is_whitespace},
-    syntax::{IndentQuery, LanguageConfiguration, Syntax},
-    tree_sitter::Node,
-    Rope, RopeSlice,
+    find_first_non_whitespace_char,
+    syntax::{IndentQuery, LanguageConfiguration, RopeProvider, Syntax},
+    tree_sitter::{Node, QueryCursor, QueryMatch, QueryPredicate, QueryPredicateArg},
+    Change, Rope, RopeSlice, Tendril,
 };
+use std::collections::HashMap;
 
 /// Enum representing indentation style.
 ///
@@ -186,106 +188,474 @@ pub fn indent_level_for_line(line: RopeSlice, tab_width: usize) -> usize {
     len / tab_width
 }
 
-/// Find the highest syntax node at position.
-/// This is to identify the column where this node (e.g., an HTML closing tag) ends.
-fn get_highest_syntax_node_at_bytepos(syntax: &Syntax, pos: usize) -> Option<Node> {
-    let tree = syntax.tree();
+/// The kind of indentation a node adds.
+#[derive(Debug, Clone, Copy)]
+enum IndentCaptureType<'a> {
+    Indent,
+    Outdent,
+    /// Align to the start of the anchor node.
+    Align(Node<'a>),
+}
 
-    // named_descendant
-    let mut node = match tree.root_node().descendant_for_byte_range(pos, pos) {
-        Some(node) => node,
-        None => return None,
-    };
+/// The lines an indent capture applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndentScope {
+    /// Every line of the node, including its first line.
+    All,
+    /// Every line of the node but its first line.
+    Tail,
+}
 
-    while let Some(parent) = node.parent() {
-        if parent.start_byte() == node.start_byte() {
-            node = parent
-        } else {
-            break;
+#[derive(Debug, Clone, Copy)]
+struct IndentCapture<'a> {
+    capture_type: IndentCaptureType<'a>,
+    scope: IndentScope,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExtendCapture {
+    /// Extend the node to the following lines that are indented more than its first line.
+    Extend,
+    /// Don't extend the next extended node this node is in, e.g. after a `return`.
+    PreventOnce,
+}
+
+/// The captures of the indent query, by node id.
+#[derive(Debug, Default)]
+struct IndentQueryResult<'a> {
+    indent_captures: HashMap<usize, Vec<IndentCapture<'a>>>,
+    extend_captures: HashMap<usize, Vec<ExtendCapture>>,
+}
+
+/// The indentation a line, or a number of lines, adds.
+#[derive(Debug, Default, Clone, Copy)]
+struct Indentation<'a> {
+    indent: usize,
+    outdent: usize,
+    align: Option<Node<'a>>,
+}
+
+impl<'a> Indentation<'a> {
+    /// Adds the indentation of a line. A line indents or outdents by at most one level, and
+    /// once aligned, the lines before don't matter anymore. Lines that close an aligned node
+    /// are indented instead, so that the node can be captured with both `@align` and `@indent`.
+    fn add_line(&mut self, added: &Indentation<'a>) {
+        if self.align.is_some() {
+            return;
+        }
+        if added.align.is_some() && self.indent >= self.outdent {
+            self.align = added.align;
+            return;
+        }
+        if added.indent > 0 && added.outdent == 0 {
+            self.indent += 1;
+        } else if added.outdent > 0 && added.indent == 0 {
+            self.outdent += 1;
+        }
+    }
+
+    fn add_capture(&mut self, added: IndentCaptureType<'a>) {
+        match added {
+            IndentCaptureType::Indent => self.indent = 1,
+            IndentCaptureType::Outdent => self.outdent = 1,
+            IndentCaptureType::Align(anchor) => {
+                // the innermost alignment of the line wins
+                if self.align.is_none() {
+                    self.align = Some(anchor);
+                }
+            }
         }
     }
 
-    Some(node)
+    fn as_string(&self, indent_style: IndentStyle, text: RopeSlice) -> String {
+        let mut indent = String::new();
+        if let Some(anchor) = self.align {
+            // keep the tabs before the anchor, so that it lines up for any tab width
+            let anchor = text.byte_to_char(anchor.start_byte());
+            let line_start = text.line_to_char(text.char_to_line(anchor));
+            indent.extend(text.slice(line_start..anchor).chars().map(|ch| {
+                if ch == '\t' {
+                    '\t'
+                } else {
+                    ' '
+                }
+            }));
+        }
+        let level = self.indent.saturating_sub(self.outdent);
+        indent.push_str(&indent_style.as_str().repeat(level));
+        indent
+    }
+}
+
+/// Returns whether a predicate of the indent query holds for a match. `new_line` is the line
+/// and byte position of a line break that is about to be inserted.
+fn predicate_holds(
+    predicate: &QueryPredicate,
+    mat: &QueryMatch,
+    new_line: Option<(usize, usize)>,
+) -> bool {
+    let node = |arg: &QueryPredicateArg| match arg {
+        QueryPredicateArg::Capture(index) => mat.nodes_for_capture_index(*index).next(),
+        QueryPredicateArg::String(_) => None,
+    };
+    let line = |node: Node| {
+        let line = node.start_position().row;
+        match new_line {
+            Some((new_line, byte_pos)) if line == new_line && node.start_byte() >= byte_pos => {
+                line + 1
+            }
+            _ => line,
+        }
+    };
+
+    // the predicates are validated when the query is loaded
+    match (predicate.operator.as_ref(), predicate.args.as_slice()) {
+        ("not-kind-eq?", [capture, QueryPredicateArg::String(kind)]) => {
+            !matches!(node(capture), Some(node) if node.kind() == kind.as_ref())
+        }
+        (operator @ ("same-line?" | "not-same-line?"), [a, b]) => match (node(a), node(b)) {
+            (Some(a), Some(b)) => (line(a) == line(b)) == (operator == "same-line?"),
+            _ => true,
+        },
+        _ => true,
+    }
 }
 
-/// Calculate the indentation at a given treesitter node.
-/// If newline is false, then any "indent" nodes on the line are ignored ("outdent" still applies).
-/// This is because the indentation is only increased starting at the second line of the node.
-fn calculate_indentation(
+/// Runs the indent query on the nodes intersecting the byte `range`.
+fn query_indents<'a>(
     query: &IndentQuery,
-    node: Option<Node>,
+    syntax: &'a Syntax,
+    text: RopeSlice<'a>,
+    range: std::ops::Range<usize>,
+    new_line: Option<(usize, usize)>,
+) -> IndentQueryResult<'a> {
+    let query = &query.query;
+    let mut result = IndentQueryResult::default();
+
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(range);
+    for mat in cursor.matches(query, syntax.tree().root_node(), RopeProvider(text)) {
+        if !query
+            .general_predicates(mat.pattern_index)
+            .iter()
+            .all(|predicate| predicate_holds(predicate, &mat, new_line))
+        {
+            continue;
+        }
+
+        let anchor = query
+            .capture_index_for_name("anchor")
+            .and_then(|index| mat.nodes_for_capture_index(index).next());
+
+        for capture in mat.captures {
+            let capture_type = match query.capture_names()[capture.index as usize].as_str() {
+                "indent" => IndentCaptureType::Indent,
+                "outdent" => IndentCaptureType::Outdent,
+                "align" => match anchor {
+                    Some(anchor) => IndentCaptureType::Align(anchor),
+                    None => continue,
+                },
+                name @ ("extend" | "extend.prevent-once") => {
+                    result
+                        .extend_captures
+                        .entry(capture.node.id())
+                        .or_default()
+                        .push(if name == "extend" {
+                            ExtendCapture::Extend
+                        } else {
+                            ExtendCapture::PreventOnce
+                        });
+                    continue;
+                }
+                // other captures are only used by predicates
+                _ => continue,
+            };
+
+            let scope = query
+                .property_settings(mat.pattern_index)
+                .iter()
+                .find(|property| property.key.as_ref() == "scope")
+                .and_then(|property| property.value.as_deref());
+            let scope = match (scope, capture_type) {
+                (Some("all"), _) => IndentScope::All,
+                (Some(_), _) => IndentScope::Tail,
+                (None, IndentCaptureType::Outdent) => IndentScope::All,
+                (None, _) => IndentScope::Tail,
+            };
+
+            result
+                .indent_captures
+                .entry(capture.node.id())
+                .or_default()
+                .push(IndentCapture {
+                    capture_type,
+                    scope,
+                });
+        }
+    }
+    result
+}
+
+/// Returns, for the node and each of its ancestors from the root down, whether it is the
+/// first node on its line. If a line break is about to be inserted at `new_line_byte_pos`, the
+/// first node after it is the first on its line.
+fn get_first_in_line(mut node: Node, new_line_byte_pos: Option<usize>) -> Vec<bool> {
+    let mut first_in_line = Vec::new();
+    loop {
+        // nodes without a previous sibling are first on their line if their parent is
+        first_in_line.push(node.prev_sibling().map(|prev| {
+            prev.end_position().row != node.start_position().row
+                || matches!(new_line_byte_pos, Some(byte_pos)
+                    if node.start_byte() >= byte_pos && prev.start_byte() < byte_pos)
+        }));
+        match node.parent() {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+
+    // the root node is first on its line
+    let mut parent_is_first = true;
+    first_in_line
+        .into_iter()
+        .rev()
+        .map(|first| {
+            let first = first.unwrap_or(parent_is_first);
+            parent_is_first = first;
+            first
+        })
+        .collect()
+}
+
+/// Moves `node` up to the closest ancestor of `deepest_preceding` that is captured with
+/// `@extend` and that the `line` belongs to, because it is indented more than the ancestor.
+fn extend_nodes<'a>(
+    node: &mut Node<'a>,
+    mut deepest_preceding: Node<'a>,
+    extend_captures: &HashMap<usize, Vec<ExtendCapture>>,
+    text: RopeSlice,
     line: usize,
-    newline: bool,
-) -> usize {
-    let mut increment: isize = 0;
+    tab_width: usize,
+) {
+    let mut prevent_extend = false;
+    while deepest_preceding != *node {
+        let mut extend = false;
+        let mut captured = false;
+        for capture in extend_captures
+            .get(&deepest_preceding.id())
+            .into_iter()
+            .flatten()
+        {
+            match capture {
+                ExtendCapture::PreventOnce => prevent_extend = true,
+                ExtendCapture::Extend => {
+                    captured = true;
+                    let node_line = deepest_preceding.start_position().row;
+                    extend = deepest_preceding.end_position().row == line
+                        || indent_level_for_line(text.line(line), tab_width)
+                            > indent_level_for_line(text.line(node_line), tab_width);
+                }
+            }
+        }
 
-    let mut node = match node {
-        Some(node) => node,
-        None => return 0,
+        if captured && prevent_extend {
+            prevent_extend = false;
+        } else if extend && !prevent_extend {
+            *node = deepest_preceding;
+            return;
+        }
+
+        // with syntax errors, the node might not be an ancestor
+        deepest_preceding = match deepest_preceding.parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+    }
+}
+
+/// Computes the indentation at `pos` from the indent query. If `new_line` is true, this is the
+/// indentation of a line break inserted at `pos`, otherwise the indentation of the line `pos`
+/// is on.
+///
+/// Walking up the syntax tree from the node at `pos`, each node can indent or outdent the
+/// lines it spans:
+///
+/// - `@indent` indents the lines of a node after its first line.
+/// - `@outdent` outdents all the lines of a node, such as a closing bracket.
+/// - `@align` aligns the lines of a node after its first line with the `@anchor` node of the
+///   same match, such as the first argument of a call.
+/// - `@extend` extends a node to the following lines that are indented more than its first
+///   line, for languages like Python where blocks aren't closed by a token.
+/// - `@extend.prevent-once` prevents the next extension, so that a line after a `return`
+///   is outdented.
+///
+/// Each line indents or outdents by at most one level. `(#set! "scope" "all")` and
+/// `(#set! "scope" "tail")` change the lines a capture applies to, and the `#not-kind-eq?`,
+/// `#same-line?` and `#not-same-line?` predicates restrict matches further.
+pub fn treesitter_indent_for_pos(
+    query: &IndentQuery,
+    syntax: &Syntax,
+    indent_style: IndentStyle,
+    tab_width: usize,
+    text: RopeSlice,
+    pos: usize,
+    new_line: bool,
+) -> Option<String> {
+    let line = text.char_to_line(pos);
+    let byte_pos = text.char_to_byte(pos);
+    let mut node = syntax
+        .tree()
+        .root_node()
+        .descendant_for_byte_range(byte_pos, byte_pos)?;
+
+    // the deepest node ending before `pos`, which might be extended to cover it
+    let deepest_preceding = {
+        let mut cursor = node.walk();
+        let preceding = node
+            .children(&mut cursor)
+            .filter(|child| child.end_byte() <= byte_pos)
+            .last();
+        preceding.map(|mut preceding| {
+            while preceding.child_count() > 0 {
+                preceding = preceding.child(preceding.child_count() - 1).unwrap();
+            }
+            preceding
+        })
+    };
+    // the query range has to include the preceding nodes in case one is extended
+    let query_range = match deepest_preceding {
+        Some(preceding) => preceding.end_byte().saturating_sub(1)..byte_pos + 1,
+        None => byte_pos..byte_pos + 1,
     };
+    let new_line_break = if new_line {
+        Some((line, byte_pos))
+    } else {
+        None
+    };
+    let result = query_indents(query, syntax, text, query_range, new_line_break);
+
+    if let Some(deepest_preceding) = deepest_preceding {
+        extend_nodes(
+            &mut node,
+            deepest_preceding,
+            &result.extend_captures,
+            text,
+            line,
+            tab_width,
+        );
+    }
 
-    let mut current_line = line;
-    let mut consider_indent = newline;
-    let mut increment_from_line: isize = 0;
+    let mut first_in_line = get_first_in_line(node, new_line_break.map(|(_, byte)| byte));
+    let mut indentation = Indentation::default();
+    // The indentation of the current line and the line below it. Lines are only added to the
+    // result once all the nodes on them are processed.
+    let mut indent_for_line = Indentation::default();
+    let mut indent_for_line_below = Indentation::default();
 
     loop {
-        let node_kind = node.kind();
-        let start = node.start_position().row;
-        if current_line != start {
-            // Indent/dedent by at most one per line:
-            // .map(|a| {       <-- ({ is two scopes
-            //     let len = 1; <-- indents one level
-            // })               <-- }) is two scopes
-            if consider_indent || increment_from_line < 0 {
-                increment += increment_from_line.signum();
+        let is_first = first_in_line.pop().unwrap_or(true);
+        for capture in result.indent_captures.get(&node.id()).into_iter().flatten() {
+            if is_first && capture.scope == IndentScope::All {
+                indent_for_line.add_capture(capture.capture_type);
+            } else {
+                indent_for_line_below.add_capture(capture.capture_type);
             }
-            increment_from_line = 0;
-            current_line = start;
-            consider_indent = true;
         }
 
-        if query.outdent.contains(node_kind) {
-            increment_from_line -= 1;
-        }
-        if query.indent.contains(node_kind) {
-            increment_from_line += 1;
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => {
+                indentation.add_line(&indent_for_line_below);
+                indentation.add_line(&indent_for_line);
+                break;
+            }
+        };
+
+        let mut node_line = node.start_position().row;
+        let mut parent_line = parent.start_position().row;
+        if new_line && node_line == line {
+            // account for the line break
+            if node.start_byte() >= byte_pos {
+                node_line += 1;
+            }
+            if parent.start_byte() >= byte_pos {
+                parent_line += 1;
+            }
         }
 
-        if let Some(parent) = node.parent() {
-            node = parent;
-        } else {
-            break;
+        if node_line != parent_line {
+            // the line below the queried one doesn't matter
+            if node_line < line + new_line as usize {
+                indentation.add_line(&indent_for_line_below);
+            }
+            if node_line == parent_line + 1 {
+                indent_for_line_below = indent_for_line;
+            } else {
+                indentation.add_line(&indent_for_line);
+                indent_for_line_below = Indentation::default();
+            }
+            indent_for_line = Indentation::default();
         }
+
+        node = parent;
     }
-    if consider_indent || increment_from_line < 0 {
-        increment += increment_from_line.signum();
-    }
-    increment.max(0) as usize
+
+    Some(indentation.as_string(indent_style, text))
 }
 
-// TODO: two usecases: if we are triggering this for a new, blank line:
-// - it should return 0 when mass indenting stuff
-// - it should look up the wrapper node and count it too when we press o/O
+/// Computes the indentation at `pos` with the indent query of the language, if there is one.
+/// See [treesitter_indent_for_pos].
 pub fn suggested_indent_for_pos(
     language_config: Option<&LanguageConfiguration>,
     syntax: Option<&Syntax>,
+    indent_style: IndentStyle,
+    tab_width: usize,
     text: RopeSlice,
     pos: usize,
-    line: usize,
     new_line: bool,
-) -> Option<usize> {
-    if let (Some(query), Some(syntax)) = (
-        language_config.and_then(|config| config.indent_query()),
-        syntax,
-    ) {
-        let byte_start = text.char_to_byte(pos);
-        let node = get_highest_syntax_node_at_bytepos(syntax, byte_start);
-        // TODO: special case for comments
-        // TODO: if preserve_leading_whitespace
-        Some(calculate_indentation(query, node, line, new_line))
-    } else {
-        None
+) -> Option<String> {
+    let syntax = syntax?;
+    let query = language_config.and_then(|config| config.indent_query())?;
+    treesitter_indent_for_pos(query, syntax, indent_style, tab_width, text, pos, new_line)
+}
+
+/// Recomputes the indentation of each of the `lines` from the indent query of the language.
+/// Returns the changes replacing the leading whitespace of the lines that aren't indented
+/// correctly. Blank lines, and lines whose indentation can't be computed from the syntax
+/// tree, are left untouched.
+pub fn reindent_lines(
+    query: &IndentQuery,
+    syntax: &Syntax,
+    indent_style: IndentStyle,
+    tab_width: usize,
+    text: RopeSlice,
+    lines: impl IntoIterator<Item = usize>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for line in lines {
+        let line_start = text.line_to_char(line);
+        let pos = match find_first_non_whitespace_char(text.line(line)) {
+            Some(pos) => line_start + pos,
+            None => continue,
+        };
+
+        let indent = match treesitter_indent_for_pos(
+            query,
+            syntax,
+            indent_style,
+            tab_width,
+            text,
+            pos,
+            false,
+        ) {
+            Some(indent) => indent,
+            None => continue,
+        };
+        if text.slice(line_start..pos) != indent.as_str() {
+            changes.push((line_start, pos, Some(Tendril::from(indent))));
+        }
     }
+    changes
 }
 
 pub fn get_scopes(syntax: Option<&Syntax>, text: RopeSlice, pos: usize) -> Vec<&'static str> {
@@ -434,6 +804,7 @@ where
                 injection_regex: None,
                 roots: vec![],
                 comment_token: None,
+                block_comment_tokens: None,
                 auto_format: false,
                 diagnostic_severity: Severity::Warning,
                 tree_sitter_library: None,
@@ -442,8 +813,12 @@ where
                     tab_width: 4,
                     unit: String::from("    "),
                 }),
+                increment_groups: Vec::new(),
+                auto_pairs: None,
+                soft_wrap: None,
                 indent_query: OnceCell::new(),
                 textobject_query: OnceCell::new(),
+                fold_query: OnceCell::new(),
             }],
         });
 
@@ -461,14 +836,15 @@ where
         for i in 0..doc.len_lines() {
             let line = text.line(i);
             if let Some(pos) = crate::find_first_non_whitespace_char(line) {
-                let indent = indent_level_for_line(line, tab_width);
+                let indent = line.slice(..pos).to_string();
                 assert_eq!(
                     suggested_indent_for_pos(
                         Some(&language_config),
                         Some(&syntax),
+                        IndentStyle::Spaces(4),
+                        tab_width,
                         text,
                         text.line_to_char(i) + pos,
-                        i,
                         false
                     ),
                     Some(indent),
@@ -479,4 +855,146 @@ where
             }
         }
     }
+
+    #[test]
+    fn test_reindent_lines() {
+        use crate::syntax::{Configuration, Loader};
+        use std::sync::Arc;
+
+        // set runtime path so we can find the grammar and queries
+        let mut runtime = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            roots = []
+            indent = { tab-width = 4, unit = "    " }
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+        let language_config = loader.language_config_for_scope("source.rust").unwrap();
+        let highlight_config = language_config.highlight_config(&[]).unwrap();
+
+        let doc = Rope::from(
+            "mod test {
+    fn hello_world() {
+        1 + 1;
+
+        let test_function = function_with_param(
+            this_param,
+            that_param
+        );
+    }
+}
+",
+        );
+        let syntax = Syntax::new(&doc, highlight_config.clone(), loader.clone());
+
+        // the document is indented correctly, so reindenting it is a no-op
+        let query = language_config.indent_query().unwrap();
+        let changes = reindent_lines(
+            query,
+            &syntax,
+            IndentStyle::Spaces(4),
+            4,
+            doc.slice(..),
+            0..doc.len_lines(),
+        );
+        assert_eq!(changes, Vec::new());
+
+        // remove the indentation and restore it
+        let mut unindented = Rope::from(
+            doc.lines()
+                .map(|line| line.to_string().trim_start_matches(' ').to_string())
+                .collect::<String>(),
+        );
+        let syntax = Syntax::new(&unindented, highlight_config, loader);
+        let changes = reindent_lines(
+            query,
+            &syntax,
+            IndentStyle::Spaces(4),
+            4,
+            unindented.slice(..),
+            0..unindented.len_lines(),
+        );
+        crate::Transaction::change(&unindented, changes.into_iter()).apply(&mut unindented);
+        assert_eq!(unindented, doc);
+    }
+
+    #[test]
+    fn test_indent_query_captures() {
+        use crate::syntax::{Configuration, IndentQuery, Loader};
+        use std::sync::Arc;
+
+        // set runtime path so we can find the grammar
+        let mut runtime = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "typescript"
+            scope = "source.ts"
+            file-types = ["ts"]
+            roots = []
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+        let language_config = loader.language_config_for_scope("source.ts").unwrap();
+        let highlight_config = language_config.highlight_config(&[]).unwrap();
+        let query = IndentQuery::new(
+            highlight_config.language,
+            r#"
+            (arguments) @indent
+            (arguments
+              "(" @open
+              .
+              (_) @anchor
+              (#same-line? @open @anchor)) @align
+            ")" @outdent
+
+            (if_statement) @indent @extend
+            (return_statement) @extend.prevent-once
+            "#,
+        )
+        .unwrap();
+
+        let indent_for = |doc: &str, line: usize, new_line: bool| {
+            let doc = Rope::from(doc);
+            let text = doc.slice(..);
+            let syntax = Syntax::new(&doc, highlight_config.clone(), loader.clone());
+            let pos = if new_line {
+                crate::line_ending::line_end_char_index(&text, line)
+            } else {
+                text.line_to_char(line) + find_first_non_whitespace_char(text.line(line)).unwrap()
+            };
+            treesitter_indent_for_pos(
+                &query,
+                &syntax,
+                IndentStyle::Spaces(2),
+                2,
+                text,
+                pos,
+                new_line,
+            )
+            .unwrap()
+        };
+
+        // arguments after the first one are aligned with it
+        assert_eq!(indent_for("foo(a,\n b);\n", 1, false), "    ");
+        assert_eq!(indent_for("foo(\n  a,\n);\n", 1, false), "  ");
+        assert_eq!(indent_for("foo(a,\n    b,\n);\n", 2, false), "");
+
+        // a line break extends the `if` statement, unless it returns
+        assert_eq!(indent_for("if (a)\n  b();\n", 1, true), "  ");
+        assert_eq!(indent_for("if (a)\n  return;\n", 1, true), "");
+    }
 }
@@ -5,12 +5,14 @@ pub mod chars;
 pub mod comment;
 pub mod diagnostic;
 pub mod diff;
+pub mod fold;
 pub mod graphemes;
 pub mod history;
 pub mod increment;
 pub mod indent;
 pub mod line_ending;
 pub mod macros;
+pub mod marks;
 pub mod match_brackets;
 pub mod movement;
 pub mod object;
@@ -20,11 +22,13 @@ pub mod register;
 pub mod search;
 pub mod selection;
 pub mod shellwords;
+pub mod snippets;
 mod state;
 pub mod surround;
 pub mod syntax;
 pub mod textobject;
 mod transaction;
+pub mod wrap;
 
 pub mod unicode {
     pub use unicode_general_category as category;
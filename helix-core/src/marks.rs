@@ -0,0 +1,79 @@
+use crate::{ChangeSet, RopeSlice, Selection};
+use std::collections::BTreeMap;
+
+/// Returns whether a mark is global. Global (uppercase) marks are unique across all
+/// documents, while every document has its own set of local marks.
+pub fn is_global(name: char) -> bool {
+    name.is_ascii_uppercase()
+}
+
+/// Named selections of a document. Marks are mapped through every change applied to
+/// the document, so they keep pointing at the same text.
+#[derive(Debug, Default, Clone)]
+pub struct Marks {
+    inner: BTreeMap<char, Selection>,
+}
+
+impl Marks {
+    pub fn get(&self, name: char) -> Option<&Selection> {
+        self.inner.get(&name)
+    }
+
+    pub fn set(&mut self, name: char, selection: Selection) {
+        self.inner.insert(name, selection);
+    }
+
+    pub fn remove(&mut self, name: char) -> Option<Selection> {
+        self.inner.remove(&name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over the marks, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Selection)> {
+        self.inner
+            .iter()
+            .map(|(&name, selection)| (name, selection))
+    }
+
+    /// Maps every mark through `changes`. `text` is the document after the changes
+    /// are applied.
+    pub fn map(&mut self, changes: &ChangeSet, text: RopeSlice) {
+        for selection in self.inner.values_mut() {
+            *selection = selection.clone().map(changes).ensure_invariants(text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Range, Rope, Transaction};
+
+    #[test]
+    fn test_map() {
+        let doc = Rope::from("hello world");
+        let mut marks = Marks::default();
+        marks.set('a', Selection::single(6, 11));
+        marks.set('b', Selection::single(0, 5));
+
+        // delete "hello " and insert at the end
+        let transaction = Transaction::change(
+            &doc,
+            vec![(0, 6, None), (11, 11, Some("!".into()))].into_iter(),
+        );
+        let mut new_doc = doc.clone();
+        transaction.apply(&mut new_doc);
+        marks.map(transaction.changes(), new_doc.slice(..));
+
+        assert_eq!(new_doc, "world!");
+        assert_eq!(marks.get('a').unwrap().primary(), Range::new(0, 5));
+        assert_eq!(marks.get('b').unwrap().primary(), Range::new(0, 1));
+        assert_eq!(
+            marks.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            ['a', 'b']
+        );
+    }
+}
@@ -1,6 +1,8 @@
+use std::ops::Range;
+
 use tree_sitter::Node;
 
-use crate::{Rope, Syntax};
+use crate::{Rope, RopeSlice, Syntax};
 
 const PAIRS: &[(char, char)] = &[
     ('(', ')'),
@@ -70,6 +72,73 @@ fn find_pair(syntax: &Syntax, doc: &Rope, pos: usize, traverse_parents: bool) ->
     }
 }
 
+// Returns the positions of the brackets within the char range `range`, along with their
+// nesting depth, ordered by position.
+//
+// Only brackets that are the first and last child of a node are paired, so brackets
+// within strings and comments are ignored. Quotes are not counted as brackets.
+pub fn bracket_depths(syntax: &Syntax, doc: RopeSlice, range: Range<usize>) -> Vec<(usize, usize)> {
+    let (start, end) = (doc.char_to_byte(range.start), doc.char_to_byte(range.end));
+    let mut brackets = Vec::new();
+    let mut cursor = syntax.tree().walk();
+    // the depth of the brackets that are children of each node of the path to the cursor
+    let mut depths = vec![0];
+
+    loop {
+        let node = cursor.node();
+        let depth = *depths.last().unwrap();
+
+        if node.start_byte() < end && node.end_byte() > start {
+            let mut child_depth = depth;
+            if let Some((open, close)) = bracket_children(doc, &node) {
+                for bracket in [open, close] {
+                    let pos = doc.byte_to_char(bracket.start_byte());
+                    if range.contains(&pos) {
+                        brackets.push((pos, depth));
+                    }
+                }
+                child_depth += 1;
+            }
+            if cursor.goto_first_child() {
+                depths.push(child_depth);
+                continue;
+            }
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                brackets.sort_unstable();
+                return brackets;
+            }
+            depths.pop();
+        }
+    }
+}
+
+// Returns the first and the last child of `node` if they are a pair of brackets.
+fn bracket_children<'a>(doc: RopeSlice, node: &Node<'a>) -> Option<(Node<'a>, Node<'a>)> {
+    let count = node.child_count();
+    if count < 2 {
+        return None;
+    }
+    let (open, close) = (node.child(0)?, node.child(count - 1)?);
+    let is_token =
+        |node: &Node| node.child_count() == 0 && node.end_byte() - node.start_byte() == 1;
+    if !is_token(&open) || !is_token(&close) {
+        return None;
+    }
+
+    let pair = (
+        doc.char(doc.byte_to_char(open.start_byte())),
+        doc.char(doc.byte_to_char(close.start_byte())),
+    );
+    if PAIRS.contains(&pair) && pair.0 != pair.1 {
+        Some((open, close))
+    } else {
+        None
+    }
+}
+
 fn is_valid_bracket(c: char) -> bool {
     PAIRS.iter().any(|(l, r)| *l == c || *r == c)
 }
@@ -90,3 +159,66 @@ fn surrounding_bytes(doc: &Rope, node: &Node) -> Option<(usize, usize)> {
 
     Some((start_byte, end_byte))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{Configuration, Loader};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_bracket_depths() {
+        // set runtime path so we can find the grammar
+        let mut runtime = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            roots = []
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+        let language_config = loader.language_config_for_scope("source.rust").unwrap();
+        let highlight_config = language_config.highlight_config(&[]).unwrap();
+
+        let source =
+            Rope::from("fn f(a: Vec<u8>) -> bool { let s = \"([\"; x < y && g([1]) } // ({\n");
+        let syntax = Syntax::new(&source, highlight_config, loader);
+        let doc = source.slice(..);
+
+        // the brackets of the string, the comment, `->` and the comparison are ignored, while
+        // `<` and `>` count around type arguments
+        assert_eq!(
+            bracket_depths(&syntax, doc, 0..source.len_chars()),
+            [
+                (4, 0),
+                (11, 1),
+                (14, 1),
+                (15, 0),
+                (25, 0),
+                (51, 1),
+                (52, 2),
+                (54, 2),
+                (55, 1),
+                (57, 0)
+            ]
+        );
+
+        // brackets outside of the range are left out, their depth doesn't change
+        assert_eq!(
+            bracket_depths(&syntax, doc, 50..58),
+            [(51, 1), (52, 2), (54, 2), (55, 1), (57, 0)]
+        );
+        assert_eq!(
+            bracket_depths(&syntax, doc, 12..52),
+            [(14, 1), (15, 0), (25, 0), (51, 1)]
+        );
+        assert!(bracket_depths(&syntax, doc, 58..65).is_empty());
+    }
+}
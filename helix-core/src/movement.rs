@@ -1,6 +1,7 @@
-use std::iter;
+use std::{iter, ops};
 
 use ropey::iter::Chars;
+use tree_sitter::{Node, QueryCursor};
 
 use crate::{
     chars::{categorize_char, char_is_line_ending, CharCategory},
@@ -9,7 +10,10 @@ use crate::{
         next_grapheme_boundary, nth_next_grapheme_boundary, nth_prev_grapheme_boundary,
         prev_grapheme_boundary,
     },
-    pos_at_coords, Position, Range, RopeSlice,
+    pos_at_coords,
+    syntax::LanguageConfiguration,
+    textobject::TextObject,
+    Position, Range, RopeSlice,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -106,13 +110,24 @@ pub fn move_prev_word_end(slice: RopeSlice, range: Range, count: usize) -> Range
     word_move(slice, range, count, WordMotionTarget::PrevWordEnd)
 }
 
+pub fn move_next_sub_word_start(slice: RopeSlice, range: Range, count: usize) -> Range {
+    word_move(slice, range, count, WordMotionTarget::NextSubWordStart)
+}
+
+pub fn move_next_sub_word_end(slice: RopeSlice, range: Range, count: usize) -> Range {
+    word_move(slice, range, count, WordMotionTarget::NextSubWordEnd)
+}
+
+pub fn move_prev_sub_word_start(slice: RopeSlice, range: Range, count: usize) -> Range {
+    word_move(slice, range, count, WordMotionTarget::PrevSubWordStart)
+}
+
+pub fn move_prev_sub_word_end(slice: RopeSlice, range: Range, count: usize) -> Range {
+    word_move(slice, range, count, WordMotionTarget::PrevSubWordEnd)
+}
+
 fn word_move(slice: RopeSlice, range: Range, count: usize, target: WordMotionTarget) -> Range {
-    let is_prev = matches!(
-        target,
-        WordMotionTarget::PrevWordStart
-            | WordMotionTarget::PrevLongWordStart
-            | WordMotionTarget::PrevWordEnd
-    );
+    let is_prev = target.is_prev();
 
     // Special-case early-out.
     if (is_prev && range.head == 0) || (!is_prev && range.head == slice.len_chars()) {
@@ -192,6 +207,26 @@ pub enum WordMotionTarget {
     NextLongWordStart,
     NextLongWordEnd,
     PrevLongWordStart,
+    // A "sub word" is a part of a word delimited by underscores, camelCase humps and
+    // changes between letters and digits, such as `foo` and `bar` in both `foo_bar`
+    // and `fooBar`.
+    NextSubWordStart,
+    NextSubWordEnd,
+    PrevSubWordStart,
+    PrevSubWordEnd,
+}
+
+impl WordMotionTarget {
+    fn is_prev(self) -> bool {
+        matches!(
+            self,
+            WordMotionTarget::PrevWordStart
+                | WordMotionTarget::PrevLongWordStart
+                | WordMotionTarget::PrevWordEnd
+                | WordMotionTarget::PrevSubWordStart
+                | WordMotionTarget::PrevSubWordEnd
+        )
+    }
 }
 
 pub trait CharHelpers {
@@ -203,12 +238,7 @@ impl CharHelpers for Chars<'_> {
     /// starting on a boundary (either directly or after skipping newline characters).
     /// Any other changes to the anchor should be handled by the calling code.
     fn range_to_target(&mut self, target: WordMotionTarget, origin: Range) -> Range {
-        let is_prev = matches!(
-            target,
-            WordMotionTarget::PrevWordStart
-                | WordMotionTarget::PrevLongWordStart
-                | WordMotionTarget::PrevWordEnd
-        );
+        let is_prev = target.is_prev();
 
         // Reverse the iterator if needed for the motion direction.
         if is_prev {
@@ -284,7 +314,33 @@ fn is_long_word_boundary(a: char, b: char) -> bool {
     }
 }
 
+/// Returns whether there is a sub word boundary between `a` and the `b` following it.
+pub(crate) fn is_sub_word_boundary(a: char, b: char) -> bool {
+    match (categorize_char(a), categorize_char(b)) {
+        (CharCategory::Word, CharCategory::Word) => {
+            (a == '_') != (b == '_')
+                || (a.is_lowercase() && b.is_uppercase())
+                || (a.is_alphabetic() && b.is_numeric())
+                || (a.is_numeric() && b.is_alphabetic())
+        }
+        (a, b) => a != b,
+    }
+}
+
+fn is_sub_word_separator(ch: char) -> bool {
+    ch == '_' || ch.is_whitespace()
+}
+
 fn reached_target(target: WordMotionTarget, prev_ch: char, next_ch: char) -> bool {
+    // the chars are visited backwards by the motions to previous targets
+    let sub_word_boundary = || {
+        if target.is_prev() {
+            is_sub_word_boundary(next_ch, prev_ch)
+        } else {
+            is_sub_word_boundary(prev_ch, next_ch)
+        }
+    };
+
     match target {
         WordMotionTarget::NextWordStart | WordMotionTarget::PrevWordEnd => {
             is_word_boundary(prev_ch, next_ch)
@@ -302,6 +358,99 @@ fn reached_target(target: WordMotionTarget, prev_ch: char, next_ch: char) -> boo
             is_long_word_boundary(prev_ch, next_ch)
                 && (!prev_ch.is_whitespace() || char_is_line_ending(next_ch))
         }
+        WordMotionTarget::NextSubWordStart | WordMotionTarget::PrevSubWordEnd => {
+            sub_word_boundary() && (char_is_line_ending(next_ch) || !is_sub_word_separator(next_ch))
+        }
+        WordMotionTarget::NextSubWordEnd | WordMotionTarget::PrevSubWordStart => {
+            sub_word_boundary() && (!is_sub_word_separator(prev_ch) || char_is_line_ending(next_ch))
+        }
+    }
+}
+
+/// Selects the `count`th next or previous tree-sitter textobject, such as a function, after or
+/// before the cursor. The `around` capture of the object is used when the query has it, and the
+/// `inside` capture otherwise.
+pub fn goto_treesitter_object(
+    slice: RopeSlice,
+    range: Range,
+    object_name: &str,
+    dir: Direction,
+    slice_tree: Node,
+    lang_config: &LanguageConfiguration,
+    count: usize,
+) -> Range {
+    let get_range = move |range: Range| -> Option<Range> {
+        let byte_pos = slice.char_to_byte(range.cursor(slice));
+
+        let capture_name = |textobject: TextObject| format!("{}.{}", object_name, textobject);
+        let mut cursor = QueryCursor::new();
+        let nodes = lang_config.textobject_query()?.capture_nodes_any(
+            &[
+                &capture_name(TextObject::Around),
+                &capture_name(TextObject::Inside),
+            ],
+            slice_tree,
+            slice,
+            &mut cursor,
+        )?;
+
+        let node = match dir {
+            Direction::Forward => nodes
+                .filter(|node| node.start_byte() > byte_pos)
+                .min_by_key(|node| node.start_byte())?,
+            Direction::Backward => nodes
+                .filter(|node| node.end_byte() <= byte_pos)
+                .max_by_key(|node| node.start_byte())?,
+        };
+
+        let start_char = slice.byte_to_char(node.start_byte());
+        let end_char = slice.byte_to_char(node.end_byte());
+        if start_char == end_char {
+            return None;
+        }
+
+        // the cursor ends up on the start of the object when moving backwards
+        Some(match dir {
+            Direction::Forward => Range::new(start_char, end_char),
+            Direction::Backward => Range::new(end_char, start_char),
+        })
+    };
+
+    (0..count).fold(range, |range, _| get_range(range).unwrap_or(range))
+}
+
+/// Selects the `count`th next or previous hunk of changed lines after or before the cursor
+/// line, `hunks` being their ordered line ranges. The hunks may have been computed for an older
+/// version of the text, so lines past its end are clamped to the end.
+pub fn goto_change(
+    slice: RopeSlice,
+    range: Range,
+    hunks: &[ops::Range<usize>],
+    dir: Direction,
+    count: usize,
+) -> Range {
+    let cursor_line = range.cursor_line(slice);
+    let hunk = match dir {
+        Direction::Forward => hunks
+            .iter()
+            .position(|lines| lines.start > cursor_line)
+            .map(|i| i + count - 1),
+        Direction::Backward => hunks
+            .iter()
+            .rposition(|lines| lines.start < cursor_line)
+            .and_then(|i| i.checked_sub(count - 1)),
+    };
+    let lines = match hunk.and_then(|i| hunks.get(i)) {
+        Some(lines) => lines,
+        None => return range,
+    };
+
+    let len_lines = slice.len_lines();
+    let start = slice.line_to_char(lines.start.min(len_lines));
+    let end = slice.line_to_char(lines.end.min(len_lines));
+    match dir {
+        Direction::Forward => Range::new(start, end),
+        Direction::Backward => Range::new(end, start),
     }
 }
 
@@ -1125,4 +1274,179 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_behaviour_when_moving_to_next_sub_words() {
+        let tests = [
+            (
+                "camelCaseWords and snake_case_words",
+                vec![
+                    (1, Range::new(0, 0), Range::new(0, 5)),
+                    (1, Range::new(0, 5), Range::new(5, 9)),
+                    (3, Range::new(0, 0), Range::new(9, 15)),
+                    (1, Range::new(19, 19), Range::new(19, 25)),
+                ],
+            ),
+            (
+                "utf8Decoder stops at digits",
+                vec![
+                    (1, Range::new(0, 0), Range::new(0, 3)),
+                    (1, Range::new(0, 3), Range::new(3, 4)),
+                ],
+            ),
+        ];
+
+        for (sample, scenario) in tests {
+            for (count, begin, expected_end) in scenario.into_iter() {
+                let range = move_next_sub_word_start(Rope::from(sample).slice(..), begin, count);
+                assert_eq!(range, expected_end, "Case failed: [{}]", sample);
+            }
+        }
+
+        let tests = [(
+            "snake_case_words",
+            vec![
+                (1, Range::new(0, 0), Range::new(0, 5)),
+                (1, Range::new(0, 5), Range::new(5, 10)),
+            ],
+        )];
+
+        for (sample, scenario) in tests {
+            for (count, begin, expected_end) in scenario.into_iter() {
+                let range = move_next_sub_word_end(Rope::from(sample).slice(..), begin, count);
+                assert_eq!(range, expected_end, "Case failed: [{}]", sample);
+            }
+        }
+    }
+
+    #[test]
+    fn test_behaviour_when_moving_to_previous_sub_words() {
+        let tests = [(
+            "snake_caseWord",
+            vec![
+                (1, Range::new(13, 13), Range::new(14, 10)),
+                (1, Range::new(14, 10), Range::new(10, 6)),
+                (1, Range::new(10, 6), Range::new(6, 0)),
+            ],
+        )];
+
+        for (sample, scenario) in tests {
+            for (count, begin, expected_end) in scenario.into_iter() {
+                let range = move_prev_sub_word_start(Rope::from(sample).slice(..), begin, count);
+                assert_eq!(range, expected_end, "Case failed: [{}]", sample);
+            }
+        }
+    }
+
+    #[test]
+    fn test_goto_treesitter_object() {
+        use crate::syntax::{Configuration, Loader, Syntax};
+        use std::sync::Arc;
+
+        // set runtime path so we can find the grammar and queries
+        let mut runtime = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            roots = []
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+        let language_config = loader.language_config_for_scope("source.rust").unwrap();
+        let highlight_config = language_config.highlight_config(&[]).unwrap();
+
+        let doc = Rope::from("fn a() {}\nfn b() {}\nfn c() {}\n");
+        let syntax = Syntax::new(&doc, highlight_config, loader.clone());
+        let slice = doc.slice(..);
+        let root = syntax.tree().root_node();
+
+        let goto = |range: Range, dir: Direction, count: usize| {
+            goto_treesitter_object(slice, range, "function", dir, root, &language_config, count)
+        };
+
+        // (start range, direction, count, final range)
+        let tests = [
+            (Range::point(0), Direction::Forward, 1, Range::new(10, 19)),
+            (Range::point(0), Direction::Forward, 2, Range::new(20, 29)),
+            (
+                Range::new(10, 19),
+                Direction::Forward,
+                1,
+                Range::new(20, 29),
+            ),
+            // stops on the last object when the count runs past it
+            (Range::point(0), Direction::Forward, 5, Range::new(20, 29)),
+            (Range::point(25), Direction::Backward, 1, Range::new(19, 10)),
+            (Range::point(25), Direction::Backward, 2, Range::new(9, 0)),
+            (Range::point(25), Direction::Backward, 5, Range::new(9, 0)),
+            // nothing to move to
+            (Range::point(25), Direction::Forward, 1, Range::point(25)),
+            (Range::point(5), Direction::Backward, 1, Range::point(5)),
+        ];
+
+        for (range, dir, count, expected) in tests {
+            assert_eq!(
+                goto(range, dir, count),
+                expected,
+                "{:?} {:?} x{}",
+                range,
+                dir,
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn test_goto_change() {
+        use crate::diff::compare_lines;
+        use crate::Transaction;
+
+        let base = Rope::from("a\nb\nc\nd\ne\n");
+        let mut doc = Rope::from("a\nB\nc\nd\nE\n");
+        let hunks: Vec<_> = compare_lines(&base, &doc)
+            .into_iter()
+            .map(|hunk| hunk.after)
+            .collect();
+        assert_eq!(hunks, [1..2, 4..5]);
+
+        let slice = doc.slice(..);
+        assert_eq!(
+            goto_change(slice, Range::point(0), &hunks, Direction::Forward, 1),
+            Range::new(2, 4)
+        );
+        assert_eq!(
+            goto_change(slice, Range::point(0), &hunks, Direction::Forward, 2),
+            Range::new(8, 10)
+        );
+        assert_eq!(
+            goto_change(slice, Range::point(8), &hunks, Direction::Backward, 1),
+            Range::new(4, 2)
+        );
+        // nothing to move to
+        assert_eq!(
+            goto_change(slice, Range::point(0), &hunks, Direction::Forward, 3),
+            Range::point(0)
+        );
+
+        // the hunks lag behind the text until they are computed again
+        let transaction = Transaction::change(&doc, [(2, 10, None)].into_iter());
+        assert!(transaction.apply(&mut doc));
+        assert_eq!(doc, "a\n");
+        let slice = doc.slice(..);
+        assert_eq!(
+            goto_change(slice, Range::point(0), &hunks, Direction::Forward, 1),
+            Range::point(2)
+        );
+        assert_eq!(
+            goto_change(slice, Range::point(0), &hunks, Direction::Forward, 2),
+            Range::point(2)
+        );
+    }
 }
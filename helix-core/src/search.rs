@@ -1,5 +1,76 @@
+use std::ops::Range;
+
+use regex_cursor::{engines::meta, regex_automata::util::syntax, Input};
+
 use crate::RopeSlice;
 
+/// An error building a [`RopeRegex`], such as a syntax error in its pattern.
+pub type BuildError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A regex searching the chunks of a rope in place, rather than a contiguous copy of the text.
+/// Matches are char ranges of the searched text.
+#[derive(Debug, Clone)]
+pub struct RopeRegex {
+    regex: meta::Regex,
+    pattern: String,
+}
+
+impl RopeRegex {
+    pub fn new(pattern: &str) -> Result<Self, BuildError> {
+        Self::with_case_insensitive(pattern, false)
+    }
+
+    /// Builds a regex which, when `case_insensitive`, matches letters regardless of their case.
+    pub fn with_case_insensitive(
+        pattern: &str,
+        case_insensitive: bool,
+    ) -> Result<Self, BuildError> {
+        let regex = meta::Regex::builder()
+            .syntax(syntax::Config::new().case_insensitive(case_insensitive))
+            .build(pattern)?;
+        Ok(Self {
+            regex,
+            pattern: pattern.to_owned(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, text: RopeSlice) -> bool {
+        self.regex.is_match(Input::new(text))
+    }
+
+    /// Iterates over the successive non-overlapping matches in `text`.
+    pub fn find_iter<'a>(&'a self, text: RopeSlice<'a>) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.regex
+            .find_iter(Input::new(text))
+            .map(move |mat| text.byte_to_char(mat.start())..text.byte_to_char(mat.end()))
+    }
+
+    /// Returns the first match within the char `range` of `text`. The text around the range is
+    /// still looked at by assertions such as `\b`.
+    pub fn find_in(&self, text: RopeSlice, range: Range<usize>) -> Option<Range<usize>> {
+        let input =
+            Input::new(text).range(text.char_to_byte(range.start)..text.char_to_byte(range.end));
+        self.regex
+            .find(input)
+            .map(|mat| text.byte_to_char(mat.start())..text.byte_to_char(mat.end()))
+    }
+
+    /// Returns the last match within the char `range` of `text`, out of the successive
+    /// non-overlapping matches from the start of the range.
+    pub fn find_last_in(&self, text: RopeSlice, range: Range<usize>) -> Option<Range<usize>> {
+        let input =
+            Input::new(text).range(text.char_to_byte(range.start)..text.char_to_byte(range.end));
+        self.regex
+            .find_iter(input)
+            .last()
+            .map(|mat| text.byte_to_char(mat.start())..text.byte_to_char(mat.end()))
+    }
+}
+
 pub fn find_nth_next(text: RopeSlice, ch: char, mut pos: usize, n: usize) -> Option<usize> {
     if pos >= text.len_chars() || n == 0 {
         return None;
@@ -43,3 +114,39 @@ pub fn find_nth_prev(text: RopeSlice, ch: char, mut pos: usize, n: usize) -> Opt
 
     Some(pos)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    #[test]
+    fn test_rope_regex() {
+        // build the rope from small chunks so that matches span several of them
+        let mut text = Rope::new();
+        for _ in 0..100 {
+            text.insert(text.len_chars(), "héllo wörld ");
+        }
+        let text = text.slice(..);
+        assert!(text.chunks().count() > 1);
+
+        let regex = RopeRegex::new(r"w\w+d").unwrap();
+        let matches: Vec<_> = regex.find_iter(text).collect();
+        assert_eq!(matches.len(), 100);
+        assert_eq!(matches[0], 6..11);
+        assert_eq!(matches[99], 1194..1199);
+
+        assert_eq!(regex.find_in(text, 7..1200), Some(18..23));
+        assert_eq!(regex.find_in(text, 1195..1200), None);
+        assert_eq!(regex.find_last_in(text, 0..1198), Some(1182..1187));
+        assert_eq!(regex.find_last_in(text, 0..6), None);
+
+        // look-around assertions see the text outside of the range
+        let regex = RopeRegex::new(r"\bllo").unwrap();
+        assert_eq!(regex.find_in(text, 2..12), None);
+
+        let regex = RopeRegex::with_case_insensitive("HÉLLO", true).unwrap();
+        assert!(regex.is_match(text));
+        assert_eq!(regex.as_str(), "HÉLLO");
+    }
+}
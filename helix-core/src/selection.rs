@@ -8,8 +8,10 @@ use crate::{
         prev_grapheme_boundary,
     },
     movement::Direction,
+    search::RopeRegex,
     Assoc, ChangeSet, RopeSlice,
 };
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 use std::borrow::Cow;
 
@@ -47,7 +49,7 @@ use std::borrow::Cow;
 /// single grapheme inward from the range's edge.  There are a
 /// variety of helper methods on `Range` for working in terms of
 /// that block cursor, all of which have `cursor` in their name.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Range {
     /// The anchor of the range: the side that doesn't move when extending.
     pub anchor: usize,
@@ -353,7 +355,7 @@ impl From<(usize, usize)> for Range {
 
 /// A selection consists of one or more selection ranges.
 /// invariant: A selection can never be empty (always contains at least primary range).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Selection {
     ranges: SmallVec<[Range; 1]>,
     primary_index: usize,
@@ -598,12 +600,12 @@ impl<'a> IntoIterator for &'a Selection {
 pub fn keep_or_remove_matches(
     text: RopeSlice,
     selection: &Selection,
-    regex: &crate::regex::Regex,
+    regex: &RopeRegex,
     remove: bool,
 ) -> Option<Selection> {
     let result: SmallVec<_> = selection
         .iter()
-        .filter(|range| regex.is_match(&range.fragment(text)) ^ remove)
+        .filter(|range| regex.is_match(text.slice(range.from()..range.to())) ^ remove)
         .copied()
         .collect();
 
@@ -617,23 +619,16 @@ pub fn keep_or_remove_matches(
 pub fn select_on_matches(
     text: RopeSlice,
     selection: &Selection,
-    regex: &crate::regex::Regex,
+    regex: &RopeRegex,
 ) -> Option<Selection> {
     let mut result = SmallVec::with_capacity(selection.len());
 
     for sel in selection {
-        // TODO: can't avoid occasional allocations since Regex can't operate on chunks yet
-        let fragment = sel.fragment(text);
-
         let sel_start = sel.from();
-        let start_byte = text.char_to_byte(sel_start);
 
-        for mat in regex.find_iter(&fragment) {
+        for mat in regex.find_iter(text.slice(sel_start..sel.to())) {
             // TODO: retain range direction
-
-            let start = text.byte_to_char(start_byte + mat.start());
-            let end = text.byte_to_char(start_byte + mat.end());
-            result.push(Range::new(start, end));
+            result.push(Range::new(sel_start + mat.start, sel_start + mat.end));
         }
     }
 
@@ -646,11 +641,7 @@ pub fn select_on_matches(
 }
 
 // TODO: support to split on capture #N instead of whole match
-pub fn split_on_matches(
-    text: RopeSlice,
-    selection: &Selection,
-    regex: &crate::regex::Regex,
-) -> Selection {
+pub fn split_on_matches(text: RopeSlice, selection: &Selection, regex: &RopeRegex) -> Selection {
     let mut result = SmallVec::with_capacity(selection.len());
 
     for sel in selection {
@@ -660,21 +651,15 @@ pub fn split_on_matches(
             continue;
         }
 
-        // TODO: can't avoid occasional allocations since Regex can't operate on chunks yet
-        let fragment = sel.fragment(text);
-
         let sel_start = sel.from();
         let sel_end = sel.to();
 
-        let start_byte = text.char_to_byte(sel_start);
-
         let mut start = sel_start;
 
-        for mat in regex.find_iter(&fragment) {
+        for mat in regex.find_iter(text.slice(sel_start..sel_end)) {
             // TODO: retain range direction
-            let end = text.byte_to_char(start_byte + mat.start());
-            result.push(Range::new(start, end));
-            start = text.byte_to_char(start_byte + mat.end());
+            result.push(Range::new(start, sel_start + mat.start));
+            start = sel_start + mat.end;
         }
 
         if start < sel_end {
@@ -985,13 +970,11 @@ mod test {
 
     #[test]
     fn test_split_on_matches() {
-        use crate::regex::Regex;
-
         let text = Rope::from(" abcd efg wrs   xyz 123 456");
 
         let selection = Selection::new(smallvec![Range::new(0, 9), Range::new(11, 20),], 0);
 
-        let result = split_on_matches(text.slice(..), &selection, &Regex::new(r"\s+").unwrap());
+        let result = split_on_matches(text.slice(..), &selection, &RopeRegex::new(r"\s+").unwrap());
 
         assert_eq!(
             result.ranges(),
@@ -0,0 +1,541 @@
+//! Parser for the snippet syntax of the Language Server Protocol, see
+//! <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#snippet_syntax>
+
+use crate::{ChangeSet, Range, Selection, Tendril};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetElement {
+    /// `$1` or `${1}`
+    Tabstop {
+        tabstop: usize,
+    },
+    /// `${1:value}`
+    Placeholder {
+        tabstop: usize,
+        value: Vec<SnippetElement>,
+    },
+    /// `${1|one,two|}`
+    Choice {
+        tabstop: usize,
+        choices: Vec<String>,
+    },
+    /// `$name`, `${name}` or `${name:default}`. Transforms (`${name/regex/format/}`) are
+    /// parsed but ignored.
+    Variable {
+        name: String,
+        default: Option<Vec<SnippetElement>>,
+    },
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    elements: Vec<SnippetElement>,
+}
+
+impl Snippet {
+    /// Parses a snippet. Parsing never fails: like in other editors, anything that
+    /// isn't valid snippet syntax is kept as text.
+    pub fn parse(s: &str) -> Self {
+        let mut parser = Parser { input: s, pos: 0 };
+        Self {
+            elements: parser.parse_elements(false),
+        }
+    }
+
+    pub fn elements(&self) -> &[SnippetElement] {
+        &self.elements
+    }
+
+    /// Renders the snippet to text, replacing line breaks with `newline` (usually the
+    /// line ending followed by the indentation of the line the snippet is inserted on).
+    ///
+    /// Also returns the tabstops in the order they're visited, with the ranges of every
+    /// occurrence as char offsets into the text. `$0` comes last, and defaults to the end
+    /// of the text. Choices render their first option and variables their default value.
+    pub fn render(&self, newline: &str) -> (Tendril, Vec<Vec<Range>>) {
+        let mut placeholders = BTreeMap::new();
+        collect_placeholders(&self.elements, &mut placeholders);
+
+        let mut renderer = Renderer {
+            text: Tendril::new(),
+            len: 0,
+            newline,
+            placeholders: &placeholders,
+            tabstops: BTreeMap::new(),
+            expanding: Vec::new(),
+        };
+        renderer.render(&self.elements);
+
+        let Renderer {
+            text,
+            len,
+            mut tabstops,
+            ..
+        } = renderer;
+        let last = tabstops
+            .remove(&0)
+            .unwrap_or_else(|| vec![Range::point(len)]);
+        let mut tabstops: Vec<_> = tabstops.into_values().collect();
+        tabstops.push(last);
+
+        (text, tabstops)
+    }
+}
+
+/// Finds the placeholder of every tabstop, which also fills the other occurrences
+/// of the tabstop.
+fn collect_placeholders<'a>(
+    elements: &'a [SnippetElement],
+    placeholders: &mut BTreeMap<usize, &'a [SnippetElement]>,
+) {
+    for element in elements {
+        match element {
+            SnippetElement::Placeholder { tabstop, value } => {
+                placeholders.entry(*tabstop).or_insert(value);
+                collect_placeholders(value, placeholders);
+            }
+            SnippetElement::Variable {
+                default: Some(default),
+                ..
+            } => collect_placeholders(default, placeholders),
+            _ => (),
+        }
+    }
+}
+
+struct Renderer<'a> {
+    text: Tendril,
+    /// Length of the text in chars.
+    len: usize,
+    newline: &'a str,
+    placeholders: &'a BTreeMap<usize, &'a [SnippetElement]>,
+    tabstops: BTreeMap<usize, Vec<Range>>,
+    /// Tabstops whose value is being rendered. A placeholder can refer to its own tabstop,
+    /// as in `${1:$1}`, which renders empty instead of recursing.
+    expanding: Vec<usize>,
+}
+
+impl<'a> Renderer<'a> {
+    fn push_str(&mut self, s: &str) {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.text.push_str(self.newline);
+                self.len += self.newline.chars().count();
+            }
+            self.text.push_str(line);
+            self.len += line.chars().count();
+        }
+    }
+
+    fn render_tabstop(&mut self, tabstop: usize, value: &[SnippetElement]) {
+        if self.expanding.contains(&tabstop) {
+            return;
+        }
+        self.expanding.push(tabstop);
+        self.render(value);
+        self.expanding.pop();
+    }
+
+    fn render(&mut self, elements: &[SnippetElement]) {
+        for element in elements {
+            let start = self.len;
+            match element {
+                SnippetElement::Text(text) => {
+                    self.push_str(text);
+                    continue;
+                }
+                SnippetElement::Tabstop { tabstop } => {
+                    if let Some(value) = self.placeholders.get(tabstop) {
+                        self.render_tabstop(*tabstop, value);
+                    }
+                }
+                SnippetElement::Placeholder { tabstop, value } => {
+                    self.render_tabstop(*tabstop, value)
+                }
+                SnippetElement::Choice { choices, .. } => {
+                    if let Some(choice) = choices.first() {
+                        self.push_str(choice);
+                    }
+                }
+                SnippetElement::Variable { default, .. } => {
+                    if let Some(default) = default {
+                        self.render(default);
+                    }
+                    continue;
+                }
+            }
+
+            let tabstop = match element {
+                SnippetElement::Tabstop { tabstop }
+                | SnippetElement::Placeholder { tabstop, .. }
+                | SnippetElement::Choice { tabstop, .. } => *tabstop,
+                _ => unreachable!(),
+            };
+            self.tabstops
+                .entry(tabstop)
+                .or_default()
+                .push(Range::new(start, self.len));
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the `}` ending an element, returning `value` if it is there.
+    fn close<T>(&mut self, value: T) -> Option<T> {
+        if self.eat('}') {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Parses elements until the end of the input, or until an unescaped `}` if
+    /// `nested` is set. Otherwise, a stray `}` is kept as text.
+    fn parse_elements(&mut self, nested: bool) -> Vec<SnippetElement> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '}' if nested => break,
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c @ ('$' | '}' | '\\')) => {
+                            self.pos += 1;
+                            text.push(c);
+                        }
+                        _ => text.push('\\'),
+                    }
+                }
+                '$' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    match self.parse_dollar() {
+                        Some(element) => {
+                            if !text.is_empty() {
+                                elements.push(SnippetElement::Text(std::mem::take(&mut text)));
+                            }
+                            elements.push(element);
+                        }
+                        None => {
+                            self.pos = start + 1;
+                            text.push('$');
+                        }
+                    }
+                }
+                c => {
+                    self.pos += c.len_utf8();
+                    text.push(c);
+                }
+            }
+        }
+        if !text.is_empty() {
+            elements.push(SnippetElement::Text(text));
+        }
+        elements
+    }
+
+    /// Parses what follows a `$`.
+    fn parse_dollar(&mut self) -> Option<SnippetElement> {
+        if !self.eat('{') {
+            if let Some(tabstop) = self.parse_int() {
+                return Some(SnippetElement::Tabstop { tabstop });
+            }
+            let name = self.parse_var()?;
+            return Some(SnippetElement::Variable {
+                name,
+                default: None,
+            });
+        }
+
+        if let Some(tabstop) = self.parse_int() {
+            if self.eat('}') {
+                Some(SnippetElement::Tabstop { tabstop })
+            } else if self.eat(':') {
+                let value = self.parse_elements(true);
+                self.close(SnippetElement::Placeholder { tabstop, value })
+            } else if self.eat('|') {
+                let choices = self.parse_choices()?;
+                Some(SnippetElement::Choice { tabstop, choices })
+            } else {
+                None
+            }
+        } else {
+            let name = self.parse_var()?;
+            if self.eat('}') {
+                Some(SnippetElement::Variable {
+                    name,
+                    default: None,
+                })
+            } else if self.eat(':') {
+                let default = self.parse_elements(true);
+                self.close(SnippetElement::Variable {
+                    name,
+                    default: Some(default),
+                })
+            } else if self.eat('/') {
+                // regex and format
+                self.skip_until('/')?;
+                self.skip_until('/')?;
+                // options
+                while self.peek()?.is_ascii_alphabetic() {
+                    self.pos += 1;
+                }
+                self.close(SnippetElement::Variable {
+                    name,
+                    default: None,
+                })
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Parses `one,two|}`, the rest of a choice.
+    fn parse_choices(&mut self) -> Option<Vec<String>> {
+        let mut choices = Vec::new();
+        let mut choice = String::new();
+        loop {
+            match self.peek()? {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c @ ('$' | '}' | '\\' | ',' | '|')) => {
+                            self.pos += 1;
+                            choice.push(c);
+                        }
+                        _ => choice.push('\\'),
+                    }
+                }
+                ',' => {
+                    self.pos += 1;
+                    choices.push(std::mem::take(&mut choice));
+                }
+                '|' => {
+                    self.pos += 1;
+                    choices.push(choice);
+                    return self.close(choices);
+                }
+                c => {
+                    self.pos += c.len_utf8();
+                    choice.push(c);
+                }
+            }
+        }
+    }
+
+    /// Skips past the next unescaped `end` that isn't nested in a `${...}`, as in the
+    /// format of a transform.
+    fn skip_until(&mut self, end: char) -> Option<()> {
+        let mut depth = 0;
+        loop {
+            let c = self.peek()?;
+            self.pos += c.len_utf8();
+            match c {
+                '\\' => {
+                    let c = self.peek()?;
+                    self.pos += c.len_utf8();
+                }
+                '$' if self.eat('{') => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == end && depth == 0 => return Some(()),
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_int(&mut self) -> Option<usize> {
+        let len = self.input[self.pos..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.input.len() - self.pos);
+        let int = self.input[self.pos..self.pos + len].parse().ok()?;
+        self.pos += len;
+        Some(int)
+    }
+
+    fn parse_var(&mut self) -> Option<String> {
+        let rest = &self.input[self.pos..];
+        if !rest.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let len = rest
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+}
+
+/// A snippet that was inserted into a document, whose tabstops can be visited in
+/// order. The tabstops are mapped through every change to the document.
+#[derive(Debug, Clone)]
+pub struct ActiveSnippet {
+    tabstops: Vec<Selection>,
+    current: usize,
+}
+
+impl ActiveSnippet {
+    /// Creates an active snippet from the tabstops returned by [Snippet::render],
+    /// with the snippet text inserted at `offset`.
+    pub fn new(tabstops: Vec<Vec<Range>>, offset: usize) -> Self {
+        assert!(!tabstops.is_empty(), "a snippet has at least one tabstop");
+        let tabstops = tabstops
+            .into_iter()
+            .map(|ranges| {
+                let ranges = ranges
+                    .into_iter()
+                    .map(|range| Range::new(range.anchor + offset, range.head + offset))
+                    .collect();
+                Selection::new(ranges, 0)
+            })
+            .collect();
+        Self {
+            tabstops,
+            current: 0,
+        }
+    }
+
+    /// The selection of the current tabstop.
+    pub fn current(&self) -> &Selection {
+        &self.tabstops[self.current]
+    }
+
+    /// Whether the current tabstop is the last one.
+    pub fn is_done(&self) -> bool {
+        self.current + 1 == self.tabstops.len()
+    }
+
+    pub fn forward(&mut self) -> Option<&Selection> {
+        if self.is_done() {
+            return None;
+        }
+        self.current += 1;
+        Some(self.current())
+    }
+
+    pub fn backward(&mut self) -> Option<&Selection> {
+        self.current = self.current.checked_sub(1)?;
+        Some(self.current())
+    }
+
+    pub fn map(&mut self, changes: &ChangeSet) {
+        for selection in &mut self.tabstops {
+            *selection = selection.clone().map(changes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use SnippetElement::*;
+
+    fn text(s: &str) -> SnippetElement {
+        Text(s.to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Snippet::parse("match(${1:x}) {\n\t$0\n}").elements(),
+            [
+                text("match("),
+                Placeholder {
+                    tabstop: 1,
+                    value: vec![text("x")]
+                },
+                text(") {\n\t"),
+                Tabstop { tabstop: 0 },
+                text("\n}"),
+            ]
+        );
+        assert_eq!(
+            Snippet::parse("${1|one,t\\,wo|} ${2:a ${3:b}} ${TM_FILENAME/(.*)/${1:/upcase}/g}")
+                .elements(),
+            [
+                Choice {
+                    tabstop: 1,
+                    choices: vec!["one".to_string(), "t,wo".to_string()]
+                },
+                text(" "),
+                Placeholder {
+                    tabstop: 2,
+                    value: vec![
+                        text("a "),
+                        Placeholder {
+                            tabstop: 3,
+                            value: vec![text("b")]
+                        }
+                    ]
+                },
+                text(" "),
+                Variable {
+                    name: "TM_FILENAME".to_string(),
+                    default: None
+                },
+            ]
+        );
+        // invalid syntax and escapes are kept as text
+        assert_eq!(
+            Snippet::parse("\\$1 $ ${1:a").elements(),
+            [text("$1 $ ${1:a")]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let snippet = Snippet::parse("fn ${1:name}($2) {\n\t${3:todo!()}\n}$1");
+        let (text, tabstops) = snippet.render("\n    ");
+        assert_eq!(text, "fn name() {\n    \ttodo!()\n    }name");
+        assert_eq!(
+            tabstops,
+            [
+                vec![Range::new(3, 7), Range::new(30, 34)],
+                vec![Range::point(8)],
+                vec![Range::new(17, 24)],
+                vec![Range::point(34)],
+            ]
+        );
+
+        let (text, tabstops) = Snippet::parse("${1|a,b|}$0;").render("\n");
+        assert_eq!(text, "a;");
+        assert_eq!(tabstops, [vec![Range::new(0, 1)], vec![Range::point(1)]]);
+    }
+
+    #[test]
+    fn test_render_recursive_tabstop() {
+        let (text, tabstops) = Snippet::parse("${1:$1}").render("\n");
+        assert_eq!(text, "");
+        assert_eq!(
+            tabstops,
+            [
+                vec![Range::point(0), Range::point(0)],
+                vec![Range::point(0)]
+            ]
+        );
+
+        let (text, _) = Snippet::parse("${1:a${2:b$1}} $2").render("\n");
+        assert_eq!(text, "ab ba");
+    }
+}
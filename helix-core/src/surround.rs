@@ -1,4 +1,4 @@
-use crate::{search, Range, Selection};
+use crate::{search, syntax::Syntax, Range, Selection};
 use ropey::RopeSlice;
 
 pub const PAIRS: &[(char, char)] = &[
@@ -164,6 +164,262 @@ pub fn get_surround_pos(
     Some(change_pos)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    Open,
+    Close,
+    SelfClosing,
+}
+
+/// An HTML or XML tag such as `<div class="a">` or `</div>`. Positions are char indices and
+/// the ends are exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tag {
+    pub start: usize,
+    pub end: usize,
+    pub name_start: usize,
+    pub name_end: usize,
+    kind: TagKind,
+}
+
+impl Tag {
+    pub fn name<'a>(&self, text: RopeSlice<'a>) -> RopeSlice<'a> {
+        text.slice(self.name_start..self.name_end)
+    }
+}
+
+/// An opening tag and its matching closing tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagPair {
+    pub open: Tag,
+    pub close: Tag,
+}
+
+fn is_tag_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.')
+}
+
+/// Parses the tag starting at `pos`. Comments, doctypes and processing instructions are not
+/// tags.
+fn parse_tag(text: RopeSlice, pos: usize) -> Option<Tag> {
+    let mut chars = text
+        .chars_at(pos)
+        .enumerate()
+        .map(|(i, ch)| (pos + i, ch))
+        .peekable();
+
+    if chars.next()?.1 != '<' {
+        return None;
+    }
+    let closing = chars.next_if(|&(_, ch)| ch == '/').is_some();
+
+    let &(name_start, first) = chars.peek()?;
+    if !first.is_alphabetic() {
+        return None;
+    }
+    let mut name_end = name_start;
+    while let Some((i, _)) = chars.next_if(|&(_, ch)| is_tag_name_char(ch)) {
+        name_end = i + 1;
+    }
+
+    let mut quote = None;
+    let mut prev = None;
+    for (i, ch) in chars {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => continue,
+            None => match ch {
+                '"' | '\'' if !closing => quote = Some(ch),
+                '<' => return None,
+                '>' => {
+                    let kind = if closing {
+                        TagKind::Close
+                    } else if prev == Some('/') {
+                        TagKind::SelfClosing
+                    } else {
+                        TagKind::Open
+                    };
+                    return Some(Tag {
+                        start: pos,
+                        end: i + 1,
+                        name_start,
+                        name_end,
+                        kind,
+                    });
+                }
+                _ => (),
+            },
+        }
+        prev = Some(ch);
+    }
+    None
+}
+
+/// Returns the tag containing `pos`, if any.
+fn tag_at(text: RopeSlice, pos: usize) -> Option<Tag> {
+    let mut chars = text.chars_at(pos + 1);
+    let mut start = pos + 1;
+    while let Some(ch) = chars.prev() {
+        start -= 1;
+        match ch {
+            '<' => return parse_tag(text, start).filter(|tag| tag.end > pos),
+            '>' if start != pos => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Finds the closing tag named `name` after `pos`, skipping nested tags of the same name.
+fn find_close_tag(text: RopeSlice, name: RopeSlice, pos: usize) -> Option<Tag> {
+    let mut depth = 0;
+    for (start, ch) in (pos..).zip(text.chars_at(pos)) {
+        if ch == '<' {
+            if let Some(tag) = parse_tag(text, start).filter(|tag| tag.name(text) == name) {
+                match tag.kind {
+                    TagKind::Open => depth += 1,
+                    TagKind::Close if depth == 0 => return Some(tag),
+                    TagKind::Close => depth -= 1,
+                    TagKind::SelfClosing => (),
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds the nth pair of tags around `pos` by searching the text. Tags that are never closed,
+/// such as `<br>`, are skipped.
+fn find_nth_tag_pair_textual(text: RopeSlice, pos: usize, n: usize) -> Option<TagPair> {
+    // on a tag, search from just inside of its element
+    let pos = match tag_at(text, pos) {
+        Some(tag) if tag.kind == TagKind::Open => tag.end,
+        Some(tag) if tag.kind == TagKind::Close => tag.start,
+        _ => pos,
+    };
+
+    // closing tags before `pos` that haven't been matched yet, innermost last
+    let mut unmatched: Vec<Tag> = Vec::new();
+    let mut search_from = pos;
+    let mut remaining = n;
+
+    let mut chars = text.chars_at(pos);
+    let mut start = pos;
+    while let Some(ch) = chars.prev() {
+        start -= 1;
+        if ch != '<' {
+            continue;
+        }
+        let tag = match parse_tag(text, start) {
+            Some(tag) if tag.end <= pos => tag,
+            _ => continue,
+        };
+        match tag.kind {
+            TagKind::Close => unmatched.push(tag),
+            TagKind::SelfClosing => (),
+            TagKind::Open => {
+                let name = tag.name(text);
+                if let Some(i) = unmatched.iter().rposition(|close| close.name(text) == name) {
+                    unmatched.remove(i);
+                } else if let Some(close) = find_close_tag(text, name, search_from) {
+                    remaining -= 1;
+                    if remaining == 0 {
+                        return Some(TagPair { open: tag, close });
+                    }
+                    search_from = close.end;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Returns the tags of `node` if it is an element, where the first child is an opening tag and
+/// the last child is the matching closing tag.
+fn tag_pair_of_node(text: RopeSlice, node: tree_sitter::Node) -> Option<TagPair> {
+    if node.child_count() < 2 {
+        return None;
+    }
+    let first = node.child(0)?;
+    let last = node.child(node.child_count() - 1)?;
+
+    let open = parse_tag(text, text.byte_to_char(first.start_byte())).filter(|tag| {
+        tag.kind == TagKind::Open && tag.end == text.byte_to_char(first.end_byte())
+    })?;
+    let close = parse_tag(text, text.byte_to_char(last.start_byte())).filter(|tag| {
+        tag.kind == TagKind::Close && tag.end == text.byte_to_char(last.end_byte())
+    })?;
+
+    if open.name(text) == close.name(text) {
+        Some(TagPair { open, close })
+    } else {
+        None
+    }
+}
+
+/// Finds the nth pair of tags around `pos` with the syntax tree.
+fn find_nth_tag_pair_syntax(
+    syntax: &Syntax,
+    text: RopeSlice,
+    pos: usize,
+    n: usize,
+) -> Option<TagPair> {
+    let byte_pos = text.char_to_byte(pos);
+    let tree = syntax.layer_for_byte_range(byte_pos, byte_pos).tree();
+    let mut node = tree
+        .root_node()
+        .descendant_for_byte_range(byte_pos, byte_pos)?;
+
+    let mut remaining = n;
+    loop {
+        if let Some(pair) = tag_pair_of_node(text, node) {
+            remaining -= 1;
+            if remaining == 0 {
+                return Some(pair);
+            }
+        }
+        node = node.parent()?;
+    }
+}
+
+/// Find the nth pair of HTML or XML tags around the cursor of `range`. The syntax tree is used
+/// to match the tags when there is one, with a search through the text as a fallback.
+pub fn find_nth_tag_pair(
+    syntax: Option<&Syntax>,
+    text: RopeSlice,
+    range: Range,
+    n: usize,
+) -> Option<TagPair> {
+    if text.len_chars() == 0 || n == 0 {
+        return None;
+    }
+    let pos = range.cursor(text);
+
+    syntax
+        .and_then(|syntax| find_nth_tag_pair_syntax(syntax, text, pos, n))
+        .or_else(|| find_nth_tag_pair_textual(text, pos, n))
+}
+
+/// Find the tags around every cursor, like [get_surround_pos]. Returns None if the same tags
+/// are found for multiple cursors.
+pub fn get_surround_tags(
+    syntax: Option<&Syntax>,
+    text: RopeSlice,
+    selection: &Selection,
+    skip: usize,
+) -> Option<Vec<TagPair>> {
+    let mut pairs: Vec<TagPair> = Vec::new();
+
+    for &range in selection {
+        let pair = find_nth_tag_pair(syntax, text, range, skip)?;
+        if pairs.contains(&pair) {
+            return None;
+        }
+        pairs.push(pair);
+    }
+    Some(pairs)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -302,4 +558,70 @@ mod test {
             None // overlapping surround chars
         );
     }
+
+    #[allow(clippy::type_complexity)]
+    fn check_find_nth_tag_pair(text: &str, cases: Vec<(usize, usize, Option<(&str, &str)>)>) {
+        let doc = Rope::from(text);
+        let slice = doc.slice(..);
+
+        for (cursor_pos, n, expected) in cases {
+            let pair = find_nth_tag_pair(None, slice, Range::point(cursor_pos), n);
+            let tags = pair.map(|pair| {
+                (
+                    slice.slice(pair.open.start..pair.open.end).to_string(),
+                    slice.slice(pair.close.start..pair.close.end).to_string(),
+                )
+            });
+            assert_eq!(
+                tags.as_ref()
+                    .map(|(open, close)| (open.as_str(), close.as_str())),
+                expected,
+                "cursor at {}, n = {}",
+                cursor_pos,
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_nth_tag_pair() {
+        check_find_nth_tag_pair(
+            r#"<div class="a>b"><p>some <b>text</b><br> here</p></div>"#,
+            vec![
+                // cursor on [s]ome
+                (20, 1, Some(("<p>", "</p>"))),
+                (20, 2, Some((r#"<div class="a>b">"#, "</div>"))),
+                (20, 3, None),
+                // cursor on t[e]xt, skipping the unclosed <br>
+                (29, 1, Some(("<b>", "</b>"))),
+                // cursor on he[r]e
+                (43, 1, Some(("<p>", "</p>"))),
+                // cursor on the tags
+                (18, 1, Some(("<p>", "</p>"))),
+                (48, 1, Some(("<p>", "</p>"))),
+                (3, 1, Some((r#"<div class="a>b">"#, "</div>"))),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_find_nth_tag_pair_nested() {
+        check_find_nth_tag_pair(
+            "<a><a>x</a><a/>y</a>",
+            vec![
+                // cursor on x
+                (6, 1, Some(("<a>", "</a>"))),
+                (6, 2, Some(("<a>", "</a>"))),
+                // cursor on y, after the self closing tag
+                (15, 1, Some(("<a>", "</a>"))),
+                (15, 2, None),
+            ],
+        );
+
+        let doc = Rope::from("<a><a>x</a>y</a>");
+        let slice = doc.slice(..);
+        let outer = find_nth_tag_pair(None, slice, Range::point(6), 2).unwrap();
+        assert_eq!((outer.open.start, outer.close.start), (0, 12));
+        assert_eq!(outer.close.name(slice), "a");
+    }
 }
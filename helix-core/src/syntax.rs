@@ -1,5 +1,7 @@
 use crate::{
+    auto_pairs::AutoPairs,
     chars::char_is_line_ending,
+    comment::BlockCommentToken,
     diagnostic::Severity,
     regex::Regex,
     transaction::{ChangeSet, Operation},
@@ -18,6 +20,7 @@ use std::{
     fmt,
     path::Path,
     sync::Arc,
+    time::Duration,
 };
 
 use once_cell::sync::{Lazy, OnceCell};
@@ -59,6 +62,7 @@ pub struct LanguageConfiguration {
     pub shebangs: Vec<String>, // interpreter(s) associated with language
     pub roots: Vec<String>,      // these indicate project roots <.git, Cargo.toml>
     pub comment_token: Option<String>,
+    pub block_comment_tokens: Option<BlockCommentToken>,
 
     #[serde(default, skip_serializing, deserialize_with = "deserialize_lsp_config")]
     pub config: Option<serde_json::Value>,
@@ -82,11 +86,24 @@ pub struct LanguageConfiguration {
     pub language_server: Option<LanguageServerConfiguration>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub indent: Option<IndentationConfiguration>,
+    /// Groups of words cycled by increment and decrement, in addition to the
+    /// [default groups](crate::increment::word::DEFAULT_GROUPS).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub increment_groups: Vec<Vec<String>>,
+
+    /// The pairs to auto-close, defaults to [auto_pairs::PAIRS](crate::auto_pairs::PAIRS).
+    #[serde(default, skip_serializing)]
+    pub auto_pairs: Option<AutoPairs>,
+    /// Whether to soft wrap long lines, overrides the `soft-wrap` editor option.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_wrap: Option<bool>,
 
     #[serde(skip)]
     pub(crate) indent_query: OnceCell<Option<IndentQuery>>,
     #[serde(skip)]
     pub(crate) textobject_query: OnceCell<Option<TextObjectQuery>>,
+    #[serde(skip)]
+    pub(crate) fold_query: OnceCell<Option<TextObjectQuery>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -106,15 +123,76 @@ pub struct IndentationConfiguration {
     pub unit: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+/// The indent query of a language, read from `indents.scm`. See the [indent](crate::indent)
+/// module for the captures and predicates it supports.
+#[derive(Debug)]
 pub struct IndentQuery {
+    pub query: Query,
+}
+
+impl IndentQuery {
+    pub fn new(language: Grammar, source: &str) -> Result<Self, String> {
+        let query = Query::new(language, source).map_err(|err| err.to_string())?;
+
+        for pattern in 0..query.pattern_count() {
+            for predicate in query.general_predicates(pattern) {
+                use QueryPredicateArg::{Capture, String};
+                let valid = matches!(
+                    (predicate.operator.as_ref(), predicate.args.as_slice()),
+                    ("not-kind-eq?", [Capture(_), String(_)])
+                        | ("same-line?" | "not-same-line?", [Capture(_), Capture(_)])
+                );
+                if !valid {
+                    return Err(format!("Invalid predicate \"{}\"", predicate.operator));
+                }
+            }
+            for property in query.property_settings(pattern) {
+                if !matches!(
+                    (property.key.as_ref(), property.value.as_deref()),
+                    ("scope", Some("all" | "tail"))
+                ) {
+                    return Err(format!("Invalid property \"{}\"", property.key));
+                }
+            }
+        }
+
+        Ok(Self { query })
+    }
+
+    /// Creates the query from the node kinds listed in the older `indents.toml` format.
+    fn from_legacy(language: Grammar, legacy: &LegacyIndentQuery) -> Result<Self, String> {
+        // the format doesn't tell named and anonymous node kinds apart
+        let patterns = |kinds: &[String]| {
+            let mut patterns = Vec::new();
+            for kind in kinds {
+                if language.id_for_node_kind(kind, true) != 0 {
+                    patterns.push(format!("({})", kind));
+                }
+                if language.id_for_node_kind(kind, false) != 0 {
+                    patterns.push(format!("{:?}", kind));
+                }
+            }
+            patterns
+        };
+
+        let mut source = String::new();
+        for (kinds, capture) in [(&legacy.indent, "indent"), (&legacy.outdent, "outdent")] {
+            let patterns = patterns(kinds);
+            if !patterns.is_empty() {
+                source.push_str(&format!("[{}] @{}\n", patterns.join(" "), capture));
+            }
+        }
+        Self::new(language, &source)
+    }
+}
+
+/// The node kinds that indent and outdent, as listed in `indents.toml`.
+#[derive(Debug, Default, Deserialize)]
+struct LegacyIndentQuery {
     #[serde(default)]
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
-    pub indent: HashSet<String>,
+    indent: Vec<String>,
     #[serde(default)]
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
-    pub outdent: HashSet<String>,
+    outdent: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -132,7 +210,21 @@ impl TextObjectQuery {
         slice: RopeSlice<'a>,
         cursor: &'a mut QueryCursor,
     ) -> Option<impl Iterator<Item = Node<'a>>> {
-        let capture_idx = self.query.capture_index_for_name(capture_name)?;
+        self.capture_nodes_any(&[capture_name], node, slice, cursor)
+    }
+
+    /// Like [capture_nodes](Self::capture_nodes), for the first of the `capture_names`
+    /// the query has.
+    pub fn capture_nodes_any<'a>(
+        &'a self,
+        capture_names: &[&str],
+        node: Node<'a>,
+        slice: RopeSlice<'a>,
+        cursor: &'a mut QueryCursor,
+    ) -> Option<impl Iterator<Item = Node<'a>>> {
+        let capture_idx = capture_names
+            .iter()
+            .find_map(|name| self.query.capture_index_for_name(name))?;
         let captures = cursor.captures(&self.query, node, RopeProvider(slice));
 
         captures
@@ -196,14 +288,7 @@ impl LanguageConfiguration {
         if highlights_query.is_empty() {
             None
         } else {
-            let language = get_language(
-                &crate::RUNTIME_DIR,
-                self.tree_sitter_library
-                    .as_deref()
-                    .unwrap_or(&self.language_id),
-            )
-            .map_err(|e| log::info!("{}", e))
-            .ok()?;
+            let language = self.load_grammar()?;
             let config = HighlightConfiguration::new(
                 language,
                 &highlights_query,
@@ -217,6 +302,27 @@ impl LanguageConfiguration {
         }
     }
 
+    fn load_grammar(&self) -> Option<Grammar> {
+        get_language(
+            &crate::RUNTIME_DIR,
+            self.tree_sitter_library
+                .as_deref()
+                .unwrap_or(&self.language_id),
+        )
+        .map_err(|e| log::info!("{}", e))
+        .ok()
+    }
+
+    /// The grammar of the language, taken from the highlight configuration when it is already
+    /// initialized. Queries compiled for the grammar are cached, so they must not depend on
+    /// whether the language was highlighted first.
+    fn grammar(&self) -> Option<Grammar> {
+        match self.highlight_config.get() {
+            Some(Some(config)) => Some(config.language),
+            _ => self.load_grammar(),
+        }
+    }
+
     pub fn reconfigure(&self, scopes: &[String]) {
         if let Some(Some(config)) = self.highlight_config.get() {
             config.configure(scopes);
@@ -237,9 +343,20 @@ impl LanguageConfiguration {
         self.indent_query
             .get_or_init(|| {
                 let language = self.language_id.to_ascii_lowercase();
+                let grammar = self.grammar()?;
 
-                let toml = load_runtime_file(&language, "indents.toml").ok()?;
-                toml::from_slice(toml.as_bytes()).ok()
+                let query_text = read_query(&language, "indents.scm");
+                let query = if !query_text.is_empty() {
+                    IndentQuery::new(grammar, &query_text)
+                } else {
+                    let toml = load_runtime_file(&language, "indents.toml").ok()?;
+                    let legacy = toml::from_slice(toml.as_bytes()).ok()?;
+                    IndentQuery::from_legacy(grammar, &legacy)
+                };
+
+                query
+                    .map_err(|err| log::error!("Invalid indent query for {}: {}", language, err))
+                    .ok()
             })
             .as_ref()
     }
@@ -256,6 +373,24 @@ impl LanguageConfiguration {
             .as_ref()
     }
 
+    /// The `folds.scm` query, whose `@fold` captures are the nodes that can be folded.
+    pub fn fold_query(&self) -> Option<&TextObjectQuery> {
+        self.fold_query
+            .get_or_init(|| {
+                let language = self.language_id.to_ascii_lowercase();
+                let query_text = read_query(&language, "folds.scm");
+                if query_text.is_empty() {
+                    return None;
+                }
+                let grammar = self.grammar()?;
+                Query::new(grammar, &query_text)
+                    .map(|query| TextObjectQuery { query })
+                    .map_err(|err| log::error!("Invalid folds query for {}: {}", language, err))
+                    .ok()
+            })
+            .as_ref()
+    }
+
     pub fn scope(&self) -> &str {
         &self.scope
     }
@@ -366,6 +501,20 @@ impl Loader {
         None
     }
 
+    /// Finds the language configuration a highlight configuration was created from.
+    pub fn language_config_for_highlight_config(
+        &self,
+        highlight_config: &Arc<HighlightConfiguration>,
+    ) -> Option<Arc<LanguageConfiguration>> {
+        self.language_configs
+            .iter()
+            .find(|config| match config.highlight_config.get() {
+                Some(Some(config)) => Arc::ptr_eq(config, highlight_config),
+                _ => false,
+            })
+            .cloned()
+    }
+
     pub fn set_scopes(&self, scopes: Vec<String>) {
         self.scopes.store(Arc::new(scopes));
 
@@ -397,11 +546,26 @@ thread_local! {
     })
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Syntax {
     layers: HopSlotMap<LayerId, LanguageLayer>,
     root: LayerId,
     loader: Arc<Loader>,
+    /// The byte ranges whose injections were parsed since the last edit, such as the lines
+    /// visible in each view. Overlapping ranges are merged.
+    injections_ranges: Vec<ops::Range<usize>>,
+}
+
+/// Adds `range` to `ranges`, merging it with the ranges it overlaps or touches.
+fn add_range(ranges: &mut Vec<ops::Range<usize>>, mut range: ops::Range<usize>) {
+    ranges.retain(|other| {
+        let overlaps = other.start <= range.end && range.start <= other.end;
+        if overlaps {
+            range = range.start.min(other.start)..range.end.max(other.end);
+        }
+        !overlaps
+    });
+    ranges.push(range);
 }
 
 fn byte_range_to_str(range: std::ops::Range<usize>, source: RopeSlice) -> Cow<str> {
@@ -422,6 +586,7 @@ impl Syntax {
                 start_point: Point::new(0, 0),
                 end_point: Point::new(usize::MAX, usize::MAX),
             }],
+            dirty: true,
         };
 
         // track scope_descriptor: a Vec of scopes for item in tree
@@ -433,6 +598,7 @@ impl Syntax {
             root,
             layers,
             loader,
+            injections_ranges: Vec::new(),
         };
 
         syntax
@@ -442,27 +608,29 @@ impl Syntax {
         syntax
     }
 
+    /// Updates the syntax trees for the changes between `old_source` and `source`, and
+    /// re-parses every layer.
     pub fn update(
         &mut self,
         old_source: &Rope,
         source: &Rope,
         changeset: &ChangeSet,
     ) -> Result<(), Error> {
-        let mut queue = VecDeque::new();
-        queue.push_back(self.root);
-
-        let scopes = self.loader.scopes.load();
-        let injection_callback = |language: &str| {
-            self.loader
-                .language_configuration_for_injection_string(language)
-                .and_then(|language_config| language_config.highlight_config(&scopes))
-        };
+        self.edit(old_source, changeset);
+        self.parse(source, 0..usize::MAX, None, None)
+    }
 
+    /// Applies the changes between `old_source` and the new text to the syntax trees and the
+    /// ranges of the layers, without parsing. Until they are parsed again with
+    /// [`Syntax::parse`], the edited trees keep highlighting the text that didn't change.
+    pub fn edit(&mut self, old_source: &Rope, changeset: &ChangeSet) {
         // Convert the changeset into tree sitter edits.
         let edits = generate_edits(old_source, changeset);
 
         // Use the edits to update all layers markers
         if !edits.is_empty() {
+            self.injections_ranges.clear();
+
             fn point_add(a: Point, b: Point) -> Point {
                 if b.row > 0 {
                     Point::new(a.row.saturating_add(b.row), b.column)
@@ -479,6 +647,17 @@ impl Syntax {
             }
 
             for layer in &mut self.layers.values_mut() {
+                layer.dirty = true;
+
+                // If a tree already exists, notify it of changes.
+                if let Some(tree) = &mut layer.tree {
+                    for edit in edits.iter().rev() {
+                        // Apply the edits in reverse.
+                        // If we applied them in order then edit 1 would disrupt the positioning of edit 2.
+                        tree.edit(edit);
+                    }
+                }
+
                 // The root layer always covers the whole range (0..usize::MAX)
                 if layer.depth == 0 {
                     continue;
@@ -544,18 +723,46 @@ impl Syntax {
                 }
             }
         }
+    }
+
+    /// Re-parses the layers that were edited since they were last parsed. Injections are only
+    /// looked for within the byte range `range`, the injected layers outside of it are kept
+    /// as they are. An empty range only parses the root layer.
+    ///
+    /// Parsing halts with [`Error::Cancelled`] once `timeout` elapsed or when
+    /// `cancellation_flag` is set, leaving the layers that weren't parsed yet edited.
+    pub fn parse(
+        &mut self,
+        source: &Rope,
+        range: ops::Range<usize>,
+        timeout: Option<Duration>,
+        cancellation_flag: Option<&AtomicUsize>,
+    ) -> Result<(), Error> {
+        let mut queue = VecDeque::new();
+        queue.push_back(self.root);
+
+        let scopes = self.loader.scopes.load();
+        let injection_callback = |language: &str| {
+            self.loader
+                .language_configuration_for_injection_string(language)
+                .and_then(|language_config| language_config.highlight_config(&scopes))
+        };
 
         PARSER.with(|ts_parser| {
             let ts_parser = &mut ts_parser.borrow_mut();
             let mut cursor = ts_parser.cursors.pop().unwrap_or_else(QueryCursor::new);
-            // TODO: might need to set cursor range
-            cursor.set_byte_range(0..usize::MAX);
+            cursor.set_byte_range(range.clone());
+
+            ts_parser
+                .parser
+                .set_timeout_micros(timeout.map_or(0, |timeout| timeout.as_micros() as u64));
+            // Safety: the flag is unset before returning, so the parser doesn't outlive it.
+            unsafe { ts_parser.parser.set_cancellation_flag(cancellation_flag) };
 
             let source_slice = source.slice(..);
 
             let mut touched = HashSet::new();
-
-            // TODO: we should be able to avoid editing & parsing layers with ranges earlier in the document before the edit
+            let mut result = Ok(());
 
             while let Some(layer_id) = queue.pop_front() {
                 // Mark the layer as touched
@@ -563,17 +770,17 @@ impl Syntax {
 
                 let layer = &mut self.layers[layer_id];
 
-                // If a tree already exists, notify it of changes.
-                if let Some(tree) = &mut layer.tree {
-                    for edit in edits.iter().rev() {
-                        // Apply the edits in reverse.
-                        // If we applied them in order then edit 1 would disrupt the positioning of edit 2.
-                        tree.edit(edit);
+                // Re-parse the tree if it was edited.
+                if layer.dirty || layer.tree.is_none() {
+                    if let Err(err) = layer.parse(&mut ts_parser.parser, source) {
+                        result = Err(err);
+                        break;
                     }
                 }
 
-                // Re-parse the tree.
-                layer.parse(&mut ts_parser.parser, source)?;
+                if range.is_empty() {
+                    continue;
+                }
 
                 // Switch to an immutable borrow.
                 let layer = &self.layers[layer_id];
@@ -674,6 +881,7 @@ impl Syntax {
                             config,
                             depth,
                             ranges,
+                            dirty: true,
                         })
                     });
 
@@ -686,18 +894,70 @@ impl Syntax {
 
             // Return the cursor back in the pool.
             ts_parser.cursors.push(cursor);
+            ts_parser.parser.set_timeout_micros(0);
+            unsafe { ts_parser.parser.set_cancellation_flag(None) };
+
+            if result.is_err() {
+                // Start the next parse over instead of resuming this one, and drop the
+                // injections that were found but not parsed yet.
+                ts_parser.parser.reset();
+                self.layers.retain(|_, layer| layer.tree.is_some());
+                return result;
+            }
 
-            // Remove all untouched layers
-            self.layers.retain(|id, _| touched.contains(&id));
+            // Remove the untouched layers within the range, their injections are gone
+            self.layers
+                .retain(|id, layer| touched.contains(&id) || !layer.intersects(&range));
+            if !range.is_empty() {
+                add_range(&mut self.injections_ranges, range);
+            }
 
             Ok(())
         })
     }
 
+    /// Whether the syntax trees are parsed, along with the injections within the byte range
+    /// `range`.
+    pub fn is_parsed(&self, range: ops::Range<usize>) -> bool {
+        !self.layers[self.root].dirty
+            && (range.is_empty()
+                || self
+                    .injections_ranges
+                    .iter()
+                    .any(|parsed| parsed.start <= range.start && range.end <= parsed.end))
+    }
+
     pub fn tree(&self) -> &Tree {
         self.layers[self.root].tree()
     }
 
+    /// Returns the innermost layer covering the byte range `start..end`, such as the layer of
+    /// JavaScript injected into an HTML `<script>` tag.
+    pub fn layer_for_byte_range(&self, start: usize, end: usize) -> &LanguageLayer {
+        self.layers
+            .values()
+            .filter(|layer| {
+                layer
+                    .ranges
+                    .iter()
+                    .any(|range| range.start_byte <= start && end <= range.end_byte)
+            })
+            .max_by_key(|layer| layer.depth)
+            .unwrap_or(&self.layers[self.root])
+    }
+
+    /// Returns the language configuration of the innermost layer covering the byte range
+    /// `start..end`.
+    pub fn language_config_for_byte_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Option<Arc<LanguageConfiguration>> {
+        let layer = self.layer_for_byte_range(start, end);
+        self.loader
+            .language_config_for_highlight_config(&layer.config)
+    }
+
     /// Iterate over the highlighted regions for a given slice of source code.
     pub fn highlight_iter<'a>(
         &'a self,
@@ -787,7 +1047,7 @@ impl Syntax {
     // TODO: Folding
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LanguageLayer {
     // mode
     // grammar
@@ -795,6 +1055,8 @@ pub struct LanguageLayer {
     pub(crate) tree: Option<Tree>,
     pub ranges: Vec<Range>,
     pub depth: usize,
+    /// Whether the tree was edited since it was last parsed.
+    pub(crate) dirty: bool,
 }
 
 impl LanguageLayer {
@@ -803,6 +1065,13 @@ impl LanguageLayer {
         self.tree.as_ref().unwrap()
     }
 
+    /// Whether any of the ranges of the layer intersect the byte range `range`.
+    fn intersects(&self, range: &ops::Range<usize>) -> bool {
+        self.ranges
+            .iter()
+            .any(|layer| layer.start_byte < range.end && range.start < layer.end_byte)
+    }
+
     fn parse(&mut self, parser: &mut Parser, source: &Rope) -> Result<(), Error> {
         parser.set_included_ranges(&self.ranges).unwrap();
 
@@ -810,7 +1079,6 @@ impl LanguageLayer {
             .set_language(self.config.language)
             .map_err(|_| Error::InvalidLanguage)?;
 
-        // unsafe { syntax.parser.set_cancellation_flag(cancellation_flag) };
         let tree = parser
             .parse_with(
                 &mut |byte, _| {
@@ -825,8 +1093,8 @@ impl LanguageLayer {
                 self.tree.as_ref(),
             )
             .ok_or(Error::Cancelled)?;
-        // unsafe { ts_parser.parser.set_cancellation_flag(None) };
         self.tree = Some(tree);
+        self.dirty = false;
         Ok(())
     }
 }
@@ -940,7 +1208,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{iter, mem, ops, str, usize};
 use tree_sitter::{
     Language as Grammar, Node, Parser, Point, Query, QueryCaptures, QueryCursor, QueryError,
-    QueryMatch, Range, TextProvider, Tree,
+    QueryMatch, QueryPredicateArg, Range, TextProvider, Tree,
 };
 
 const CANCELLATION_CHECK_INTERVAL: usize = 100;
@@ -1011,7 +1279,7 @@ struct HighlightIter<'a> {
 }
 
 // Adapter to convert rope chunks to bytes
-struct ChunksBytes<'a> {
+pub(crate) struct ChunksBytes<'a> {
     chunks: ropey::iter::Chunks<'a>,
 }
 impl<'a> Iterator for ChunksBytes<'a> {
@@ -1021,7 +1289,7 @@ impl<'a> Iterator for ChunksBytes<'a> {
     }
 }
 
-struct RopeProvider<'a>(RopeSlice<'a>);
+pub(crate) struct RopeProvider<'a>(pub RopeSlice<'a>);
 impl<'a> TextProvider<'a> for RopeProvider<'a> {
     type I = ChunksBytes<'a>;
 
@@ -1861,6 +2129,62 @@ mod test {
         assert_eq!(struct_node.kind(), "struct_item");
     }
 
+    #[test]
+    fn test_layer_for_byte_range() {
+        // set runtime path so we can find the grammar
+        let mut runtime = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            injection-regex = "rust"
+            file-types = ["rs"]
+            roots = []
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+        let language_config = loader.language_config_for_scope("source.rust").unwrap();
+        let highlight_config = language_config.highlight_config(&[]).unwrap();
+
+        let source = Rope::from("fn main() { println!(\"{}\", 1); }");
+        let syntax = Syntax::new(&source, highlight_config, loader.clone());
+
+        // `fn main`
+        let layer = syntax.layer_for_byte_range(0, 7);
+        assert_eq!(layer.depth, 0);
+        assert!(std::ptr::eq(layer, &syntax.layers[syntax.root]));
+
+        // `"{}"` in the token tree of the macro, which is injected
+        let layer = syntax.layer_for_byte_range(21, 25);
+        assert_eq!(layer.depth, 1);
+        assert!(loader
+            .language_config_for_highlight_config(&layer.config)
+            .is_some());
+
+        // a range spanning the macro and the surrounding code
+        assert_eq!(syntax.layer_for_byte_range(12, 28).depth, 0);
+    }
+
+    #[test]
+    fn test_add_range() {
+        let mut ranges = Vec::new();
+        add_range(&mut ranges, 10..20);
+        add_range(&mut ranges, 30..40);
+        assert_eq!(ranges, [10..20, 30..40]);
+        // a range already covered
+        add_range(&mut ranges, 12..18);
+        assert_eq!(ranges, [30..40, 10..20]);
+        // a range joining both
+        add_range(&mut ranges, 20..30);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 10..40);
+    }
+
     #[test]
     fn test_input_edits() {
         use tree_sitter::InputEdit;
@@ -1919,7 +2243,7 @@ mod test {
     #[test]
     fn test_load_runtime_file() {
         // Test to make sure we can load some data from the runtime directory.
-        let contents = load_runtime_file("rust", "indents.toml").unwrap();
+        let contents = load_runtime_file("rust", "indents.scm").unwrap();
         assert!(!contents.is_empty());
 
         let results = load_runtime_file("rust", "does-not-exist");
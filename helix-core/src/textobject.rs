@@ -5,7 +5,7 @@ use tree_sitter::{Node, QueryCursor};
 
 use crate::chars::{categorize_char, char_is_whitespace, CharCategory};
 use crate::graphemes::next_grapheme_boundary;
-use crate::movement::Direction;
+use crate::movement::{is_sub_word_boundary, Direction};
 use crate::surround;
 use crate::syntax::LanguageConfiguration;
 use crate::Range;
@@ -107,6 +107,66 @@ pub fn textobject_word(
     }
 }
 
+/// Selects the sub word under the cursor, such as `Case` in `camelCase` or `snake_case`, and the
+/// `count - 1` sub words after it in the same word. Around also selects the underscores after the
+/// sub words, or before them when there are none after. Outside of words, this behaves like
+/// [textobject_word].
+pub fn textobject_sub_word(
+    slice: RopeSlice,
+    range: Range,
+    textobject: TextObject,
+    count: usize,
+) -> Range {
+    let pos = range.cursor(slice);
+
+    match slice.get_char(pos) {
+        Some(ch) if ch != '_' && categorize_char(ch) == CharCategory::Word => (),
+        _ => return textobject_word(slice, range, textobject, count, false),
+    }
+
+    let mut start = pos;
+    while start > 0 && !is_sub_word_boundary(slice.char(start - 1), slice.char(start)) {
+        start -= 1;
+    }
+    let len = slice.len_chars();
+    let mut end = pos + 1;
+    while end < len && !is_sub_word_boundary(slice.char(end - 1), slice.char(end)) {
+        end += 1;
+    }
+    for _ in 1..count {
+        let mut next = end;
+        while next < len && slice.char(next) == '_' {
+            next += 1;
+        }
+        match slice.get_char(next) {
+            Some(ch) if ch != '_' && categorize_char(ch) == CharCategory::Word => (),
+            _ => break,
+        }
+        end = next + 1;
+        while end < len && !is_sub_word_boundary(slice.char(end - 1), slice.char(end)) {
+            end += 1;
+        }
+    }
+
+    match textobject {
+        TextObject::Inside => Range::new(start, end),
+        TextObject::Around => {
+            let underscores_right = slice.chars_at(end).take_while(|&c| c == '_').count();
+
+            if underscores_right > 0 {
+                Range::new(start, end + underscores_right)
+            } else {
+                let underscores_left = {
+                    let mut iter = slice.chars_at(start);
+                    iter.reverse();
+                    iter.take_while(|&c| c == '_').count()
+                };
+                Range::new(start - underscores_left, end)
+            }
+        }
+    }
+}
+
 pub fn textobject_surround(
     slice: RopeSlice,
     range: Range,
@@ -372,4 +432,93 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_textobject_sub_word() {
+        // (text, [(char position, textobject, final range), ...])
+        let tests = &[
+            (
+                "camelCaseWords",
+                vec![
+                    (0, Inside, (0, 5)),
+                    (7, Inside, (5, 9)),
+                    (13, Inside, (9, 14)),
+                    (7, Around, (5, 9)),
+                ],
+            ),
+            (
+                "snake_case_words",
+                vec![
+                    (2, Inside, (0, 5)),
+                    (7, Inside, (6, 10)),
+                    (7, Around, (6, 11)),
+                    (13, Around, (10, 16)),
+                ],
+            ),
+            (
+                "utf8_Decoder x",
+                vec![
+                    (1, Inside, (0, 3)),
+                    (3, Inside, (3, 4)),
+                    (3, Around, (3, 5)),
+                ],
+            ),
+        ];
+
+        for (sample, scenario) in tests {
+            let doc = Rope::from(*sample);
+            let slice = doc.slice(..);
+            for &case in scenario {
+                let (pos, objtype, expected_range) = case;
+                let result = textobject_sub_word(slice, Range::point(pos), objtype, 1);
+                assert_eq!(
+                    result,
+                    expected_range.into(),
+                    "\nCase failed: {:?} - {:?}",
+                    sample,
+                    case
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_textobject_sub_word_count() {
+        // (text, [(char position, textobject, count, final range), ...])
+        let tests = &[
+            (
+                "camelCaseWords",
+                vec![
+                    (0, Inside, 2, (0, 9)),
+                    (0, Inside, 5, (0, 14)),
+                    (7, Inside, 2, (5, 14)),
+                ],
+            ),
+            (
+                "snake_case_words x",
+                vec![
+                    (2, Inside, 2, (0, 10)),
+                    (2, Around, 2, (0, 11)),
+                    (2, Inside, 5, (0, 16)),
+                    (13, Around, 2, (10, 16)),
+                ],
+            ),
+        ];
+
+        for (sample, scenario) in tests {
+            let doc = Rope::from(*sample);
+            let slice = doc.slice(..);
+            for &case in scenario {
+                let (pos, objtype, count, expected_range) = case;
+                let result = textobject_sub_word(slice, Range::point(pos), objtype, count);
+                assert_eq!(
+                    result,
+                    expected_range.into(),
+                    "\nCase failed: {:?} - {:?}",
+                    sample,
+                    case
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,274 @@
+//! Soft wrapping of long lines into several rows on screen.
+
+use std::borrow::Cow;
+
+use crate::{
+    fold::{Folds, VisibleLines},
+    graphemes::{grapheme_width, RopeGraphemes},
+    line_ending::line_end_char_index,
+    LineEnding, RopeSlice,
+};
+
+fn width(grapheme: &str, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        tab_width
+    } else {
+        grapheme_width(grapheme)
+    }
+}
+
+/// The rows a line is displayed on. A line that isn't wrapped has a single row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRows {
+    /// Char offsets into the line where each row starts, the first one is 0.
+    starts: Vec<usize>,
+    /// The width of the indentation of the rows after the first one.
+    indent: usize,
+}
+
+impl LineRows {
+    /// A line that isn't wrapped.
+    pub fn single() -> Self {
+        Self {
+            starts: vec![0],
+            indent: 0,
+        }
+    }
+
+    /// Wraps `line` to rows of `width` columns, at whitespace if possible. The rows after
+    /// the first one are indented like the line, unless that leaves less than half of the
+    /// width for the text.
+    pub fn new(line: RopeSlice, width: usize, tab_width: usize) -> Self {
+        let indent: usize = line
+            .chars()
+            .map_while(|ch| match ch {
+                ' ' => Some(1),
+                '\t' => Some(tab_width),
+                _ => None,
+            })
+            .sum();
+        let indent = if indent * 2 > width { 0 } else { indent };
+
+        let mut starts = vec![0];
+        let mut col = 0;
+        let mut offset = 0;
+        // the offset and column after the last whitespace on the row
+        let mut last_break = None;
+
+        for grapheme in RopeGraphemes::new(line) {
+            if LineEnding::from_rope_slice(&grapheme).is_some() {
+                break;
+            }
+            let grapheme = Cow::from(grapheme);
+            let grapheme_width = self::width(&grapheme, tab_width);
+            let row_start = *starts.last().unwrap();
+
+            if col + grapheme_width > width && offset > row_start {
+                match last_break {
+                    Some((break_offset, break_col)) if break_offset > row_start => {
+                        starts.push(break_offset);
+                        col = indent + col - break_col;
+                    }
+                    _ => {
+                        starts.push(offset);
+                        col = indent;
+                    }
+                }
+                last_break = None;
+            }
+
+            col += grapheme_width;
+            offset += grapheme.chars().count();
+            if grapheme == " " || grapheme == "\t" {
+                last_break = Some((offset, col));
+            }
+        }
+
+        Self { starts, indent }
+    }
+
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// The char offset into the line where `row` starts.
+    pub fn start(&self, row: usize) -> usize {
+        self.starts[row]
+    }
+
+    /// The row of the char at `offset` into the line.
+    pub fn row_at(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// The column the text of `row` starts at.
+    pub fn indent(&self, row: usize) -> usize {
+        if row == 0 {
+            0
+        } else {
+            self.indent
+        }
+    }
+}
+
+/// The rows a document is displayed on: the lines hidden by [`Folds`] are skipped, and
+/// lines are soft wrapped if a width is given. Rows are identified by their line and their
+/// index within the line.
+#[derive(Debug, Clone)]
+pub struct Rows<'a> {
+    text: RopeSlice<'a>,
+    lines: VisibleLines,
+    width: Option<usize>,
+    tab_width: usize,
+}
+
+impl<'a> Rows<'a> {
+    pub fn new(text: RopeSlice<'a>, folds: &Folds, width: Option<usize>, tab_width: usize) -> Self {
+        Self {
+            text,
+            lines: folds.visible_lines(text),
+            width,
+            tab_width,
+        }
+    }
+
+    pub fn text(&self) -> RopeSlice<'a> {
+        self.text
+    }
+
+    pub fn visible_lines(&self) -> &VisibleLines {
+        &self.lines
+    }
+
+    pub fn is_wrapped(&self) -> bool {
+        self.width.is_some()
+    }
+
+    /// The rows of `line`.
+    pub fn line_rows(&self, line: usize) -> LineRows {
+        match self.width {
+            Some(width) => LineRows::new(self.text.line(line), width, self.tab_width),
+            None => LineRows::single(),
+        }
+    }
+
+    /// Iterates forward over the rows of the visible lines, starting from `row` of `line`,
+    /// or from its last row if it has fewer rows.
+    pub fn iter_from(&self, line: usize, row: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let first = line;
+        self.lines.iter_from(line).flat_map(move |line| {
+            let rows = self.line_rows(line).len();
+            let start = if line == first { row.min(rows - 1) } else { 0 };
+            (start..rows).map(move |row| (line, row))
+        })
+    }
+
+    /// Iterates backward over the rows of the visible lines, starting from `row` of `line`,
+    /// or from its last row if it has fewer rows.
+    pub fn iter_back_from(
+        &self,
+        line: usize,
+        row: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let first = line;
+        self.lines.iter_back_from(line).flat_map(move |line| {
+            let rows = self.line_rows(line).len();
+            let rows = if line == first {
+                rows.min(row.saturating_add(1))
+            } else {
+                rows
+            };
+            (0..rows).rev().map(move |row| (line, row))
+        })
+    }
+
+    /// The line, the row within the line and the column within the row of `pos`.
+    pub fn coords_at_pos(&self, pos: usize) -> (usize, usize, usize) {
+        let line = self.text.char_to_line(pos);
+        let line_start = self.text.line_to_char(line);
+        let rows = self.line_rows(line);
+        let row = rows.row_at(pos - line_start);
+
+        let row_start = line_start + rows.start(row);
+        let col = RopeGraphemes::new(self.text.slice(row_start..pos))
+            .map(|grapheme| width(&Cow::from(grapheme), self.tab_width))
+            .sum::<usize>();
+        (line, row, rows.indent(row) + col)
+    }
+
+    /// The position at `col` on `row` of `line`, or the end of the row if it is shorter.
+    pub fn pos_at_coords(&self, line: usize, row: usize, col: usize) -> usize {
+        let rows = self.line_rows(line);
+        let line_start = self.text.line_to_char(line);
+        let line_end = line_end_char_index(&self.text, line);
+        let row_end = if row + 1 < rows.len() {
+            // stay before the first char of the next row
+            line_start + rows.start(row + 1) - 1
+        } else {
+            line_end
+        };
+
+        let mut pos = line_start + rows.start(row);
+        let mut selected = rows.indent(row);
+        let line_slice = self.text.slice(pos..self.text.line_to_char(line + 1));
+        for grapheme in RopeGraphemes::new(line_slice) {
+            if selected >= col {
+                break;
+            }
+            selected += width(&Cow::from(grapheme), self.tab_width);
+            pos += grapheme.chars().count();
+        }
+
+        pos.min(row_end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    #[test]
+    fn test_line_rows() {
+        let text = Rope::from("  hello wide world\n");
+        let rows = LineRows::new(text.slice(..), 10, 4);
+        // "  hello " | "  wide " | "  world"
+        assert_eq!(rows.starts, [0, 8, 13]);
+        assert_eq!(rows.indent(1), 2);
+        assert_eq!(rows.row_at(7), 0);
+        assert_eq!(rows.row_at(8), 1);
+
+        // no whitespace to break at
+        let text = Rope::from("abcdefgh");
+        assert_eq!(LineRows::new(text.slice(..), 3, 4).starts, [0, 3, 6]);
+    }
+
+    #[test]
+    fn test_rows() {
+        let text = Rope::from("a b c d\nxy\n");
+        let text = text.slice(..);
+        let rows = Rows::new(text, &Folds::default(), Some(4), 4);
+
+        assert_eq!(
+            rows.iter_from(0, 0).collect::<Vec<_>>(),
+            [(0, 0), (0, 1), (1, 0), (2, 0)]
+        );
+        assert_eq!(
+            rows.iter_from(0, 1).collect::<Vec<_>>(),
+            [(0, 1), (1, 0), (2, 0)]
+        );
+        assert_eq!(rows.iter_from(1, 5).next(), Some((1, 0)));
+        assert_eq!(
+            rows.iter_back_from(1, 0).collect::<Vec<_>>(),
+            [(1, 0), (0, 1), (0, 0)]
+        );
+        assert_eq!(rows.coords_at_pos(5), (0, 1, 1));
+        assert_eq!(rows.pos_at_coords(0, 1, 1), 5);
+        // past the end of a row that isn't the last one of the line
+        assert_eq!(rows.pos_at_coords(0, 0, 10), 3);
+    }
+}
@@ -1,6 +1,6 @@
 use helix_core::{merge_toml_values, pos_at_coords, syntax, Selection};
 use helix_lsp::{lsp, util::lsp_pos_to_pos, LspProgressMap};
-use helix_view::{theme, Editor};
+use helix_view::{diff::Side, theme, Editor};
 use serde_json::json;
 
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     commands::{align_view, apply_workspace_edit, Align},
     compositor::Compositor,
     config::Config,
-    job::Jobs,
+    job::{self, Jobs},
     ui,
 };
 
@@ -200,6 +200,9 @@ impl Application {
     }
 
     fn render(&mut self) {
+        self.parse_syntax();
+        self.diff_documents();
+
         let editor = &mut self.editor;
         let compositor = &mut self.compositor;
         let jobs = &mut self.jobs;
@@ -213,6 +216,67 @@ impl Application {
         compositor.render(&mut cx);
     }
 
+    /// Parses the syntax trees of the documents whose last edits couldn't be parsed in time
+    /// on a blocking thread, and the injections that are visible.
+    fn parse_syntax(&mut self) {
+        for doc in self.editor.documents.values_mut() {
+            if let Some(parse) = doc.parse_in_background() {
+                let doc_id = doc.id();
+                self.jobs.callback(async move {
+                    let parsed = parse.await;
+                    let call: job::Callback =
+                        Box::new(move |editor: &mut Editor, _compositor: &mut Compositor| {
+                            if let Some(doc) = editor.documents.get_mut(&doc_id) {
+                                doc.set_parsed_syntax(parsed);
+                            }
+                        });
+                    Ok(call)
+                });
+            }
+        }
+
+        self.editor.parse_injections();
+    }
+
+    /// Computes the diff hunks of the documents that changed since their last diff on a
+    /// blocking thread.
+    fn diff_documents(&mut self) {
+        for doc in self.editor.documents.values_mut() {
+            if let Some(diff) = doc.diff_in_background() {
+                let doc_id = doc.id();
+                self.jobs.callback(async move {
+                    let diff = diff.await;
+                    let call: job::Callback =
+                        Box::new(move |editor: &mut Editor, _compositor: &mut Compositor| {
+                            if let Some(doc) = editor.documents.get_mut(&doc_id) {
+                                doc.set_diff_hunks(diff);
+                            }
+                        });
+                    Ok(call)
+                });
+            }
+        }
+
+        let documents = &self.editor.documents;
+        let diff = self.editor.diff.as_mut().and_then(|diff| {
+            let left = documents.get(&diff.doc(Side::Left))?;
+            let right = documents.get(&diff.doc(Side::Right))?;
+            diff.diff_in_background(left, right)
+        });
+        if let Some(diff) = diff {
+            self.jobs.callback(async move {
+                let diff = diff.await;
+                let call: job::Callback =
+                    Box::new(move |editor: &mut Editor, _compositor: &mut Compositor| {
+                        if let Some(pair) = &mut editor.diff {
+                            pair.set_hunks(diff);
+                        }
+                    });
+                Ok(call)
+            });
+        }
+    }
+
     pub async fn event_loop(&mut self) {
         let mut reader = EventStream::new();
         let mut last_render = Instant::now();
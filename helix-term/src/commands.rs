@@ -1,16 +1,22 @@
 use helix_core::{
-    comment, coords_at_pos, find_first_non_whitespace_char, find_root, graphemes,
+    comment, coords_at_pos, find_first_non_whitespace_char, find_root, fold, graphemes,
     history::UndoKind,
     increment::date_time::DateTimeIncrementor,
-    increment::{number::NumberIncrementor, Increment},
+    increment::{
+        color::ColorIncrementor, number::NumberIncrementor, version::VersionIncrementor,
+        word::WordIncrementor, Increment,
+    },
     indent,
     indent::IndentStyle,
     line_ending::{get_line_ending_of_str, line_end_char_index, str_is_line_ending},
-    match_brackets,
+    marks, match_brackets,
     movement::{self, Direction},
     object, pos_at_coords,
-    regex::{self, Regex, RegexBuilder},
-    search, selection, shellwords, surround, textobject,
+    regex::{self, Regex},
+    search::{self, RopeRegex},
+    selection, shellwords, surround,
+    syntax::LanguageConfiguration,
+    textobject,
     tree_sitter::Node,
     unicode::width::UnicodeWidthChar,
     LineEnding, Position, Range, Rope, RopeGraphemes, RopeSlice, Selection, SmallVec, Tendril,
@@ -18,8 +24,9 @@ use helix_core::{
 };
 use helix_view::{
     clipboard::ClipboardType,
+    diff::Side,
     document::{Mode, SCRATCH_BUFFER_NAME},
-    editor::{Action, Motion},
+    editor::{Action, Motion, SearchConfig},
     info::Info,
     input::KeyEvent,
     keyboard::KeyCode,
@@ -51,6 +58,7 @@ use std::{fmt, future::Future};
 use std::{
     borrow::Cow,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use once_cell::sync::Lazy;
@@ -126,7 +134,6 @@ pub(crate) fn align_view(doc: &Document, view: &mut View, align: Align) {
         .selection(view.id)
         .primary()
         .cursor(doc.text().slice(..));
-    let line = doc.text().char_to_line(pos);
 
     let height = view.inner_area().height as usize;
 
@@ -136,7 +143,16 @@ pub(crate) fn align_view(doc: &Document, view: &mut View, align: Align) {
         Align::Bottom => height,
     };
 
-    view.offset.row = line.saturating_sub(relative);
+    // count the rows on screen, skipping folded lines
+    let rows = view.rows(doc);
+    let (line, row, _) = rows.coords_at_pos(pos);
+    let (line, row) = rows
+        .iter_back_from(line, row)
+        .take(relative + 1)
+        .last()
+        .unwrap_or((line, row));
+    view.offset.row = line;
+    view.top_row = row;
 }
 
 /// A MappableCommand is either a static command like "jump_view_up" or a Typable command like
@@ -227,12 +243,18 @@ impl MappableCommand {
         move_next_long_word_start, "Move to beginning of next long word",
         move_prev_long_word_start, "Move to beginning of previous long word",
         move_next_long_word_end, "Move to end of next long word",
+        move_next_sub_word_start, "Move to beginning of next sub word",
+        move_prev_sub_word_start, "Move to beginning of previous sub word",
+        move_next_sub_word_end, "Move to end of next sub word",
         extend_next_word_start, "Extend to beginning of next word",
         extend_prev_word_start, "Extend to beginning of previous word",
         extend_next_long_word_start, "Extend to beginning of next long word",
         extend_prev_long_word_start, "Extend to beginning of previous long word",
         extend_next_long_word_end, "Extend to end of next long word",
         extend_next_word_end, "Extend to end of next word",
+        extend_next_sub_word_start, "Extend to beginning of next sub word",
+        extend_prev_sub_word_start, "Extend to beginning of previous sub word",
+        extend_next_sub_word_end, "Extend to end of next sub word",
         find_till_char, "Move till next occurance of char",
         find_next_char, "Move to next occurance of char",
         extend_till_char, "Extend till next occurance of char",
@@ -261,6 +283,7 @@ impl MappableCommand {
         extend_search_next, "Add next search match to selection",
         extend_search_prev, "Add previous search match to selection",
         search_selection, "Use current selection as search pattern",
+        clear_search_highlight, "Clear the highlights of search matches",
         global_search, "Global Search in workspace folder",
         extend_line, "Select current line, if already selected, extend to next line",
         extend_to_line_bounds, "Extend selection to line bounds (line-wise selection)",
@@ -310,6 +333,18 @@ impl MappableCommand {
         goto_last_diag, "Goto last diagnostic",
         goto_next_diag, "Goto next diagnostic",
         goto_prev_diag, "Goto previous diagnostic",
+        goto_next_change, "Goto next change",
+        goto_prev_change, "Goto previous change",
+        diff_get, "Replace diff hunks with the other side",
+        diff_put, "Replace diff hunks of the other side",
+        goto_next_function, "Goto next function",
+        goto_prev_function, "Goto previous function",
+        goto_next_class, "Goto next class",
+        goto_prev_class, "Goto previous class",
+        goto_next_parameter, "Goto next parameter",
+        goto_prev_parameter, "Goto previous parameter",
+        goto_next_comment, "Goto next comment",
+        goto_prev_comment, "Goto previous comment",
         goto_line_start, "Goto line start",
         goto_line_end, "Goto line end",
         goto_next_buffer, "Goto next buffer",
@@ -323,6 +358,8 @@ impl MappableCommand {
         extend_to_line_end_newline, "Extend to line end",
         signature_help, "Show signature help",
         insert_tab, "Insert tab char",
+        goto_next_tabstop, "Goto next snippet tabstop, or insert tab char",
+        goto_prev_tabstop, "Goto previous snippet tabstop",
         insert_newline, "Insert newline char",
         delete_char_backward, "Delete previous char",
         delete_char_forward, "Delete next char",
@@ -334,6 +371,7 @@ impl MappableCommand {
         redo, "Redo change",
         earlier, "Move backward in history",
         later, "Move forward in history",
+        undo_tree_picker, "Open undo tree picker",
         yank, "Yank selection",
         yank_joined_to_clipboard, "Join and yank selections to clipboard",
         yank_main_selection_to_clipboard, "Yank main selection to clipboard",
@@ -351,6 +389,7 @@ impl MappableCommand {
         indent, "Indent selection",
         unindent, "Unindent selection",
         format_selections, "Format selection",
+        reindent, "Reindent selected lines",
         join_selections, "Join lines inside selection",
         keep_selections, "Keep selections matching regex",
         remove_selections, "Remove selections matching regex",
@@ -360,6 +399,7 @@ impl MappableCommand {
         completion, "Invoke completion popup",
         hover, "Show docs for item under cursor",
         toggle_comments, "Comment/uncomment selections",
+        toggle_block_comments, "Comment/uncomment selections with block comments",
         rotate_selections_forward, "Rotate selections forward",
         rotate_selections_backward, "Rotate selections backward",
         rotate_selection_contents_forward, "Rotate selection contents forward",
@@ -371,6 +411,11 @@ impl MappableCommand {
         jump_forward, "Jump forward on jumplist",
         jump_backward, "Jump backward on jumplist",
         save_selection, "Save the current selection to the jumplist",
+        selection_forward, "Go forward in the selection history",
+        selection_backward, "Go back in the selection history",
+        set_mark, "Set a mark at the current selection",
+        goto_mark, "Goto mark",
+        mark_picker, "Open mark picker",
         jump_view_right, "Jump to the split to the right",
         jump_view_left, "Jump to the split to the left",
         jump_view_up, "Jump to the split above",
@@ -388,6 +433,10 @@ impl MappableCommand {
         align_view_bottom, "Align view bottom",
         scroll_up, "Scroll view up",
         scroll_down, "Scroll view down",
+        fold, "Fold the syntax node around the cursor",
+        unfold, "Unfold the fold at the cursor",
+        fold_all, "Fold all syntax nodes",
+        unfold_all, "Unfold all folds",
         match_brackets, "Goto matching bracket",
         surround_add, "Surround add",
         surround_replace, "Surround replace",
@@ -505,6 +554,47 @@ where
 
 use helix_core::movement::{move_horizontally, move_vertically};
 
+/// Moves the selections by `count` lines, skipping the lines hidden by folds. When the
+/// document is soft wrapped, the selections move by rows on screen instead.
+fn move_vertically_impl(cx: &mut Context, dir: Direction, behaviour: Movement) {
+    let count = cx.count();
+    let (view, doc) = current!(cx.editor);
+    let text = doc.text().slice(..);
+    let rows = view.rows(doc);
+
+    let selection = doc.selection(view.id).clone().transform(|range| {
+        if !rows.is_wrapped() {
+            let lines = rows.visible_lines();
+            let line = range.cursor_line(text);
+            let target = match dir {
+                Direction::Forward => lines.iter_from(line).take(count + 1).last(),
+                Direction::Backward => lines.iter_back_from(line).take(count + 1).last(),
+            }
+            .unwrap_or(line);
+            return move_vertically(text, range, dir, line.abs_diff(target), behaviour);
+        }
+
+        let (line, row, col) = rows.coords_at_pos(range.cursor(text));
+        let horiz = range.horiz.unwrap_or(col as u32);
+        let (new_line, new_row) = match dir {
+            Direction::Forward => rows.iter_from(line, row).take(count + 1).last(),
+            Direction::Backward => rows.iter_back_from(line, row).take(count + 1).last(),
+        }
+        .unwrap_or((line, row));
+
+        // Special-case to avoid moving to the end of the last non-empty line.
+        if behaviour == Movement::Extend && text.line(new_line).len_chars() == 0 {
+            return range;
+        }
+
+        let new_pos = rows.pos_at_coords(new_line, new_row, horiz as usize);
+        let mut new_range = range.put_cursor(text, new_pos, behaviour == Movement::Extend);
+        new_range.horiz = Some(horiz);
+        new_range
+    });
+    doc.set_selection(view.id, selection);
+}
+
 fn move_char_left(cx: &mut Context) {
     move_impl(cx, move_horizontally, Direction::Backward, Movement::Move)
 }
@@ -514,11 +604,11 @@ fn move_char_right(cx: &mut Context) {
 }
 
 fn move_line_up(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Backward, Movement::Move)
+    move_vertically_impl(cx, Direction::Backward, Movement::Move)
 }
 
 fn move_line_down(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Forward, Movement::Move)
+    move_vertically_impl(cx, Direction::Forward, Movement::Move)
 }
 
 fn extend_char_left(cx: &mut Context) {
@@ -530,11 +620,11 @@ fn extend_char_right(cx: &mut Context) {
 }
 
 fn extend_line_up(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Backward, Movement::Extend)
+    move_vertically_impl(cx, Direction::Backward, Movement::Extend)
 }
 
 fn extend_line_down(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Forward, Movement::Extend)
+    move_vertically_impl(cx, Direction::Forward, Movement::Extend)
 }
 
 fn goto_line_end_impl(view: &mut View, doc: &mut Document, movement: Movement) {
@@ -828,17 +918,27 @@ fn goto_window(cx: &mut Context, align: Align) {
     // as we type
     let scrolloff = cx.editor.config.scrolloff.min(height.saturating_sub(1) / 2);
 
-    let last_line = view.last_line(doc);
+    // the rows on screen, skipping folded lines
+    let rows = view.rows(doc);
+    let screen_rows: Vec<_> = rows
+        .iter_from(view.offset.row, view.top_row)
+        .take(height)
+        .collect();
+    let last_row = screen_rows.len().saturating_sub(1);
 
-    let line = match align {
-        Align::Top => (view.offset.row + scrolloff + count),
-        Align::Center => (view.offset.row + ((last_line - view.offset.row) / 2)),
-        Align::Bottom => last_line.saturating_sub(scrolloff + count),
+    let row = match align {
+        Align::Top => scrolloff + count,
+        Align::Center => last_row / 2,
+        Align::Bottom => last_row.saturating_sub(scrolloff + count),
     }
-    .max(view.offset.row + scrolloff)
-    .min(last_line.saturating_sub(scrolloff));
+    .max(scrolloff)
+    .min(last_row.saturating_sub(scrolloff));
 
-    let pos = doc.text().line_to_char(line);
+    // go to the start of the row
+    let pos = match screen_rows.get(row) {
+        Some(&(line, row)) => rows.pos_at_coords(line, row, 0),
+        None => doc.text().len_chars(),
+    };
 
     doc.set_selection(view.id, Selection::point(pos));
 }
@@ -898,6 +998,18 @@ fn move_next_long_word_end(cx: &mut Context) {
     move_word_impl(cx, movement::move_next_long_word_end)
 }
 
+fn move_next_sub_word_start(cx: &mut Context) {
+    move_word_impl(cx, movement::move_next_sub_word_start)
+}
+
+fn move_prev_sub_word_start(cx: &mut Context) {
+    move_word_impl(cx, movement::move_prev_sub_word_start)
+}
+
+fn move_next_sub_word_end(cx: &mut Context) {
+    move_word_impl(cx, movement::move_next_sub_word_end)
+}
+
 fn goto_file_start(cx: &mut Context) {
     if cx.count.is_some() {
         goto_line(cx);
@@ -1008,6 +1120,18 @@ fn extend_next_long_word_end(cx: &mut Context) {
     extend_word_impl(cx, movement::move_next_long_word_end)
 }
 
+fn extend_next_sub_word_start(cx: &mut Context) {
+    extend_word_impl(cx, movement::move_next_sub_word_start)
+}
+
+fn extend_prev_sub_word_start(cx: &mut Context) {
+    extend_word_impl(cx, movement::move_prev_sub_word_start)
+}
+
+fn extend_next_sub_word_end(cx: &mut Context) {
+    extend_word_impl(cx, movement::move_next_sub_word_end)
+}
+
 fn will_find_char<F>(cx: &mut Context, search_fn: F, inclusive: bool, extend: bool)
 where
     F: Fn(RopeSlice, char, usize, usize, bool) -> Option<usize> + 'static,
@@ -1255,39 +1379,53 @@ pub fn scroll(cx: &mut Context, offset: usize, direction: Direction) {
     let range = doc.selection(view.id).primary();
     let text = doc.text().slice(..);
 
-    let cursor = coords_at_pos(text, range.cursor(text));
-    let doc_last_line = doc.text().len_lines().saturating_sub(1);
+    let height = view.inner_area().height;
+
+    let scrolloff = cx.editor.config.scrolloff.min(height as usize / 2);
+
+    // scroll by rows on screen, skipping folded lines
+    let rows = view.rows(doc);
+    let top = (view.offset.row, view.top_row);
 
-    let last_line = view.last_line(doc);
+    let doc_last_line = rows
+        .visible_lines()
+        .visible(doc.text().len_lines().saturating_sub(1));
+    let doc_last_row = (doc_last_line, rows.line_rows(doc_last_line).len() - 1);
+    let last_row = rows.iter_from(top.0, top.1).take(height as usize).last();
 
-    if direction == Backward && view.offset.row == 0
-        || direction == Forward && last_line == doc_last_line
+    if direction == Backward && top == (0, 0)
+        || direction == Forward && last_row == Some(doc_last_row)
     {
         return;
     }
 
-    let height = view.inner_area().height;
-
-    let scrolloff = cx.editor.config.scrolloff.min(height as usize / 2);
-
-    view.offset.row = match direction {
-        Forward => view.offset.row + offset,
-        Backward => view.offset.row.saturating_sub(offset),
+    let (line, row) = match direction {
+        Forward => rows.iter_from(top.0, top.1).take(offset + 1).last(),
+        Backward => rows.iter_back_from(top.0, top.1).take(offset + 1).last(),
     }
-    .min(doc_last_line);
-
-    // recalculate last line
-    let last_line = view.last_line(doc);
+    .unwrap_or(top);
+    view.offset.row = line;
+    view.top_row = row;
 
     // clamp into viewport
-    let line = cursor
-        .row
-        .max(view.offset.row + scrolloff)
-        .min(last_line.saturating_sub(scrolloff));
+    let screen_rows: Vec<_> = rows
+        .iter_from(view.offset.row, view.top_row)
+        .take(height as usize)
+        .collect();
+    let (line, row, col) = rows.coords_at_pos(range.cursor(text));
+    let top = screen_rows.get(scrolloff).copied();
+    let bottom = screen_rows
+        .len()
+        .checked_sub(scrolloff + 1)
+        .map(|i| screen_rows[i]);
+    let cursor = (line, row);
+    let target = cursor
+        .max(top.unwrap_or((line, row)))
+        .min(bottom.unwrap_or((line, row)));
 
     // If cursor needs moving, replace primary selection
-    if line != cursor.row {
-        let head = pos_at_coords(text, Position::new(line, cursor.col), true); // this func will properly truncate to line end
+    if target != cursor {
+        let head = rows.pos_at_coords(target.0, target.1, col); // this func will properly truncate to line end
 
         let anchor = if doc.mode == Mode::Select {
             range.anchor
@@ -1412,10 +1550,11 @@ fn select_regex(cx: &mut Context) {
         "select:".into(),
         Some(reg),
         |_input: &str| Vec::new(),
-        move |view, doc, regex, event| {
+        move |editor, regex, event| {
             if event != PromptEvent::Update {
                 return;
             }
+            let (view, doc) = current!(editor);
             let text = doc.text().slice(..);
             if let Some(selection) =
                 selection::select_on_matches(text, doc.selection(view.id), &regex)
@@ -1435,10 +1574,11 @@ fn split_selection(cx: &mut Context) {
         "split:".into(),
         Some(reg),
         |_input: &str| Vec::new(),
-        move |view, doc, regex, event| {
+        move |editor, regex, event| {
             if event != PromptEvent::Update {
                 return;
             }
+            let (view, doc) = current!(editor);
             let text = doc.text().slice(..);
             let selection = selection::split_on_matches(text, doc.selection(view.id), &regex);
             doc.set_selection(view.id, selection);
@@ -1453,8 +1593,9 @@ fn split_selection_on_newline(cx: &mut Context) {
     let text = doc.text().slice(..);
     // only compile the regex once
     #[allow(clippy::trivial_regex)]
-    static REGEX: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"\r\n|[\n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{2029}]").unwrap());
+    static REGEX: Lazy<RopeRegex> = Lazy::new(|| {
+        RopeRegex::new(r"\r\n|[\n\r\u{000B}\u{000C}\u{0085}\u{2028}\u{2029}]").unwrap()
+    });
     let selection = selection::split_on_matches(text, doc.selection(view.id), &REGEX);
     doc.set_selection(view.id, selection);
 }
@@ -1463,8 +1604,7 @@ fn split_selection_on_newline(cx: &mut Context) {
 fn search_impl(
     doc: &mut Document,
     view: &mut View,
-    contents: &str,
-    regex: &Regex,
+    regex: &RopeRegex,
     movement: Movement,
     direction: Direction,
     scrolloff: usize,
@@ -1476,43 +1616,27 @@ fn search_impl(
     // Get the right side of the primary block cursor for forward search, or the
     //grapheme before the start of the selection for reverse search.
     let start = match direction {
-        Direction::Forward => text.char_to_byte(graphemes::next_grapheme_boundary(
-            text,
-            selection.primary().to(),
-        )),
-        Direction::Backward => text.char_to_byte(graphemes::prev_grapheme_boundary(
-            text,
-            selection.primary().from(),
-        )),
+        Direction::Forward => graphemes::next_grapheme_boundary(text, selection.primary().to()),
+        Direction::Backward => graphemes::prev_grapheme_boundary(text, selection.primary().from()),
     };
+    let end = text.len_chars();
 
-    //A regex::Match returns byte-positions in the str. In the case where we
-    //do a reverse search and wraparound to the end, we don't need to search
-    //the text before the current cursor position for matches, but by slicing
-    //it out, we need to add it back to the position of the selection.
-    let mut offset = 0;
-
-    // use find_at to find the next match after the cursor, loop around the end
-    // Careful, `Regex` uses `bytes` as offsets, not character indices!
+    // find the next match after the cursor, loop around the end
     let mut mat = match direction {
-        Direction::Forward => regex.find_at(contents, start),
-        Direction::Backward => regex.find_iter(&contents[..start]).last(),
+        Direction::Forward => regex.find_in(text, start..end),
+        Direction::Backward => regex.find_last_in(text, 0..start),
     };
 
     if wrap_around && mat.is_none() {
         mat = match direction {
-            Direction::Forward => regex.find(contents),
-            Direction::Backward => {
-                offset = start;
-                regex.find_iter(&contents[start..]).last()
-            }
+            Direction::Forward => regex.find_in(text, 0..end),
+            Direction::Backward => regex.find_last_in(text, start..end),
         }
         // TODO: message on wraparound
     }
 
     if let Some(mat) = mat {
-        let start = text.byte_to_char(mat.start() + offset);
-        let end = text.byte_to_char(mat.end() + offset);
+        let (start, end) = (mat.start, mat.end);
 
         if end == 0 {
             // skip empty matches that don't make sense
@@ -1564,13 +1688,8 @@ fn searcher(cx: &mut Context, direction: Direction) {
     let scrolloff = cx.editor.config.scrolloff;
     let wrap_around = cx.editor.config.search.wrap_around;
 
-    let doc = doc!(cx.editor);
-
     // TODO: could probably share with select_on_matches?
 
-    // HAXX: sadly we can't avoid allocating a single string for the whole buffer since we can't
-    // feed chunks into the regex yet
-    let contents = doc.text().slice(..).to_string();
     let completions = search_completions(cx, Some(reg));
 
     let prompt = ui::regex_prompt(
@@ -1584,54 +1703,57 @@ fn searcher(cx: &mut Context, direction: Direction) {
                 .map(|comp| (0.., std::borrow::Cow::Owned(comp.clone())))
                 .collect()
         },
-        move |view, doc, regex, event| {
+        move |editor, regex, event| {
             if event != PromptEvent::Update {
                 return;
             }
+            let (view, doc) = current!(editor);
             search_impl(
                 doc,
                 view,
-                &contents,
                 &regex,
                 Movement::Move,
                 direction,
                 scrolloff,
                 wrap_around,
             );
+            editor.search_highlight = Some(regex);
         },
     );
 
     cx.push_layer(Box::new(prompt));
 }
 
+/// Builds the regex of a search `query`. With `smart-case`, the search is case insensitive
+/// unless the query contains uppercase characters.
+fn search_regex(query: &str, config: &SearchConfig) -> Result<RopeRegex, search::BuildError> {
+    let case_insensitive = if config.smart_case {
+        !query.chars().any(char::is_uppercase)
+    } else {
+        false
+    };
+    RopeRegex::with_case_insensitive(query, case_insensitive)
+}
+
 fn search_next_or_prev_impl(cx: &mut Context, movement: Movement, direction: Direction) {
     let scrolloff = cx.editor.config.scrolloff;
     let (view, doc) = current!(cx.editor);
     let registers = &cx.editor.registers;
     if let Some(query) = registers.read('/') {
         let query = query.last().unwrap();
-        let contents = doc.text().slice(..).to_string();
         let search_config = &cx.editor.config.search;
-        let case_insensitive = if search_config.smart_case {
-            !query.chars().any(char::is_uppercase)
-        } else {
-            false
-        };
         let wrap_around = search_config.wrap_around;
-        if let Ok(regex) = RegexBuilder::new(query)
-            .case_insensitive(case_insensitive)
-            .build()
-        {
+        if let Ok(regex) = search_regex(query, search_config) {
             search_impl(
                 doc,
                 view,
-                &contents,
                 &regex,
                 movement,
                 direction,
                 scrolloff,
                 wrap_around,
             );
+            cx.editor.search_highlight = Some(regex);
         } else {
             // get around warning `mutable_borrow_reservation_conflict`
             // which will be a hard error in the future
@@ -1662,11 +1784,16 @@ fn search_selection(cx: &mut Context) {
     let contents = doc.text().slice(..);
     let query = doc.selection(view.id).primary().fragment(contents);
     let regex = regex::escape(&query);
+    cx.editor.search_highlight = search_regex(&regex, &cx.editor.config.search).ok();
     cx.editor.registers.get_mut('/').push(regex);
     let msg = format!("register '{}' set to '{}'", '/', query);
     cx.editor.set_status(msg);
 }
 
+fn clear_search_highlight(cx: &mut Context) {
+    cx.editor.search_highlight = None;
+}
+
 fn global_search(cx: &mut Context) {
     let (all_matches_sx, all_matches_rx) =
         tokio::sync::mpsc::unbounded_channel::<(usize, PathBuf)>();
@@ -1685,7 +1812,7 @@ fn global_search(cx: &mut Context) {
                 .map(|comp| (0.., std::borrow::Cow::Owned(comp.clone())))
                 .collect()
         },
-        move |_view, _doc, regex, event| {
+        move |_editor, regex, event| {
             if event != PromptEvent::Validate {
                 return;
             }
@@ -2628,6 +2755,20 @@ pub mod cmd {
         doc.reload(view.id)
     }
 
+    fn enable_highlighting(
+        cx: &mut compositor::Context,
+        _args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        let loader = cx.editor.syn_loader.clone();
+        let doc = doc_mut!(cx.editor);
+        if doc.language_config().is_none() {
+            anyhow::bail!("No language to highlight the document with.");
+        }
+        doc.enable_highlighting(loader);
+        Ok(())
+    }
+
     fn tree_sitter_scopes(
         cx: &mut compositor::Context,
         _args: &[Cow<str>],
@@ -2661,6 +2802,56 @@ pub mod cmd {
         Ok(())
     }
 
+    fn diff(
+        cx: &mut compositor::Context,
+        args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            matches!(args.len(), 1 | 2),
+            "Bad arguments. Usage: `:diff [old-file] new-file`"
+        );
+
+        if let [old, _] = args {
+            cx.editor
+                .open(PathBuf::from(old.as_ref()), Action::Replace)?;
+        }
+        let left = view!(cx.editor).id;
+        cx.editor.open(
+            PathBuf::from(args[args.len() - 1].as_ref()),
+            Action::VerticalSplit,
+        )?;
+        let right = view!(cx.editor).id;
+        cx.editor.diff_views(left, right);
+
+        Ok(())
+    }
+
+    fn diff_off(
+        cx: &mut compositor::Context,
+        _args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        cx.editor.diff = None;
+        Ok(())
+    }
+
+    fn diff_get(
+        cx: &mut compositor::Context,
+        _args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        diff_copy_hunks(cx.editor, false)
+    }
+
+    fn diff_put(
+        cx: &mut compositor::Context,
+        _args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        diff_copy_hunks(cx.editor, true)
+    }
+
     fn hsplit(
         cx: &mut compositor::Context,
         args: &[Cow<str>],
@@ -2709,6 +2900,15 @@ pub mod cmd {
         Ok(())
     }
 
+    fn clear_search_highlight(
+        cx: &mut compositor::Context,
+        _args: &[Cow<str>],
+        _event: PromptEvent,
+    ) -> anyhow::Result<()> {
+        cx.editor.search_highlight = None;
+        Ok(())
+    }
+
     fn setting(
         cx: &mut compositor::Context,
         args: &[Cow<str>],
@@ -2735,6 +2935,13 @@ pub mod cmd {
             "true-color" => runtime_config.true_color = arg.parse()?,
             "search.smart-case" => runtime_config.search.smart_case = arg.parse()?,
             "search.wrap-around" => runtime_config.search.wrap_around = arg.parse()?,
+            "soft-wrap" => {
+                runtime_config.soft_wrap = arg.parse()?;
+                for doc in cx.editor.documents.values_mut() {
+                    doc.set_soft_wrap(runtime_config.soft_wrap);
+                }
+            }
+            "rainbow-brackets" => runtime_config.rainbow_brackets = arg.parse()?,
             _ => anyhow::bail!("Unknown key `{}`.", args[0]),
         }
 
@@ -3087,6 +3294,13 @@ pub mod cmd {
             fun: reload,
             completer: None,
         },
+        TypableCommand {
+            name: "enable-highlighting",
+            aliases: &[],
+            doc: "Parse and highlight the current document, if it was opened in large-file mode.",
+            fun: enable_highlighting,
+            completer: None,
+        },
         TypableCommand {
             name: "tree-sitter-scopes",
             aliases: &[],
@@ -3108,6 +3322,34 @@ pub mod cmd {
             fun: hsplit,
             completer: Some(completers::filename),
         },
+        TypableCommand {
+            name: "diff",
+            aliases: &[],
+            doc: "Compare the current file or a given file with another file in a vertical split.",
+            fun: diff,
+            completer: Some(completers::filename),
+        },
+        TypableCommand {
+            name: "diff-off",
+            aliases: &[],
+            doc: "Stop comparing the views of `:diff`.",
+            fun: diff_off,
+            completer: None,
+        },
+        TypableCommand {
+            name: "diff-get",
+            aliases: &[],
+            doc: "Replace the diff hunks within the selections with the ones of the other view.",
+            fun: diff_get,
+            completer: None,
+        },
+        TypableCommand {
+            name: "diff-put",
+            aliases: &[],
+            doc: "Replace the diff hunks of the other view with the ones within the selections.",
+            fun: diff_put,
+            completer: None,
+        },
         TypableCommand {
             name: "tutor",
             aliases: &[],
@@ -3122,6 +3364,13 @@ pub mod cmd {
             fun: goto_line_number,
             completer: None,
         },
+        TypableCommand {
+            name: "clear-search-highlight",
+            aliases: &["noh"],
+            doc: "Clear the highlights of search matches.",
+            fun: clear_search_highlight,
+            completer: None,
+        },
         TypableCommand {
             name: "set-option",
             aliases: &["set"],
@@ -3812,6 +4061,7 @@ fn open(cx: &mut Context, open: Open) {
     let count = cx.count();
     let (view, doc) = current!(cx.editor);
     enter_insert_mode(doc);
+    doc.finish_parse(view.id);
 
     let text = doc.text().slice(..);
     let contents = doc.text();
@@ -3842,16 +4092,20 @@ fn open(cx: &mut Context, open: Open) {
         };
 
         // TODO: share logic with insert_newline for indentation
-        let indent_level = indent::suggested_indent_for_pos(
+        let indent = indent::suggested_indent_for_pos(
             doc.language_config(),
             doc.syntax(),
+            doc.indent_style,
+            doc.tab_width(),
             text,
             line_end_index,
-            new_line.saturating_sub(1),
             true,
         )
-        .unwrap_or_else(|| indent::indent_level_for_line(text.line(cursor_line), doc.tab_width()));
-        let indent = doc.indent_unit().repeat(indent_level);
+        .unwrap_or_else(|| {
+            let indent_level =
+                indent::indent_level_for_line(text.line(cursor_line), doc.tab_width());
+            doc.indent_unit().repeat(indent_level)
+        });
         let indent_len = indent.len();
         let mut text = String::with_capacity(1 + indent_len);
         text.push_str(doc.line_ending.as_str());
@@ -3895,6 +4149,7 @@ fn normal_mode(cx: &mut Context) {
     }
 
     doc.mode = Mode::Normal;
+    doc.set_snippet(None);
 
     try_restore_indent(doc, view.id);
 
@@ -4378,6 +4633,199 @@ fn goto_prev_diag(cx: &mut Context) {
     goto_pos(editor, pos);
 }
 
+fn goto_next_change(cx: &mut Context) {
+    goto_change_impl(cx, Direction::Forward)
+}
+
+fn goto_prev_change(cx: &mut Context) {
+    goto_change_impl(cx, Direction::Backward)
+}
+
+/// Selects the lines of the `count`th next or previous hunk changed since the version of the
+/// file in version control. Within a `:diff`, the hunks differing from the other view are used
+/// instead.
+fn goto_change_impl(cx: &mut Context, direction: Direction) {
+    let count = cx.count();
+    cx.editor.update_diff();
+    let focus = cx.editor.tree.focus;
+    let hunks: Vec<_> = match cx
+        .editor
+        .diff
+        .as_ref()
+        .and_then(|diff| Some((diff, diff.side(focus)?)))
+    {
+        Some((diff, side)) => diff.hunks().iter().map(|hunk| side.lines(hunk)).collect(),
+        None => doc!(cx.editor)
+            .diff_hunks()
+            .iter()
+            .map(|hunk| hunk.after.clone())
+            .collect(),
+    };
+
+    let (view, doc) = current!(cx.editor);
+    let text = doc.text().slice(..);
+
+    let selection = doc.selection(view.id).clone().transform(|range| {
+        let new_range = movement::goto_change(text, range, &hunks, direction, count);
+
+        if doc.mode == Mode::Select {
+            Range::new(range.anchor, new_range.head)
+        } else {
+            new_range
+        }
+    });
+    doc.set_selection(view.id, selection);
+}
+
+fn diff_get(cx: &mut Context) {
+    if let Err(err) = diff_copy_hunks(cx.editor, false) {
+        cx.editor.set_error(err.to_string());
+    }
+}
+
+fn diff_put(cx: &mut Context) {
+    if let Err(err) = diff_copy_hunks(cx.editor, true) {
+        cx.editor.set_error(err.to_string());
+    }
+}
+
+/// Copies the hunks of a `:diff` touched by the selections from one view to the other. With
+/// `put`, the hunks of the focused view replace the ones of the other view, otherwise the hunks
+/// of the other view replace the ones of the focused view.
+fn diff_copy_hunks(editor: &mut Editor, put: bool) -> anyhow::Result<()> {
+    editor.update_diff();
+    // the hunks must match the documents, they aren't waited for in the background
+    if let Some(diff) = &mut editor.diff {
+        let left = &editor.documents[&diff.doc(Side::Left)];
+        let right = &editor.documents[&diff.doc(Side::Right)];
+        diff.update_now(left, right);
+    }
+    let focus = editor.tree.focus;
+    let (diff, side) = editor
+        .diff
+        .as_ref()
+        .and_then(|diff| Some((diff, diff.side(focus)?)))
+        .ok_or_else(|| anyhow!("The current view is not part of a diff"))?;
+
+    let (view, doc) = current_ref!(editor);
+    let text = doc.text().slice(..);
+    let selection = doc.selection(view.id);
+    let hunks: Vec<_> = diff
+        .hunks()
+        .iter()
+        .filter(|hunk| {
+            let lines = side.lines(hunk);
+            selection.iter().any(|range| {
+                let (start, end) = range.line_range(text);
+                // hunks without lines on this side are touched by the line following them
+                start < lines.end.max(lines.start + 1) && lines.start <= end
+            })
+        })
+        .cloned()
+        .collect();
+    ensure!(!hunks.is_empty(), "No diff hunk within the selections");
+
+    let (from, to) = if put {
+        (side, side.other())
+    } else {
+        (side.other(), side)
+    };
+    let from_text = editor.documents[&diff.doc(from)].text().clone();
+    let view_id = diff.view(to);
+    let doc = editor.documents.get_mut(&diff.doc(to)).unwrap();
+    let text = doc.text();
+
+    let transaction = Transaction::change(
+        text,
+        hunks.iter().map(|hunk| {
+            let contents = from_text.slice(from.char_range(hunk, &from_text));
+            let to_range = to.char_range(hunk, text);
+            (
+                to_range.start,
+                to_range.end,
+                (!from.lines(hunk).is_empty()).then(|| contents.to_string().into()),
+            )
+        }),
+    );
+    doc.apply(&transaction, view_id);
+    doc.append_changes_to_history(view_id);
+    Ok(())
+}
+
+fn goto_ts_object_impl(cx: &mut Context, object: &'static str, direction: Direction) {
+    let count = cx.count();
+    let motion = move |editor: &mut Editor| {
+        let (view, doc) = current!(editor);
+        let (lang_config, syntax) = match doc.language_config().zip(doc.syntax()) {
+            Some(t) => t,
+            None => {
+                editor.set_status("Syntax-tree is not available in current buffer".to_string());
+                return;
+            }
+        };
+        let text = doc.text().slice(..);
+        let root = syntax.tree().root_node();
+
+        let selection = doc.selection(view.id).clone().transform(|range| {
+            let new_range = movement::goto_treesitter_object(
+                text,
+                range,
+                object,
+                direction,
+                root,
+                lang_config,
+                count,
+            );
+
+            if doc.mode == Mode::Select {
+                // keep the anchor and extend over the whole object
+                let head = match direction {
+                    Direction::Forward => new_range.to(),
+                    Direction::Backward => new_range.from(),
+                };
+                Range::new(range.anchor, head)
+            } else {
+                new_range
+            }
+        });
+        doc.set_selection(view.id, selection);
+    };
+    motion(cx.editor);
+    cx.editor.last_motion = Some(Motion(Box::new(motion)));
+}
+
+fn goto_next_function(cx: &mut Context) {
+    goto_ts_object_impl(cx, "function", Direction::Forward)
+}
+
+fn goto_prev_function(cx: &mut Context) {
+    goto_ts_object_impl(cx, "function", Direction::Backward)
+}
+
+fn goto_next_class(cx: &mut Context) {
+    goto_ts_object_impl(cx, "class", Direction::Forward)
+}
+
+fn goto_prev_class(cx: &mut Context) {
+    goto_ts_object_impl(cx, "class", Direction::Backward)
+}
+
+fn goto_next_parameter(cx: &mut Context) {
+    goto_ts_object_impl(cx, "parameter", Direction::Forward)
+}
+
+fn goto_prev_parameter(cx: &mut Context) {
+    goto_ts_object_impl(cx, "parameter", Direction::Backward)
+}
+
+fn goto_next_comment(cx: &mut Context) {
+    goto_ts_object_impl(cx, "comment", Direction::Forward)
+}
+
+fn goto_prev_comment(cx: &mut Context) {
+    goto_ts_object_impl(cx, "comment", Direction::Backward)
+}
+
 fn signature_help(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
 
@@ -4420,7 +4868,7 @@ fn signature_help(cx: &mut Context) {
 
 pub mod insert {
     use super::*;
-    pub type Hook = fn(&Rope, &Selection, char) -> Option<Transaction>;
+    pub type Hook = fn(&Document, &Selection, char) -> Option<Transaction>;
     pub type PostHook = fn(&mut Context, char);
 
     // It trigger completion when idle timer reaches deadline
@@ -4511,30 +4959,37 @@ pub mod insert {
 
     // The default insert hook: simply insert the character
     #[allow(clippy::unnecessary_wraps)] // need to use Option<> because of the Hook signature
-    fn insert(doc: &Rope, selection: &Selection, ch: char) -> Option<Transaction> {
-        let cursors = selection.clone().cursors(doc.slice(..));
+    fn insert(doc: &Document, selection: &Selection, ch: char) -> Option<Transaction> {
+        let text = doc.text();
+        let cursors = selection.clone().cursors(text.slice(..));
         let mut t = Tendril::new();
         t.push(ch);
-        let transaction = Transaction::insert(doc, &cursors, t);
+        let transaction = Transaction::insert(text, &cursors, t);
         Some(transaction)
     }
 
     use helix_core::auto_pairs;
 
+    fn auto_pairs_hook(doc: &Document, selection: &Selection, ch: char) -> Option<Transaction> {
+        auto_pairs::hook(doc.text(), selection, ch, doc.auto_pairs())
+    }
+
     pub fn insert_char(cx: &mut Context, c: char) {
         let (view, doc) = current!(cx.editor);
 
         let hooks: &[Hook] = match cx.editor.config.auto_pairs {
-            true => &[auto_pairs::hook, insert],
+            true => &[auto_pairs_hook, insert],
             false => &[insert],
         };
 
-        let text = doc.text();
+        // typing over a snippet placeholder replaces it
+        doc.clear_snippet_placeholder(view.id);
+
         let selection = doc.selection(view.id);
 
         // run through insert hooks, stopping on the first one that returns Some(t)
         for hook in hooks {
-            if let Some(transaction) = hook(text, selection, c) {
+            if let Some(transaction) = hook(doc, selection, c) {
                 doc.apply(&transaction, view.id);
                 break;
             }
@@ -4563,8 +5018,21 @@ pub mod insert {
         doc.apply(&transaction, view.id);
     }
 
+    pub fn goto_next_tabstop(cx: &mut Context) {
+        let (view, doc) = current!(cx.editor);
+        if !doc.goto_snippet_tabstop(view.id, Direction::Forward) {
+            insert_tab(cx);
+        }
+    }
+
+    pub fn goto_prev_tabstop(cx: &mut Context) {
+        let (view, doc) = current!(cx.editor);
+        doc.goto_snippet_tabstop(view.id, Direction::Backward);
+    }
+
     pub fn insert_newline(cx: &mut Context) {
         let (view, doc) = current!(cx.editor);
+        doc.finish_parse(view.id);
         let text = doc.text().slice(..);
 
         let contents = doc.text();
@@ -4585,23 +5053,25 @@ pub mod insert {
             let curr = contents.get_char(pos).unwrap_or(' ');
 
             let current_line = text.char_to_line(pos);
-            let indent_level = indent::suggested_indent_for_pos(
+            let indent = indent::suggested_indent_for_pos(
                 doc.language_config(),
                 doc.syntax(),
+                doc.indent_style,
+                doc.tab_width(),
                 text,
                 pos,
-                current_line,
                 true,
             )
             .unwrap_or_else(|| {
-                indent::indent_level_for_line(text.line(current_line), doc.tab_width())
+                let indent_level =
+                    indent::indent_level_for_line(text.line(current_line), doc.tab_width());
+                doc.indent_unit().repeat(indent_level)
             });
 
-            let indent = doc.indent_unit().repeat(indent_level);
             let mut text = String::new();
             // If we are between pairs (such as brackets), we want to insert an additional line which is indented one level more and place the cursor there
-            let new_head_pos = if helix_core::auto_pairs::PAIRS.contains(&(prev, curr)) {
-                let inner_indent = doc.indent_unit().repeat(indent_level + 1);
+            let new_head_pos = if doc.auto_pairs().is_pair(prev, curr) {
+                let inner_indent = indent.clone() + doc.indent_unit();
                 text.reserve_exact(2 + indent.len() + inner_indent.len());
                 text.push_str(doc.line_ending.as_str());
                 text.push_str(&inner_indent);
@@ -4632,14 +5102,22 @@ pub mod insert {
 
     pub fn delete_char_backward(cx: &mut Context) {
         let count = cx.count();
+        let auto_pairs = cx.editor.config.auto_pairs;
         let (view, doc) = current!(cx.editor);
         let text = doc.text().slice(..);
         let indent_unit = doc.indent_unit();
         let tab_size = doc.tab_width();
+        let pairs = doc.auto_pairs();
 
         let transaction =
             Transaction::change_by_selection(doc.text(), doc.selection(view.id), |range| {
                 let pos = range.cursor(text);
+                // delete both sides of an empty pair: (|) -> |
+                if auto_pairs && count == 1 {
+                    if let Some((start, end)) = pairs.delete_backward(doc.text(), pos) {
+                        return (start, end, None);
+                    }
+                }
                 let line_start_pos = text.line_to_char(range.cursor_line(text));
                 // considier to delete by indent level if all characters before `pos` are indent units.
                 let fragment = Cow::from(text.slice(line_start_pos..pos));
@@ -4790,6 +5268,12 @@ fn later(cx: &mut Context) {
     }
 }
 
+fn undo_tree_picker(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let picker = ui::undo_tree_picker(doc, view.id);
+    cx.push_layer(Box::new(picker));
+}
+
 // Yank / Paste
 
 fn yank(cx: &mut Context) {
@@ -5172,6 +5656,44 @@ fn unindent(cx: &mut Context) {
     doc.apply(&transaction, view.id);
 }
 
+fn reindent(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let lines = get_lines(doc, view.id);
+    doc.finish_parse(view.id);
+
+    let syntax = match doc.syntax() {
+        Some(syntax) => syntax,
+        None => {
+            cx.editor
+                .set_error("The document has no syntax tree".to_string());
+            return;
+        }
+    };
+    let query = match doc
+        .language_config()
+        .and_then(|config| config.indent_query())
+    {
+        Some(query) => query,
+        None => {
+            cx.editor
+                .set_error("No indent query for the current language".to_string());
+            return;
+        }
+    };
+
+    let changes = indent::reindent_lines(
+        query,
+        syntax,
+        doc.indent_style,
+        doc.tab_width(),
+        doc.text().slice(..),
+        lines,
+    );
+
+    let transaction = Transaction::change(doc.text(), changes.into_iter());
+    doc.apply(&transaction, view.id);
+}
+
 fn format_selections(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
 
@@ -5268,10 +5790,11 @@ fn keep_or_remove_selections_impl(cx: &mut Context, remove: bool) {
         if !remove { "keep:" } else { "remove:" }.into(),
         Some(reg),
         |_input: &str| Vec::new(),
-        move |view, doc, regex, event| {
+        move |editor, regex, event| {
             if event != PromptEvent::Update {
                 return;
             }
+            let (view, doc) = current!(editor);
             let text = doc.text().slice(..);
 
             if let Some(selection) =
@@ -5492,18 +6015,81 @@ fn hover(cx: &mut Context) {
 }
 
 // comments
-fn toggle_comments(cx: &mut Context) {
+
+/// Toggles comments with `toggle`, using the comment tokens of the language each range is
+/// in. Ranges in injected languages, such as JavaScript in an HTML `<script>` tag, use the
+/// tokens of the injected language.
+fn toggle_comments_impl<F>(cx: &mut Context, toggle: F)
+where
+    F: Fn(&Rope, &Selection, Option<&LanguageConfiguration>) -> Transaction,
+{
     let (view, doc) = current!(cx.editor);
-    let token = doc
-        .language_config()
-        .and_then(|lc| lc.comment_token.as_ref())
-        .map(|tc| tc.as_ref());
-    let transaction = comment::toggle_line_comments(doc.text(), doc.selection(view.id), token);
+    doc.finish_parse(view.id);
+
+    // group the ranges by language
+    let mut groups: Vec<(Option<Arc<LanguageConfiguration>>, Vec<usize>)> = Vec::new();
+    for (i, range) in doc.selection(view.id).iter().enumerate() {
+        let config = doc.language_config_at(range.from(), range.to());
+        let group = groups
+            .iter_mut()
+            .find(|(group_config, _)| match (group_config, &config) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            });
+        match group {
+            Some((_, indices)) => indices.push(i),
+            None => groups.push((config, vec![i])),
+        }
+    }
 
-    doc.apply(&transaction, view.id);
+    for (config, indices) in groups {
+        let selection = doc.selection(view.id);
+        let ranges = indices.iter().map(|&i| selection.ranges()[i]).collect();
+        let transaction = toggle(doc.text(), &Selection::new(ranges, 0), config.as_deref());
+
+        // keep the other ranges, and pick up the ranges the toggle selected, if any
+        let mut selection = selection.clone().map(transaction.changes());
+        if let Some(toggled) = transaction.selection() {
+            let mut ranges = selection.ranges().to_vec();
+            for (&i, &range) in indices.iter().zip(toggled.iter()) {
+                ranges[i] = range;
+            }
+            selection = Selection::new(ranges.into(), selection.primary_index());
+        }
+        let transaction =
+            Transaction::from(transaction.changes().clone()).with_selection(selection);
+
+        doc.apply(&transaction, view.id);
+    }
     exit_select_mode(cx);
 }
 
+fn toggle_comments(cx: &mut Context) {
+    toggle_comments_impl(cx, |text, selection, config| {
+        let token = config
+            .and_then(|lc| lc.comment_token.as_ref())
+            .map(|tc| tc.as_ref());
+        let block_tokens = config.and_then(|lc| lc.block_comment_tokens.as_ref());
+
+        // languages without line comments fall back to block comments
+        match (token, block_tokens) {
+            (None, Some(tokens)) => comment::toggle_block_comments(text, selection, tokens),
+            _ => comment::toggle_line_comments(text, selection, token),
+        }
+    })
+}
+
+fn toggle_block_comments(cx: &mut Context) {
+    toggle_comments_impl(cx, |text, selection, config| {
+        let default_tokens = comment::BlockCommentToken::default();
+        let tokens = config
+            .and_then(|lc| lc.block_comment_tokens.as_ref())
+            .unwrap_or(&default_tokens);
+        comment::toggle_block_comments(text, selection, tokens)
+    })
+}
+
 fn rotate_selections(cx: &mut Context, direction: Direction) {
     let count = cx.count();
     let (view, doc) = current!(cx.editor);
@@ -5693,6 +6279,193 @@ fn jump_backward(cx: &mut Context) {
     };
 }
 
+fn selection_forward(cx: &mut Context) {
+    let count = cx.count();
+    let (view, doc) = current!(cx.editor);
+
+    if doc.selection_forward(view.id, count) {
+        view.ensure_cursor_in_view(doc, cx.editor.config.scrolloff);
+    }
+}
+
+fn selection_backward(cx: &mut Context) {
+    let count = cx.count();
+    let (view, doc) = current!(cx.editor);
+
+    if doc.selection_backward(view.id, count) {
+        view.ensure_cursor_in_view(doc, cx.editor.config.scrolloff);
+    }
+}
+
+fn set_mark(cx: &mut Context) {
+    cx.on_next_key(move |cx, event| {
+        if let Some(name) = event.char() {
+            let (view, doc) = current!(cx.editor);
+            let selection = doc.selection(view.id).clone();
+            // global marks point to a single document
+            if marks::is_global(name) {
+                for doc in cx.editor.documents_mut() {
+                    doc.marks_mut().remove(name);
+                }
+                cx.editor.global_marks.remove(&name);
+            }
+            doc_mut!(cx.editor).marks_mut().set(name, selection);
+            cx.editor.set_status(format!("Mark '{}' set", name));
+        }
+    })
+}
+
+fn goto_mark(cx: &mut Context) {
+    cx.on_next_key(move |cx, event| {
+        if let Some(name) = event.char() {
+            let doc_id = if marks::is_global(name) {
+                match global_mark_doc(cx.editor, name) {
+                    Ok(doc_id) => doc_id,
+                    Err(err) => {
+                        cx.editor
+                            .set_error(format!("Failed to open mark '{}': {}", name, err));
+                        return;
+                    }
+                }
+            } else {
+                let doc = doc!(cx.editor);
+                doc.marks().get(name).map(|_| doc.id())
+            };
+
+            match doc_id {
+                Some(doc_id) => goto_mark_impl(cx.editor, doc_id, name, Action::Replace),
+                None => cx.editor.set_error(format!("Mark '{}' not set", name)),
+            }
+        }
+    })
+}
+
+/// Finds the document holding the global mark `name`, opening its file if it isn't open.
+fn global_mark_doc(editor: &mut Editor, name: char) -> anyhow::Result<Option<DocumentId>> {
+    if let Some(doc) = editor
+        .documents()
+        .find(|doc| doc.marks().get(name).is_some())
+    {
+        return Ok(Some(doc.id()));
+    }
+
+    match editor.global_marks.get(&name) {
+        Some((path, _)) => {
+            let path = path.clone();
+            editor.open(path, Action::Load).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+fn goto_mark_impl(editor: &mut Editor, doc_id: DocumentId, name: char, action: Action) {
+    if doc!(editor).id() == doc_id && matches!(action, Action::Replace) {
+        push_jump(editor);
+    } else {
+        // switching documents pushes a jump
+        editor.switch(doc_id, action);
+    }
+
+    let (view, doc) = current!(editor);
+    if let Some(selection) = doc.marks().get(name).cloned() {
+        doc.set_selection(view.id, selection);
+        align_view(doc, view, Align::Center);
+    }
+}
+
+fn mark_picker(cx: &mut Context) {
+    struct MarkMeta {
+        name: char,
+        /// The document of a local mark; global marks are looked up again when picked.
+        doc_id: DocumentId,
+        path: Option<PathBuf>,
+        /// The line of the mark, unknown for files that aren't open.
+        line: Option<usize>,
+        label: String,
+    }
+
+    let new_meta = |doc: &Document, name: char, selection: &Selection| {
+        let text = doc.text().slice(..);
+        let line = selection.primary().cursor_line(text);
+        let path = doc
+            .relative_path()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| SCRATCH_BUFFER_NAME.into());
+        let content = text.line(line).to_string();
+        MarkMeta {
+            name,
+            doc_id: doc.id(),
+            path: doc.path().cloned(),
+            line: Some(line),
+            label: format!("{} {}:{} {}", name, path, line + 1, content.trim()),
+        }
+    };
+
+    let current = doc!(cx.editor);
+    let mut items: Vec<_> = current
+        .marks()
+        .iter()
+        .filter(|(name, _)| !marks::is_global(*name))
+        .map(|(name, selection)| new_meta(current, name, selection))
+        .collect();
+    let mut global: Vec<_> = cx
+        .editor
+        .documents()
+        .flat_map(|doc| {
+            doc.marks()
+                .iter()
+                .filter(|(name, _)| marks::is_global(*name))
+                .map(move |(name, selection)| new_meta(doc, name, selection))
+        })
+        .collect();
+    global.extend(
+        cx.editor
+            .global_marks
+            .iter()
+            .map(|(&name, (path, _))| MarkMeta {
+                name,
+                doc_id: current.id(),
+                path: Some(path.clone()),
+                line: None,
+                label: format!(
+                    "{} {}",
+                    name,
+                    helix_core::path::get_relative_path(path).to_string_lossy()
+                ),
+            }),
+    );
+    global.sort_by_key(|meta| meta.name);
+    items.extend(global);
+
+    if items.is_empty() {
+        cx.editor.set_error("No marks set".to_string());
+        return;
+    }
+
+    let picker = FilePicker::new(
+        items,
+        |meta| meta.label.as_str().into(),
+        |editor: &mut Editor, meta, action| {
+            if !marks::is_global(meta.name) {
+                goto_mark_impl(editor, meta.doc_id, meta.name, action);
+                return;
+            }
+            match global_mark_doc(editor, meta.name) {
+                Ok(Some(doc_id)) => goto_mark_impl(editor, doc_id, meta.name, action),
+                Ok(None) => editor.set_error(format!("Mark '{}' not set", meta.name)),
+                Err(err) => {
+                    editor.set_error(format!("Failed to open mark '{}': {}", meta.name, err))
+                }
+            }
+        },
+        |_editor, meta| {
+            let lines = meta.line.map(|line| (line, line));
+            Some((meta.path.clone()?, lines))
+        },
+    );
+    cx.push_layer(Box::new(picker));
+}
+
 fn save_selection(cx: &mut Context) {
     push_jump(cx.editor);
     cx.editor
@@ -5725,12 +6498,14 @@ fn split(cx: &mut Context, action: Action) {
     let id = doc.id();
     let selection = doc.selection(view.id).clone();
     let offset = view.offset;
+    let top_row = view.top_row;
 
     cx.editor.switch(id, action);
 
     // match the selection in the previous view
     let (view, doc) = current!(cx.editor);
     view.offset = offset;
+    view.top_row = top_row;
     doc.set_selection(view.id, selection);
 }
 
@@ -5821,6 +6596,89 @@ fn scroll_down(cx: &mut Context) {
     scroll(cx, cx.count(), Direction::Forward);
 }
 
+/// The ranges of the syntax nodes of the current document that can be folded, from its
+/// `folds.scm` query.
+fn foldable_ranges(editor: &mut Editor) -> Option<Vec<std::ops::Range<usize>>> {
+    let doc = doc!(editor);
+    let query = doc
+        .syntax()
+        .and_then(|syntax| Some((doc.language_config()?.fold_query()?, syntax)));
+    match query {
+        Some((query, syntax)) => Some(fold::fold_ranges(syntax, query, doc.text().slice(..))),
+        None => {
+            editor.set_status("Folding is not supported in the current buffer".to_string());
+            None
+        }
+    }
+}
+
+/// Folds `ranges` in the current view and moves the cursors out of the hidden lines.
+fn add_folds(editor: &mut Editor, ranges: impl IntoIterator<Item = std::ops::Range<usize>>) {
+    let (view, doc) = current!(editor);
+    let folds = doc.folds_mut(view.id);
+    for range in ranges {
+        folds.add(range);
+    }
+
+    let text = doc.text().slice(..);
+    let lines = doc.folds(view.id).visible_lines(text);
+    let selection = doc.selection(view.id).clone().transform(|range| {
+        let line = range.cursor_line(text);
+        if lines.is_hidden(line) {
+            Range::point(text.line_to_char(lines.visible(line)))
+        } else {
+            range
+        }
+    });
+    doc.set_selection(view.id, selection);
+}
+
+fn fold(cx: &mut Context) {
+    let ranges = match foldable_ranges(cx.editor) {
+        Some(ranges) => ranges,
+        None => return,
+    };
+    let (view, doc) = current!(cx.editor);
+    let text = doc.text().slice(..);
+    let line = doc.selection(view.id).primary().cursor_line(text);
+    let folds = doc.folds(view.id).ranges();
+
+    // the innermost node around the cursor that isn't folded yet, repeating folds the
+    // enclosing nodes
+    let range = ranges
+        .into_iter()
+        .filter(|range| {
+            let lines = text.char_to_line(range.start)..=text.char_to_line(range.end - 1);
+            lines.contains(&line) && !folds.contains(range)
+        })
+        .min_by_key(|range| range.len());
+
+    match range {
+        Some(range) => add_folds(cx.editor, [range]),
+        None => cx.editor.set_status("Nothing to fold".to_string()),
+    }
+}
+
+fn unfold(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let text = doc.text().clone();
+    let line = doc.selection(view.id).primary().cursor_line(text.slice(..));
+    if !doc.folds_mut(view.id).remove_at_line(text.slice(..), line) {
+        cx.editor.set_status("No fold at the cursor".to_string());
+    }
+}
+
+fn fold_all(cx: &mut Context) {
+    if let Some(ranges) = foldable_ranges(cx.editor) {
+        add_folds(cx.editor, ranges);
+    }
+}
+
+fn unfold_all(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    doc.folds_mut(view.id).clear();
+}
+
 fn select_textobject_around(cx: &mut Context) {
     select_textobject(cx, textobject::TextObject::Around);
 }
@@ -5857,9 +6715,16 @@ fn select_textobject(cx: &mut Context, objtype: textobject::TextObject) {
                     match ch {
                         'w' => textobject::textobject_word(text, range, objtype, count, false),
                         'W' => textobject::textobject_word(text, range, objtype, count, true),
+                        's' => textobject::textobject_sub_word(text, range, objtype, count),
                         'c' => textobject_treesitter("class", range),
                         'f' => textobject_treesitter("function", range),
                         'p' => textobject_treesitter("parameter", range),
+                        'a' => textobject_treesitter("arguments", range),
+                        'b' => textobject_treesitter("block", range),
+                        'C' => textobject_treesitter("comment", range),
+                        'e' => textobject_treesitter("entry", range),
+                        't' => textobject_treesitter("type", range),
+                        'T' => textobject_treesitter("test", range),
                         'm' => {
                             let ch = text.char(range.cursor(text));
                             if !ch.is_ascii_alphanumeric() {
@@ -5883,29 +6748,120 @@ fn select_textobject(cx: &mut Context, objtype: textobject::TextObject) {
     })
 }
 
+/// The key used by the surround commands to act on HTML or XML tags rather than on a pair of
+/// characters.
+const SURROUND_TAG: char = 't';
+
+/// Prompts for a tag, such as `div class="a"`, and calls `f` with the opening and closing tags.
+fn surround_tag_prompt(
+    cx: &mut Context,
+    f: impl Fn(&mut compositor::Context, Tendril, Tendril) + 'static,
+) {
+    let prompt = Prompt::new(
+        "tag:".into(),
+        None,
+        |_input: &str| Vec::new(),
+        move |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+            if event != PromptEvent::Validate {
+                return;
+            }
+            let input = input.trim();
+            let name = match input.split_whitespace().next() {
+                Some(name) => name,
+                None => return,
+            };
+            f(
+                cx,
+                format!("<{}>", input).into(),
+                format!("</{}>", name).into(),
+            );
+        },
+    );
+    cx.push_layer(Box::new(prompt));
+}
+
+/// Replaces the `(from, to)` spans of every surround pair with `open` and `close`.
+fn replace_surround(
+    doc: &mut Document,
+    view_id: ViewId,
+    spans: &[(usize, usize)],
+    open: &Tendril,
+    close: &Tendril,
+) {
+    let transaction = Transaction::change(
+        doc.text(),
+        spans.iter().enumerate().map(|(i, &(from, to))| {
+            let text = if i % 2 == 0 { open } else { close };
+            (from, to, Some(text.clone()))
+        }),
+    );
+    doc.apply(&transaction, view_id);
+}
+
 fn surround_add(cx: &mut Context) {
     cx.on_next_key(move |cx, event| {
         if let Some(ch) = event.char() {
             let (view, doc) = current!(cx.editor);
-            let selection = doc.selection(view.id);
-            let (open, close) = surround::get_pair(ch);
+            let spans: Vec<_> = doc
+                .selection(view.id)
+                .iter()
+                .flat_map(|range| [(range.from(), range.from()), (range.to(), range.to())])
+                .collect();
 
-            let mut changes = Vec::with_capacity(selection.len() * 2);
-            for range in selection.iter() {
-                let mut o = Tendril::new();
-                o.push(open);
-                let mut c = Tendril::new();
-                c.push(close);
-                changes.push((range.from(), range.from(), Some(o)));
-                changes.push((range.to(), range.to(), Some(c)));
+            if ch == SURROUND_TAG {
+                surround_tag_prompt(cx, move |cx, open, close| {
+                    let (view, doc) = current!(cx.editor);
+                    replace_surround(doc, view.id, &spans, &open, &close);
+                });
+                return;
             }
 
-            let transaction = Transaction::change(doc.text(), changes.into_iter());
-            doc.apply(&transaction, view.id);
+            let (open, close) = surround::get_pair(ch);
+            replace_surround(
+                doc,
+                view.id,
+                &spans,
+                &open.to_string().into(),
+                &close.to_string().into(),
+            );
         }
     })
 }
 
+/// Finds the spans of the surround pairs of `ch` around every cursor. With `names_only`, only
+/// the names of tags are included rather than the whole tags.
+fn surround_spans(
+    doc: &mut Document,
+    view_id: ViewId,
+    ch: char,
+    count: usize,
+    names_only: bool,
+) -> Option<Vec<(usize, usize)>> {
+    if ch != SURROUND_TAG {
+        let text = doc.text().slice(..);
+        let change_pos = surround::get_surround_pos(text, doc.selection(view_id), ch, count)?;
+        return Some(change_pos.into_iter().map(|pos| (pos, pos + 1)).collect());
+    }
+
+    doc.finish_parse(view_id);
+    let text = doc.text().slice(..);
+    let selection = doc.selection(view_id);
+    let pairs = surround::get_surround_tags(doc.syntax(), text, selection, count)?;
+    Some(
+        pairs
+            .iter()
+            .flat_map(|pair| [pair.open, pair.close])
+            .map(|tag| {
+                if names_only {
+                    (tag.name_start, tag.name_end)
+                } else {
+                    (tag.start, tag.end)
+                }
+            })
+            .collect(),
+    )
+}
+
 fn surround_replace(cx: &mut Context) {
     let count = cx.count();
     cx.on_next_key(move |cx, event| {
@@ -5913,25 +6869,35 @@ fn surround_replace(cx: &mut Context) {
             cx.on_next_key(move |cx, event| {
                 if let Some(to) = event.char() {
                     let (view, doc) = current!(cx.editor);
-                    let text = doc.text().slice(..);
-                    let selection = doc.selection(view.id);
-
-                    let change_pos = match surround::get_surround_pos(text, selection, from, count)
-                    {
-                        Some(c) => c,
+                    // when replacing a tag with another one, only rename it to keep its attributes
+                    let rename = from == SURROUND_TAG && to == SURROUND_TAG;
+                    let spans = match surround_spans(doc, view.id, from, count, rename) {
+                        Some(spans) => spans,
                         None => return,
                     };
 
+                    if to == SURROUND_TAG {
+                        surround_tag_prompt(cx, move |cx, open, close| {
+                            let (view, doc) = current!(cx.editor);
+                            if rename {
+                                // strip the angle brackets and attributes from the new tag
+                                let name: Tendril = close[2..close.len() - 1].into();
+                                replace_surround(doc, view.id, &spans, &name, &name);
+                            } else {
+                                replace_surround(doc, view.id, &spans, &open, &close);
+                            }
+                        });
+                        return;
+                    }
+
                     let (open, close) = surround::get_pair(to);
-                    let transaction = Transaction::change(
-                        doc.text(),
-                        change_pos.iter().enumerate().map(|(i, &pos)| {
-                            let mut t = Tendril::new();
-                            t.push(if i % 2 == 0 { open } else { close });
-                            (pos, pos + 1, Some(t))
-                        }),
+                    replace_surround(
+                        doc,
+                        view.id,
+                        &spans,
+                        &open.to_string().into(),
+                        &close.to_string().into(),
                     );
-                    doc.apply(&transaction, view.id);
                 }
             });
         }
@@ -5943,16 +6909,15 @@ fn surround_delete(cx: &mut Context) {
     cx.on_next_key(move |cx, event| {
         if let Some(ch) = event.char() {
             let (view, doc) = current!(cx.editor);
-            let text = doc.text().slice(..);
-            let selection = doc.selection(view.id);
-
-            let change_pos = match surround::get_surround_pos(text, selection, ch, count) {
-                Some(c) => c,
+            let spans = match surround_spans(doc, view.id, ch, count, false) {
+                Some(spans) => spans,
                 None => return,
             };
 
-            let transaction =
-                Transaction::change(doc.text(), change_pos.into_iter().map(|p| (p, p + 1, None)));
+            let transaction = Transaction::change(
+                doc.text(),
+                spans.into_iter().map(|(from, to)| (from, to, None)),
+            );
             doc.apply(&transaction, view.id);
         }
     })
@@ -6227,6 +7192,18 @@ fn increment_impl(cx: &mut Context, amount: i64) {
     let selection = doc.selection(view.id);
     let text = doc.text().slice(..);
 
+    // language specific words take precedence over the default ones
+    let mut word_groups = doc
+        .language_config()
+        .map(|config| config.increment_groups.clone())
+        .unwrap_or_default();
+    word_groups.extend(
+        helix_core::increment::word::DEFAULT_GROUPS
+            .iter()
+            .map(|words| words.iter().map(|word| word.to_string()).collect()),
+    );
+    let word_groups: Vec<_> = word_groups.iter().map(Vec::as_slice).collect();
+
     let changes: Vec<_> = selection
         .ranges()
         .iter()
@@ -6234,8 +7211,16 @@ fn increment_impl(cx: &mut Context, amount: i64) {
             let incrementor: Box<dyn Increment> =
                 if let Some(incrementor) = DateTimeIncrementor::from_range(text, *range) {
                     Box::new(incrementor)
+                } else if let Some(incrementor) = VersionIncrementor::from_range(text, *range) {
+                    Box::new(incrementor)
+                } else if let Some(incrementor) = ColorIncrementor::from_range(text, *range) {
+                    Box::new(incrementor)
                 } else if let Some(incrementor) = NumberIncrementor::from_range(text, *range) {
                     Box::new(incrementor)
+                } else if let Some(incrementor) =
+                    WordIncrementor::from_range(text, *range, &word_groups)
+                {
+                    Box::new(incrementor)
                 } else {
                     return None;
                 };
@@ -506,6 +506,10 @@ impl Default for Keymaps {
             "B" => move_prev_long_word_start,
             "E" => move_next_long_word_end,
 
+            "A-w" => move_next_sub_word_start,
+            "A-b" => move_prev_sub_word_start,
+            "A-e" => move_next_sub_word_end,
+
             "v" => select_mode,
             "G" => goto_line,
             "g" => { "Goto"
@@ -572,11 +576,21 @@ impl Default for Keymaps {
             "[" => { "Left bracket"
                 "d" => goto_prev_diag,
                 "D" => goto_first_diag,
+                "f" => goto_prev_function,
+                "c" => goto_prev_class,
+                "p" => goto_prev_parameter,
+                "C" => goto_prev_comment,
+                "g" => goto_prev_change,
                 "space" => add_newline_above,
             },
             "]" => { "Right bracket"
                 "d" => goto_next_diag,
                 "D" => goto_last_diag,
+                "f" => goto_next_function,
+                "c" => goto_next_class,
+                "p" => goto_next_parameter,
+                "C" => goto_next_comment,
+                "g" => goto_next_change,
                 "space" => add_newline_below,
             },
 
@@ -602,7 +616,7 @@ impl Default for Keymaps {
 
             ">" => indent,
             "<" => unindent,
-            "=" => format_selections,
+            "=" => reindent,
             "J" => join_selections,
             "K" => keep_selections,
             "A-K" => remove_selections,
@@ -651,14 +665,21 @@ impl Default for Keymaps {
             "tab" => jump_forward, // tab == <C-i>
             "C-o" => jump_backward,
             "C-s" => save_selection,
+            "A-i" => selection_forward,
+            "A-o" => selection_backward,
+            "M" => set_mark,
+            "'" => goto_mark,
 
             "space" => { "Space"
                 "f" => file_picker,
                 "b" => buffer_picker,
                 "s" => symbol_picker,
                 "S" => workspace_symbol_picker,
+                "u" => undo_tree_picker,
+                "m" => mark_picker,
                 "a" => code_action,
                 "'" => last_picker,
+                "C" => toggle_block_comments,
                 "w" => { "Window"
                     "C-w" | "w" => rotate_view,
                     "C-s" | "s" => hsplit,
@@ -680,6 +701,8 @@ impl Default for Keymaps {
                 "/" => global_search,
                 "k" => hover,
                 "r" => rename_symbol,
+                "d" => diff_get,
+                "D" => diff_put,
             },
             "z" => { "View"
                 "z" | "c" => align_view_center,
@@ -692,6 +715,10 @@ impl Default for Keymaps {
                 "C-f" | "pagedown" => page_down,
                 "C-u" => half_page_up,
                 "C-d" => half_page_down,
+                "f" => fold,
+                "o" => unfold,
+                "F" => fold_all,
+                "O" => unfold_all,
             },
             "Z" => { "View" sticky=true
                 "z" | "c" => align_view_center,
@@ -704,6 +731,10 @@ impl Default for Keymaps {
                 "C-f" | "pagedown" => page_down,
                 "C-u" => half_page_up,
                 "C-d" => half_page_down,
+                "f" => fold,
+                "o" => unfold,
+                "F" => fold_all,
+                "O" => unfold_all,
             },
 
             "\"" => select_register,
@@ -731,6 +762,10 @@ impl Default for Keymaps {
             "B" => extend_prev_long_word_start,
             "E" => extend_next_long_word_end,
 
+            "A-w" => extend_next_sub_word_start,
+            "A-b" => extend_prev_sub_word_start,
+            "A-e" => extend_next_sub_word_end,
+
             "n" => extend_search_next,
             "N" => extend_search_prev,
 
@@ -754,7 +789,8 @@ impl Default for Keymaps {
             "C-d" => delete_char_forward,
             "ret" => insert_newline,
             "C-j" => insert_newline,
-            "tab" => insert_tab,
+            "tab" => goto_next_tabstop,
+            "S-tab" => goto_prev_tabstop,
             "C-w" => delete_word_backward,
             "A-backspace" => delete_word_backward,
             "A-d" => delete_word_forward,
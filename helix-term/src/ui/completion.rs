@@ -4,7 +4,10 @@ use tui::buffer::Buffer as Surface;
 
 use std::borrow::Cow;
 
-use helix_core::Transaction;
+use helix_core::{
+    snippets::{ActiveSnippet, Snippet},
+    Transaction,
+};
 use helix_view::{graphics::Rect, Document, Editor};
 
 use crate::commands;
@@ -91,29 +94,61 @@ impl Completion {
                 offset_encoding: helix_lsp::OffsetEncoding,
                 start_offset: usize,
                 trigger_offset: usize,
-            ) -> Transaction {
+            ) -> (Transaction, Option<ActiveSnippet>) {
+                let is_snippet = item.insert_text_format == Some(lsp::InsertTextFormat::SNIPPET);
+
+                // Expands the snippet of the item, for a given insertion offset. Line breaks
+                // keep the indentation of the line the snippet is inserted on.
+                let expand = |text: &str, offset: usize| {
+                    let line = doc.text().line(doc.text().char_to_line(offset));
+                    let indent: String = line
+                        .chars()
+                        .take_while(|&c| c == ' ' || c == '\t')
+                        .collect();
+                    let newline = format!("{}{}", doc.line_ending.as_str(), indent);
+                    let (text, tabstops) = Snippet::parse(text).render(&newline);
+                    (text, ActiveSnippet::new(tabstops, offset))
+                };
+
                 if let Some(edit) = &item.text_edit {
-                    let edit = match edit {
+                    let mut edit = match edit {
                         lsp::CompletionTextEdit::Edit(edit) => edit.clone(),
                         lsp::CompletionTextEdit::InsertAndReplace(item) => {
                             unimplemented!("completion: insert_and_replace {:?}", item)
                         }
                     };
-                    util::generate_transaction_from_edits(
+                    let start = util::lsp_pos_to_pos(doc.text(), edit.range.start, offset_encoding);
+                    let snippet = match start {
+                        Some(start) if is_snippet => {
+                            let (text, snippet) = expand(&edit.new_text, start);
+                            edit.new_text = text.to_string();
+                            Some(snippet)
+                        }
+                        _ => None,
+                    };
+                    let transaction = util::generate_transaction_from_edits(
                         doc.text(),
                         vec![edit],
                         offset_encoding, // TODO: should probably transcode in Client
-                    )
+                    );
+                    (transaction, snippet)
                 } else {
                     let text = item.insert_text.as_ref().unwrap_or(&item.label);
                     // Some LSPs just give you an insertText with no offset ¯\_(ツ)_/¯
                     // in these cases we need to check for a common prefix and remove it
                     let prefix = Cow::from(doc.text().slice(start_offset..trigger_offset));
                     let text = text.trim_start_matches::<&str>(&prefix);
-                    Transaction::change(
+                    let (text, snippet) = if is_snippet {
+                        let (text, snippet) = expand(text, trigger_offset);
+                        (text, Some(snippet))
+                    } else {
+                        (text.into(), None)
+                    };
+                    let transaction = Transaction::change(
                         doc.text(),
-                        vec![(trigger_offset, trigger_offset, Some(text.into()))].into_iter(),
-                    )
+                        vec![(trigger_offset, trigger_offset, Some(text))].into_iter(),
+                    );
+                    (transaction, snippet)
                 }
             }
 
@@ -128,7 +163,7 @@ impl Completion {
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction = item_to_transaction(
+                    let (transaction, _) = item_to_transaction(
                         doc,
                         item,
                         offset_encoding,
@@ -145,15 +180,23 @@ impl Completion {
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction = item_to_transaction(
+                    let (transaction, snippet) = item_to_transaction(
                         doc,
                         item,
                         offset_encoding,
                         start_offset,
                         trigger_offset,
                     );
+                    // select the first tabstop of the snippet
+                    let transaction = match &snippet {
+                        Some(snippet) => transaction.with_selection(snippet.current().clone()),
+                        None => transaction,
+                    };
                     doc.apply(&transaction, view.id);
 
+                    // the remaining tabstops are visited with tab
+                    doc.set_snippet(snippet.filter(|snippet| !snippet.is_done()));
+
                     // apply additional edits, mostly used to auto import unqualified types
                     let resolved_additional_text_edits = if item.additional_text_edits.is_some() {
                         None
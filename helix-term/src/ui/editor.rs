@@ -11,13 +11,17 @@ use helix_core::{
     graphemes::{
         ensure_grapheme_boundary_next_byte, next_grapheme_boundary, prev_grapheme_boundary,
     },
+    match_brackets,
     movement::Direction,
+    search::RopeRegex,
     syntax::{self, HighlightEvent},
     unicode::segmentation::UnicodeSegmentation,
     unicode::width::UnicodeWidthStr,
+    wrap::{LineRows, Rows},
     LineEnding, Position, Range, Selection,
 };
 use helix_view::{
+    diff::{DiffPair, Side},
     document::{Mode, SCRATCH_BUFFER_NAME},
     editor::CursorShapeConfig,
     graphics::{CursorKind, Modifier, Rect, Style},
@@ -69,12 +73,43 @@ impl EditorView {
         theme: &Theme,
         is_focused: bool,
         config: &helix_view::editor::Config,
+        diff: Option<(&DiffPair, Side)>,
+        search: Option<&RopeRegex>,
     ) {
         let inner = view.inner_area();
         let area = view.area;
+        let rows = view.rows(doc);
+        // the highlights have to span the folded lines up to the last line on screen
+        let height = u16::try_from((view.last_line(doc) + 1).saturating_sub(view.offset.row))
+            .unwrap_or(u16::MAX);
 
-        let highlights = Self::doc_syntax_highlights(doc, view.offset, inner.height, theme);
+        let highlights = Self::doc_syntax_highlights(doc, view.offset, height, theme);
         let highlights = syntax::merge(highlights, Self::doc_diagnostics_highlights(doc, theme));
+        let highlights: Box<dyn Iterator<Item = HighlightEvent>> = match diff {
+            Some((diff, side)) => {
+                Self::render_diff_lines(view, &rows, diff, side, inner, surface, theme);
+                Box::new(syntax::merge(
+                    highlights,
+                    Self::doc_diff_highlights(doc, diff, side, theme),
+                ))
+            }
+            None => Box::new(highlights),
+        };
+        let highlights: Box<dyn Iterator<Item = HighlightEvent>> = if config.rainbow_brackets {
+            Box::new(syntax::merge(
+                highlights,
+                Self::doc_rainbow_highlights(doc, view.offset, height, theme),
+            ))
+        } else {
+            highlights
+        };
+        let highlights: Box<dyn Iterator<Item = HighlightEvent>> = match search {
+            Some(regex) => Box::new(syntax::merge(
+                highlights,
+                Self::doc_search_highlights(doc, view.offset, height, regex, theme),
+            )),
+            None => highlights,
+        };
         let highlights: Box<dyn Iterator<Item = HighlightEvent>> = if is_focused {
             Box::new(syntax::merge(
                 highlights,
@@ -83,9 +118,27 @@ impl EditorView {
         } else {
             Box::new(highlights)
         };
+        let hidden = rows.visible_lines().hidden_chars(doc.text().slice(..));
+        let highlights: Box<dyn Iterator<Item = HighlightEvent>> = if hidden.is_empty() {
+            highlights
+        } else {
+            Box::new(Self::skip_folded(highlights, hidden))
+        };
 
-        Self::render_text_highlights(doc, view.offset, inner, surface, theme, highlights);
-        Self::render_gutter(doc, view, view.area, surface, theme, is_focused, config);
+        Self::render_text_highlights(
+            doc,
+            view.offset,
+            view.top_row,
+            inner,
+            surface,
+            theme,
+            highlights,
+            rows.is_wrapped(),
+        );
+        Self::render_fold_markers(view, &rows, inner, surface, theme);
+        Self::render_gutter(
+            doc, view, &rows, view.area, surface, theme, is_focused, config,
+        );
 
         if is_focused {
             Self::render_focused_view_elements(view, doc, inner, theme, surface);
@@ -190,6 +243,144 @@ impl EditorView {
             .collect()
     }
 
+    /// Get highlight spans for the matches of a search regex within the visible lines of a
+    /// document, represented by the first line and column (`offset`) and the height.
+    pub fn doc_search_highlights(
+        doc: &Document,
+        offset: Position,
+        height: u16,
+        regex: &RopeRegex,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let scope = match theme.find_scope_index("ui.search.match") {
+            Some(scope) => scope,
+            None => return Vec::new(),
+        };
+
+        let text = doc.text().slice(..);
+        let last_line = std::cmp::min(
+            (offset.row + height as usize).saturating_sub(1),
+            text.len_lines().saturating_sub(1),
+        );
+        let start = text.line_to_char(offset.row);
+        let visible = text.slice(start..text.line_to_char(last_line + 1));
+
+        regex
+            .find_iter(visible)
+            .filter(|mat| !mat.is_empty())
+            .map(|mat| (scope, start + mat.start..start + mat.end))
+            .collect()
+    }
+
+    /// Get highlight spans for the brackets within the visible lines of a document,
+    /// represented by the first line and column (`offset`) and the height. Each pair is
+    /// coloured by its nesting depth, cycling through the `rainbow.N` scopes of the theme.
+    pub fn doc_rainbow_highlights(
+        doc: &Document,
+        offset: Position,
+        height: u16,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let syntax = match doc.syntax() {
+            Some(syntax) => syntax,
+            None => return Vec::new(),
+        };
+        let scopes: Vec<_> = (0..)
+            .map_while(|i| theme.find_scope_index(&format!("rainbow.{}", i)))
+            .collect();
+        if scopes.is_empty() {
+            return Vec::new();
+        }
+
+        let text = doc.text().slice(..);
+        let last_line = std::cmp::min(
+            (offset.row + height as usize).saturating_sub(1),
+            text.len_lines().saturating_sub(1),
+        );
+        let range = text.line_to_char(offset.row)..text.line_to_char(last_line + 1);
+
+        match_brackets::bracket_depths(syntax, text, range)
+            .into_iter()
+            .map(|(pos, depth)| (scopes[depth % scopes.len()], pos..pos + 1))
+            .collect()
+    }
+
+    /// Get highlight spans for the lines of a document that differ from the other side of a
+    /// `:diff`. The `ui.diff` scopes are used, falling back to the `diff` ones.
+    pub fn doc_diff_highlights(
+        doc: &Document,
+        diff: &DiffPair,
+        side: Side,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let text = doc.text();
+        let find_scope = |scope: &str| {
+            theme
+                .find_scope_index(&format!("ui.{}", scope))
+                .or_else(|| theme.find_scope_index(scope))
+        };
+        let plus = find_scope("diff.plus");
+        let minus = find_scope("diff.minus");
+        let delta = find_scope("diff.delta");
+
+        diff.hunks()
+            .iter()
+            .filter_map(|hunk| {
+                let scope = if hunk.is_pure_insertion() {
+                    plus
+                } else if hunk.is_pure_removal() {
+                    minus
+                } else {
+                    delta
+                }?;
+                let range = side.char_range(hunk, text);
+                if range.is_empty() {
+                    return None;
+                }
+                Some((scope, range))
+            })
+            .collect()
+    }
+
+    /// Fill the whole rows of the lines that differ from the other side of a `:diff` with
+    /// their `ui.diff` style, so that the background extends past the end of the lines.
+    pub fn render_diff_lines(
+        view: &View,
+        rows: &Rows,
+        diff: &DiffPair,
+        side: Side,
+        viewport: Rect,
+        surface: &mut Surface,
+        theme: &Theme,
+    ) {
+        let screen_rows = rows
+            .iter_from(view.offset.row, view.top_row)
+            .take(viewport.height as usize);
+        let screen_lines: Vec<_> = screen_rows.map(|(line, _)| line).collect();
+
+        for hunk in diff.hunks() {
+            let scope = if hunk.is_pure_insertion() {
+                "ui.diff.plus"
+            } else if hunk.is_pure_removal() {
+                "ui.diff.minus"
+            } else {
+                "ui.diff.delta"
+            };
+            let style = match theme.try_get(scope) {
+                Some(style) => style,
+                None => continue,
+            };
+
+            let lines = side.lines(hunk);
+            for (i, line) in screen_lines.iter().enumerate() {
+                if lines.contains(line) {
+                    let y = viewport.y + i as u16;
+                    surface.set_style(Rect::new(viewport.x, y, viewport.width, 1), style);
+                }
+            }
+        }
+    }
+
     /// Get highlight spans for selections in a document view.
     pub fn doc_selection_highlights(
         doc: &Document,
@@ -269,13 +460,98 @@ impl EditorView {
         spans
     }
 
+    /// Removes the text of the lines hidden by folds from the source events of `highlights`,
+    /// `hidden` being their ordered char ranges.
+    fn skip_folded<'a>(
+        highlights: impl Iterator<Item = HighlightEvent> + 'a,
+        hidden: Vec<std::ops::Range<usize>>,
+    ) -> impl Iterator<Item = HighlightEvent> + 'a {
+        let mut hidden = hidden.into_iter().peekable();
+        highlights.flat_map(move |event| {
+            let (mut start, end) = match event {
+                HighlightEvent::Source { start, end } => (start, end),
+                event => return vec![event],
+            };
+            let mut sources = Vec::new();
+            while let Some(range) = hidden.peek() {
+                if range.end <= start {
+                    hidden.next();
+                    continue;
+                }
+                if range.start >= end {
+                    break;
+                }
+                if range.start > start {
+                    sources.push(HighlightEvent::Source {
+                        start,
+                        end: range.start,
+                    });
+                }
+                start = range.end.min(end);
+                if range.end > end {
+                    break;
+                }
+            }
+            if start < end {
+                sources.push(HighlightEvent::Source { start, end });
+            }
+            sources
+        })
+    }
+
+    /// Render a marker after the first line of each fold on screen.
+    pub fn render_fold_markers(
+        view: &View,
+        rows: &Rows,
+        viewport: Rect,
+        surface: &mut Surface,
+        theme: &Theme,
+    ) {
+        let text = rows.text();
+        let fold_lines: Vec<_> = rows.visible_lines().fold_lines().collect();
+        if fold_lines.is_empty() {
+            return;
+        }
+        let style = theme.get("ui.fold");
+
+        let screen_rows = rows
+            .iter_from(view.offset.row, view.top_row)
+            .take(viewport.height as usize);
+        for (y, (line, row)) in screen_rows.enumerate() {
+            if !fold_lines.contains(&line) {
+                continue;
+            }
+            let line_end = helix_core::line_ending::line_end_char_index(&text, line);
+            let (_, end_row, col) = rows.coords_at_pos(line_end);
+            if row != end_row {
+                continue;
+            }
+            // leave a space after the end of the line
+            let col = col + 1;
+            if col < view.offset.col || col >= view.offset.col + viewport.width as usize {
+                continue;
+            }
+            surface.set_string(
+                viewport.x + (col - view.offset.col) as u16,
+                viewport.y + y as u16,
+                "⋯",
+                style,
+            );
+        }
+    }
+
+    /// Renders the text from the line `offset.row`. When soft wrapping, the rows of that line
+    /// before `top_row` are skipped.
+    #[allow(clippy::too_many_arguments)]
     pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
         doc: &Document,
         offset: Position,
+        top_row: usize,
         viewport: Rect,
         surface: &mut Surface,
         theme: &Theme,
         highlights: H,
+        soft_wrap: bool,
     ) {
         let text = doc.text().slice(..);
 
@@ -284,6 +560,10 @@ impl EditorView {
         let mut line = 0u16;
         let tab_width = doc.tab_width();
         let tab = " ".repeat(tab_width);
+        // the start, the rows and the next row of the line being rendered, when wrapping
+        let mut line_rows: Option<(usize, LineRows, usize)> = None;
+        // the rows of the first line that are above the view
+        let mut skipped_rows = if soft_wrap { top_row } else { 0 };
 
         let text_style = theme.get("ui.text");
 
@@ -303,12 +583,41 @@ impl EditorView {
                     // `unwrap_or_else` part is for off-the-end indices of
                     // the rope, to allow cursor highlighting at the end
                     // of the rope.
-                    let text = text.get_slice(start..end).unwrap_or_else(|| " ".into());
+                    let source = text.get_slice(start..end).unwrap_or_else(|| " ".into());
+                    let mut pos = start;
 
                     use helix_core::graphemes::{grapheme_width, RopeGraphemes};
 
-                    for grapheme in RopeGraphemes::new(text) {
-                        let out_of_bounds = visual_x < offset.col as u16
+                    for grapheme in RopeGraphemes::new(source) {
+                        if soft_wrap {
+                            let (line_start, rows, next_row) = line_rows.get_or_insert_with(|| {
+                                let line = text.char_to_line(pos);
+                                let rows = LineRows::new(
+                                    text.line(line),
+                                    viewport.width as usize,
+                                    tab_width,
+                                );
+                                (text.line_to_char(line), rows, 1)
+                            });
+                            if *next_row < rows.len() && pos >= *line_start + rows.start(*next_row)
+                            {
+                                visual_x = rows.indent(*next_row) as u16;
+                                *next_row += 1;
+                                if skipped_rows > 0 {
+                                    skipped_rows -= 1;
+                                } else {
+                                    line += 1;
+                                }
+
+                                if line >= viewport.height {
+                                    break 'outer;
+                                }
+                            }
+                        }
+                        pos += grapheme.len_chars();
+
+                        let out_of_bounds = skipped_rows > 0
+                            || visual_x < offset.col as u16
                             || visual_x >= viewport.width + offset.col as u16;
 
                         if LineEnding::from_rope_slice(&grapheme).is_some() {
@@ -327,7 +636,13 @@ impl EditorView {
                             }
 
                             visual_x = 0;
-                            line += 1;
+                            if skipped_rows > 0 {
+                                // the first line had fewer rows than the view starts at
+                                skipped_rows = 0;
+                            } else {
+                                line += 1;
+                            }
+                            line_rows = None;
 
                             // TODO: with proper iter this shouldn't be necessary
                             if line >= viewport.height {
@@ -403,9 +718,11 @@ impl EditorView {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render_gutter(
         doc: &Document,
         view: &View,
+        rows: &Rows,
         viewport: Rect,
         surface: &mut Surface,
         theme: &Theme,
@@ -413,7 +730,6 @@ impl EditorView {
         config: &helix_view::editor::Config,
     ) {
         let text = doc.text().slice(..);
-        let last_line = view.last_line(doc);
 
         // it's used inside an iterator so the collect isn't needless:
         // https://github.com/rust-lang/rust-clippy/issues/6164
@@ -434,7 +750,15 @@ impl EditorView {
         for (constructor, width) in view.gutters() {
             let gutter = constructor(doc, view, theme, config, is_focused, *width);
             text.reserve(*width); // ensure there's enough space for the gutter
-            for (i, line) in (view.offset.row..(last_line + 1)).enumerate() {
+            let screen_rows = rows
+                .iter_from(view.offset.row, view.top_row)
+                .take(view.inner_area().height as usize);
+            for (i, (line, row)) in screen_rows.enumerate() {
+                // the rows a wrapped line continues on are left blank, unless the line starts
+                // above the view
+                if row > 0 && i > 0 {
+                    continue;
+                }
                 let selected = cursors.contains(&line);
 
                 if let Some(style) = gutter(line, selected, &mut text) {
@@ -628,6 +952,12 @@ impl EditorView {
                 .push(Span::styled(format!(" {} ", enc.name()), base_style));
         }
 
+        if doc.is_large_file() {
+            right_side_text
+                .0
+                .push(Span::styled(" large file ", base_style));
+        }
+
         // Render to the statusline.
         surface.set_spans(
             viewport.x
@@ -679,6 +1009,14 @@ impl EditorView {
         let key_result = self.keymaps.get_mut(&mode).unwrap().get(event);
         cxt.editor.autoinfo = key_result.sticky.map(|node| node.infobox());
 
+        if let KeymapResultKind::Matched(_) | KeymapResultKind::MatchedSequence(_) =
+            &key_result.kind
+        {
+            // record the selection the previous command left before this one changes it
+            let (view, doc) = current!(cxt.editor);
+            doc.record_selection(view.id);
+        }
+
         match &key_result.kind {
             KeymapResultKind::Matched(command) => command.execute(cxt),
             KeymapResultKind::Pending(node) => cxt.editor.autoinfo = Some(node.infobox()),
@@ -1075,9 +1413,15 @@ impl Component for EditorView {
 
         // if the terminal size suddenly changed, we need to trigger a resize
         cx.editor.resize(area.clip_bottom(1)); // -1 from bottom for commandline
+        cx.editor.update_diff();
 
         for (view, is_focused) in cx.editor.tree.views() {
             let doc = cx.editor.document(view.doc).unwrap();
+            let diff = cx
+                .editor
+                .diff
+                .as_ref()
+                .and_then(|diff| Some((diff, diff.side(view.id)?)));
             self.render_view(
                 doc,
                 view,
@@ -1086,6 +1430,8 @@ impl Component for EditorView {
                 &cx.editor.theme,
                 is_focused,
                 &cx.editor.config,
+                diff,
+                cx.editor.search_highlight.as_ref(),
             );
         }
 
@@ -8,6 +8,7 @@ mod popup;
 mod prompt;
 mod spinner;
 mod text;
+mod undo_tree;
 
 pub use completion::Completion;
 pub use editor::EditorView;
@@ -18,10 +19,10 @@ pub use popup::Popup;
 pub use prompt::{Prompt, PromptEvent};
 pub use spinner::{ProgressSpinners, Spinner};
 pub use text::Text;
+pub use undo_tree::undo_tree_picker;
 
-use helix_core::regex::Regex;
-use helix_core::regex::RegexBuilder;
-use helix_view::{Document, Editor, View};
+use helix_core::search::RopeRegex;
+use helix_view::Editor;
 
 use std::path::PathBuf;
 
@@ -30,12 +31,14 @@ pub fn regex_prompt(
     prompt: std::borrow::Cow<'static, str>,
     history_register: Option<char>,
     completion_fn: impl FnMut(&str) -> Vec<prompt::Completion> + 'static,
-    fun: impl Fn(&mut View, &mut Document, Regex, PromptEvent) + 'static,
+    fun: impl Fn(&mut Editor, RopeRegex, PromptEvent) + 'static,
 ) -> Prompt {
     let (view, doc) = current!(cx.editor);
     let view_id = view.id;
     let snapshot = doc.selection(view_id).clone();
     let offset_snapshot = view.offset;
+    let top_row_snapshot = view.top_row;
+    let search_highlight_snapshot = cx.editor.search_highlight.clone();
 
     Prompt::new(
         prompt,
@@ -47,15 +50,14 @@ pub fn regex_prompt(
                     let (view, doc) = current!(cx.editor);
                     doc.set_selection(view.id, snapshot.clone());
                     view.offset = offset_snapshot;
+                    view.top_row = top_row_snapshot;
+                    cx.editor.search_highlight = search_highlight_snapshot.clone();
                 }
                 PromptEvent::Validate => {
                     // TODO: push_jump to store selection just before jump
 
-                    match Regex::new(input) {
-                        Ok(regex) => {
-                            let (view, doc) = current!(cx.editor);
-                            fun(view, doc, regex, event);
-                        }
+                    match RopeRegex::new(input) {
+                        Ok(regex) => fun(cx.editor, regex, event),
                         Err(_err) => (), // TODO: mark command line as error
                     }
                 }
@@ -71,18 +73,16 @@ pub fn regex_prompt(
                         false
                     };
 
-                    match RegexBuilder::new(input)
-                        .case_insensitive(case_insensitive)
-                        .build()
-                    {
+                    match RopeRegex::with_case_insensitive(input, case_insensitive) {
                         Ok(regex) => {
                             let (view, doc) = current!(cx.editor);
 
                             // revert state to what it was before the last update
                             doc.set_selection(view.id, snapshot.clone());
 
-                            fun(view, doc, regex, event);
+                            fun(cx.editor, regex, event);
 
+                            let (view, doc) = current!(cx.editor);
                             view.ensure_cursor_in_view(doc, cx.editor.config.scrolloff);
                         }
                         Err(_err) => (), // TODO: mark command line as error
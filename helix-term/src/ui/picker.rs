@@ -35,6 +35,11 @@ pub const MAX_FILE_SIZE_FOR_PREVIEW: u64 = 10 * 1024 * 1024;
 /// File path and range of lines (used to align and highlight lines)
 type FileLocation = (PathBuf, Option<(usize, usize)>);
 
+/// Document built for a picker item and range of lines (used to align and highlight lines)
+type DocumentLocation = (Document, Option<(usize, usize)>);
+
+type DocumentFn<T> = Box<dyn Fn(&T) -> Option<DocumentLocation>>;
+
 pub struct FilePicker<T> {
     picker: Picker<T>,
     pub truncate_start: bool,
@@ -43,6 +48,10 @@ pub struct FilePicker<T> {
     read_buffer: Vec<u8>,
     /// Given an item in the picker, return the file path and line number to display.
     file_fn: Box<dyn Fn(&Editor, &T) -> Option<FileLocation>>,
+    /// Given an item in the picker, build a document to display instead of a file.
+    document_fn: Option<DocumentFn<T>>,
+    /// The document built for the highlighted item, along with the index of the item.
+    item_preview: Option<(usize, DocumentLocation)>,
 }
 
 pub enum CachedPreview {
@@ -57,12 +66,14 @@ pub enum CachedPreview {
 pub enum Preview<'picker, 'editor> {
     Cached(&'picker CachedPreview),
     EditorDocument(&'editor Document),
+    ItemDocument(&'picker Document),
 }
 
 impl Preview<'_, '_> {
     fn document(&self) -> Option<&Document> {
         match self {
             Preview::EditorDocument(doc) => Some(doc),
+            Preview::ItemDocument(doc) => Some(doc),
             Preview::Cached(CachedPreview::Document(doc)) => Some(doc),
             _ => None,
         }
@@ -71,7 +82,7 @@ impl Preview<'_, '_> {
     /// Alternate text to show for the preview.
     fn placeholder(&self) -> &str {
         match *self {
-            Self::EditorDocument(_) => "<File preview>",
+            Self::EditorDocument(_) | Self::ItemDocument(_) => "<File preview>",
             Self::Cached(preview) => match preview {
                 CachedPreview::Document(_) => "<File preview>",
                 CachedPreview::Binary => "<Binary file>",
@@ -95,9 +106,21 @@ impl<T> FilePicker<T> {
             preview_cache: HashMap::new(),
             read_buffer: Vec::with_capacity(1024),
             file_fn: Box::new(preview_fn),
+            document_fn: None,
+            item_preview: None,
         }
     }
 
+    /// Preview documents built from the picker items rather than files on disk. The
+    /// document is only built for the highlighted item.
+    pub fn with_document_preview(
+        mut self,
+        document_fn: impl Fn(&T) -> Option<DocumentLocation> + 'static,
+    ) -> Self {
+        self.document_fn = Some(Box::new(document_fn));
+        self
+    }
+
     fn current_file(&self, editor: &Editor) -> Option<FileLocation> {
         self.picker
             .selection()
@@ -139,7 +162,7 @@ impl<T> FilePicker<T> {
                     (size, _) if size > MAX_FILE_SIZE_FOR_PREVIEW => CachedPreview::LargeFile,
                     _ => {
                         // TODO: enable syntax highlighting; blocked by async rendering
-                        Document::open(path, None, None)
+                        Document::open(path, None, None, None)
                             .map(|doc| CachedPreview::Document(Box::new(doc)))
                             .unwrap_or(CachedPreview::NotFound)
                     }
@@ -197,8 +220,28 @@ impl<T: 'static> Component for FilePicker<T> {
         let inner = inner.inner(&margin);
         block.render(preview_area, surface);
 
-        if let Some((path, range)) = self.current_file(cx.editor) {
-            let preview = self.get_preview(&path, cx.editor);
+        let preview = match &self.document_fn {
+            Some(document_fn) => {
+                let index = self
+                    .picker
+                    .matches
+                    .get(self.picker.cursor)
+                    .map(|&(index, _score)| index);
+                if index != self.item_preview.as_ref().map(|(index, _)| *index) {
+                    self.item_preview = index.and_then(|index| {
+                        document_fn(&self.picker.options[index]).map(|preview| (index, preview))
+                    });
+                }
+                self.item_preview
+                    .as_ref()
+                    .map(|(_, (doc, range))| (Preview::ItemDocument(doc), *range))
+            }
+            None => self
+                .current_file(cx.editor)
+                .map(|(path, range)| (self.get_preview(&path, cx.editor), range)),
+        };
+
+        if let Some((preview, range)) = preview {
             let doc = match preview.document() {
                 Some(doc) => doc,
                 None => {
@@ -226,10 +269,12 @@ impl<T: 'static> Component for FilePicker<T> {
             EditorView::render_text_highlights(
                 doc,
                 offset,
+                0,
                 inner,
                 surface,
                 &cx.editor.theme,
                 highlights,
+                false,
             );
 
             // highlight the line
@@ -340,7 +385,8 @@ impl<T> Picker<T> {
                         .map(|score| (index, score))
                 }),
         );
-        self.matches.sort_unstable_by_key(|(_, score)| -score);
+        // keep the original order of options that score the same
+        self.matches.sort_by_key(|(_, score)| -score);
 
         // reset cursor position
         self.cursor = 0;
@@ -0,0 +1,122 @@
+use crate::ui::FilePicker;
+use helix_core::Rope;
+use helix_view::{Document, Editor, ViewId};
+
+use std::time::{Duration, Instant};
+
+/// A revision in the undo tree along with a preview of the document at that revision.
+pub struct RevisionItem {
+    revision: usize,
+    label: String,
+    /// The text at the revision, the preview document is built from it when the revision
+    /// is highlighted.
+    text: Rope,
+    /// Lines changed by the revision, highlighted in the preview.
+    lines: Option<(usize, usize)>,
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=9 => "just now".to_string(),
+        10..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Lists every revision of the document's undo tree. Branches are indented, and the
+/// most recent revisions are listed first, like `git log --graph`. Selecting a revision
+/// jumps to it.
+pub fn undo_tree_picker(doc: &mut Document, view_id: ViewId) -> FilePicker<RevisionItem> {
+    // make sure pending changes are part of the tree
+    doc.append_changes_to_history(view_id);
+
+    let doc_id = doc.id();
+    let history = doc.history.take();
+    let texts = history.texts(doc.text());
+    let revisions = history.revisions();
+    let current = history.current_revision();
+    let now = Instant::now();
+
+    let mut children = vec![Vec::new(); revisions.len()];
+    for (i, revision) in revisions.iter().enumerate().skip(1) {
+        children[revision.parent()].push(i);
+    }
+
+    // Walk the tree depth first. The last child continues the branch of its parent and
+    // is visited after any other child, which starts a new, indented branch.
+    let mut order = Vec::with_capacity(revisions.len());
+    let mut stack = vec![(0, 0)];
+    while let Some((i, depth)) = stack.pop() {
+        order.push((i, depth));
+        let last_child = revisions[i].last_child();
+        if let Some(last_child) = last_child {
+            stack.push((last_child, depth));
+        }
+        for &child in children[i].iter().rev() {
+            if Some(child) != last_child {
+                stack.push((child, depth + 1));
+            }
+        }
+    }
+
+    let items = order
+        .into_iter()
+        .rev()
+        .map(|(i, depth)| {
+            let revision = &revisions[i];
+            let text = &texts[i];
+
+            let name = if i == 0 {
+                "root".to_string()
+            } else {
+                i.to_string()
+            };
+            let label = format!(
+                "{}{} {}{}",
+                "│ ".repeat(depth),
+                name,
+                format_age(now.saturating_duration_since(revision.timestamp())),
+                if i == current { " (current)" } else { "" }
+            );
+
+            let lines = (i != 0)
+                .then(|| revision.transaction().changes_iter().next())
+                .flatten()
+                .map(|(from, _to, fragment)| {
+                    let len = fragment.map_or(0, |fragment| fragment.chars().count());
+                    let start = from.min(text.len_chars());
+                    let end = (from + len).min(text.len_chars());
+                    (text.char_to_line(start), text.char_to_line(end))
+                });
+
+            RevisionItem {
+                revision: i,
+                label,
+                text: text.clone(),
+                lines,
+            }
+        })
+        .collect();
+
+    doc.history.set(history);
+
+    let mut picker = FilePicker::new(
+        items,
+        |item| item.label.as_str().into(),
+        move |editor: &mut Editor, item, _action| {
+            let scrolloff = editor.config.scrolloff;
+            let (view, doc) = current!(editor);
+            if doc.id() == doc_id {
+                doc.jump_to_revision(view.id, item.revision);
+                view.ensure_cursor_in_view(doc, scrolloff);
+            }
+        },
+        |_editor, _item| None,
+    )
+    .with_document_preview(|item| Some((Document::from(item.text.clone(), None), item.lines)));
+    picker.truncate_start = false;
+    picker
+}
@@ -0,0 +1,56 @@
+use git2::Repository;
+
+use std::path::Path;
+
+pub fn get_index_contents(file: &Path) -> Option<Vec<u8>> {
+    let repo = Repository::discover(file.parent()?).ok()?;
+    // the paths in the index are relative to the working directory
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let file = file.canonicalize().ok()?;
+    let path = file.strip_prefix(workdir).ok()?;
+
+    let index = repo.index().ok()?;
+    let entry = index.get_path(path, 0)?;
+    let blob = repo.find_blob(entry.id).ok()?;
+    Some(blob.content().to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{fs, path::PathBuf};
+
+    fn temp_repo(name: &str) -> (PathBuf, Repository) {
+        let dir = std::env::temp_dir().join(format!("helix-vcs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_index_contents() {
+        let (dir, repo) = temp_repo("index");
+        let file = dir.join("file.txt");
+        fs::write(&file, "indexed\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+
+        // changes in the working directory don't affect the index
+        fs::write(&file, "modified\n").unwrap();
+        assert_eq!(
+            get_index_contents(&file).as_deref(),
+            Some(&b"indexed\n"[..])
+        );
+
+        // untracked files have no contents
+        let untracked = dir.join("untracked.txt");
+        fs::write(&untracked, "untracked\n").unwrap();
+        assert_eq!(get_index_contents(&untracked), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+//! Access to the version of files in version control, used to show which lines of a document
+//! changed. The repository is read directly rather than through a `git` subprocess.
+
+mod git;
+
+use std::path::Path;
+
+/// Returns the contents of `file` in the index of the git repository containing it, or `None`
+/// if the file isn't in a repository or isn't tracked.
+pub fn get_diff_base(file: &Path) -> Option<Vec<u8>> {
+    git::get_index_contents(file)
+}
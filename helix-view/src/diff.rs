@@ -0,0 +1,249 @@
+use std::future::Future;
+use std::ops::Range;
+
+use helix_core::{
+    diff::{compare_lines, Hunk},
+    Rope,
+};
+
+use crate::{Document, DocumentId, ViewId};
+
+/// A side of a [`DiffPair`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The old text, shown on the left.
+    Left,
+    /// The new text, shown on the right.
+    Right,
+}
+
+impl Side {
+    pub fn other(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    /// The lines of `hunk` on this side.
+    pub fn lines(self, hunk: &Hunk) -> Range<usize> {
+        match self {
+            Side::Left => hunk.before.clone(),
+            Side::Right => hunk.after.clone(),
+        }
+    }
+
+    /// The char range of the lines of `hunk` on this side in `text`. The hunks may have been
+    /// computed for an older version of the text, so lines past its end are clamped to the end.
+    pub fn char_range(self, hunk: &Hunk, text: &Rope) -> Range<usize> {
+        let lines = self.lines(hunk);
+        let len_lines = text.len_lines();
+        text.line_to_char(lines.start.min(len_lines))..text.line_to_char(lines.end.min(len_lines))
+    }
+}
+
+/// Diff hunks of a [`DiffPair`] computed in the background, see
+/// [`DiffPair::diff_in_background`].
+pub struct DiffPairHunks {
+    docs: [DocumentId; 2],
+    versions: [i32; 2],
+    /// The hunks, or `None` if the diff failed.
+    hunks: Option<Vec<Hunk>>,
+}
+
+/// Two views compared line by line with `:diff`. The views scroll together and the lines that
+/// differ between their documents are highlighted.
+#[derive(Debug)]
+pub struct DiffPair {
+    views: [ViewId; 2],
+    docs: [DocumentId; 2],
+    /// The versions of the documents the hunks are computed for.
+    versions: [i32; 2],
+    hunks: Vec<Hunk>,
+    /// Whether the documents changed since the hunks were computed, in which case they are
+    /// compared again in the background.
+    pending: bool,
+    /// Whether the documents are being compared in the background.
+    running: bool,
+}
+
+impl DiffPair {
+    pub fn new(left: (ViewId, &Document), right: (ViewId, &Document)) -> Self {
+        Self {
+            views: [left.0, right.0],
+            docs: [left.1.id(), right.1.id()],
+            versions: [left.1.version(), right.1.version()],
+            hunks: Vec::new(),
+            pending: true,
+            running: false,
+        }
+    }
+
+    pub fn view(&self, side: Side) -> ViewId {
+        self.views[side as usize]
+    }
+
+    pub fn doc(&self, side: Side) -> DocumentId {
+        self.docs[side as usize]
+    }
+
+    /// The side `view` is on, if it is part of the pair.
+    pub fn side(&self, view: ViewId) -> Option<Side> {
+        if view == self.views[0] {
+            Some(Side::Left)
+        } else if view == self.views[1] {
+            Some(Side::Right)
+        } else {
+            None
+        }
+    }
+
+    /// The changed lines between the left and the right document, ordered by position. They
+    /// lag behind the documents until they are compared again in the background.
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// Marks the documents to be compared again if either of them changed since the last
+    /// comparison, see [`DiffPair::diff_in_background`].
+    pub fn update(&mut self, left: &Document, right: &Document) {
+        let versions = [left.version(), right.version()];
+        if versions != self.versions {
+            self.versions = versions;
+            self.pending = true;
+        }
+    }
+
+    /// Compares the documents right away if they changed since the hunks were computed, for
+    /// the commands that change the documents from the hunks.
+    pub fn update_now(&mut self, left: &Document, right: &Document) {
+        self.update(left, right);
+        if self.pending {
+            self.pending = false;
+            self.hunks = compare_lines(left.text(), right.text());
+        }
+    }
+
+    /// If the documents changed, returns a future that compares them on a blocking thread,
+    /// unless it is already being done. The result is installed with
+    /// [`DiffPair::set_hunks`].
+    pub fn diff_in_background(
+        &mut self,
+        left: &Document,
+        right: &Document,
+    ) -> Option<impl Future<Output = DiffPairHunks> + Send + 'static> {
+        if !self.pending || self.running {
+            return None;
+        }
+        self.pending = false;
+        self.running = true;
+
+        let (docs, versions) = (self.docs, self.versions);
+        let (left, right) = (left.text().clone(), right.text().clone());
+        Some(async move {
+            let hunks = tokio::task::spawn_blocking(move || compare_lines(&left, &right))
+                .await
+                .ok();
+            DiffPairHunks {
+                docs,
+                versions,
+                hunks,
+            }
+        })
+    }
+
+    /// Replaces the hunks with the ones computed in the background, unless they were computed
+    /// for another pair or the documents changed since the diff started.
+    pub fn set_hunks(&mut self, diff: DiffPairHunks) {
+        if diff.docs != self.docs {
+            return;
+        }
+        self.running = false;
+        if self.pending || diff.versions != self.versions {
+            return;
+        }
+        if let Some(hunks) = diff.hunks {
+            self.hunks = hunks;
+        }
+    }
+
+    /// Maps `line` of the document on `side` to the corresponding line on the other side.
+    /// Lines within a hunk are mapped to the same offset into the other side of the hunk.
+    pub fn map_line(&self, side: Side, line: usize) -> usize {
+        let (mut from_end, mut to_end) = (0, 0);
+        for hunk in &self.hunks {
+            let (from, to) = (side.lines(hunk), side.other().lines(hunk));
+            if line < from.start {
+                break;
+            }
+            if line < from.end {
+                return to.start + (line - from.start).min(to.len().saturating_sub(1));
+            }
+            from_end = from.end;
+            to_end = to.end;
+        }
+        line - from_end + to_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helix_core::{Selection, Transaction};
+
+    #[test]
+    fn test_map_line() {
+        let left = Document::from(Rope::from("a\nb\nc\nd\ne\n"), None);
+        let right = Document::from(Rope::from("a\nB\nB\nc\ne\nf\n"), None);
+        let mut pair = DiffPair::new((ViewId::default(), &left), (ViewId::default(), &right));
+        pair.update_now(&left, &right);
+
+        let lines: Vec<_> = (0..5).map(|line| pair.map_line(Side::Left, line)).collect();
+        assert_eq!(lines, [0, 1, 3, 4, 4]);
+        let lines: Vec<_> = (0..6)
+            .map(|line| pair.map_line(Side::Right, line))
+            .collect();
+        assert_eq!(lines, [0, 1, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_diff_in_background() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let view = ViewId::default();
+        let left = Document::from(Rope::from("a\nb\nc\n"), None);
+        let mut right = Document::from(Rope::from("a\nB\nc\n"), None);
+        right.set_selection(view, Selection::point(0));
+        let mut pair = DiffPair::new((view, &left), (view, &right));
+
+        let diff = pair.diff_in_background(&left, &right).unwrap();
+        assert!(pair.diff_in_background(&left, &right).is_none());
+        pair.set_hunks(runtime.block_on(diff));
+        assert_eq!(pair.hunks(), compare_lines(left.text(), right.text()));
+        let hunks = pair.hunks().to_vec();
+        pair.update(&left, &right);
+        assert!(pair.diff_in_background(&left, &right).is_none());
+
+        // the hunks computed before an edit are discarded, and the documents compared again
+        let transaction = Transaction::change(right.text(), [(2, 6, None)].into_iter());
+        right.apply(&transaction, view);
+        pair.update(&left, &right);
+        let diff = pair.diff_in_background(&left, &right).unwrap();
+        let transaction = Transaction::insert(right.text(), &Selection::point(0), "x".into());
+        right.apply(&transaction, view);
+        pair.update(&left, &right);
+        pair.set_hunks(runtime.block_on(diff));
+        assert_eq!(pair.hunks(), hunks);
+
+        // until then, the stale hunks are clamped to the end of the text
+        assert_eq!(right.text(), "xa\n");
+        let hunk = Hunk {
+            before: 1..3,
+            after: 2..4,
+        };
+        assert_eq!(Side::Right.char_range(&hunk, right.text()), 3..3);
+
+        let diff = pair.diff_in_background(&left, &right).unwrap();
+        pair.set_hunks(runtime.block_on(diff));
+        assert_eq!(pair.hunks(), compare_lines(left.text(), right.text()));
+    }
+}
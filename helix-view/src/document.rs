@@ -1,19 +1,29 @@
 use anyhow::{anyhow, bail, Context, Error};
+use once_cell::sync::Lazy;
 use serde::de::{self, Deserialize, Deserializer};
 use serde::Serialize;
 use std::cell::Cell;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::future::Future;
+use std::ops;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use helix_core::{
+    auto_pairs::AutoPairs,
+    diff::Hunk,
     encoding,
-    history::{History, UndoKind},
+    fold::Folds,
+    history::{self, History, SelectionHistory, UndoKind},
     indent::{auto_detect_indent_style, IndentStyle},
     line_ending::auto_detect_line_ending,
+    marks::Marks,
+    movement::Direction,
+    snippets::ActiveSnippet,
     syntax::{self, LanguageConfiguration},
     ChangeSet, Diagnostic, LineEnding, Rope, RopeBuilder, Selection, State, Syntax, Transaction,
     DEFAULT_LINE_ENDING,
@@ -27,8 +37,25 @@ const BUF_SIZE: usize = 8192;
 
 const DEFAULT_INDENT: IndentStyle = IndentStyle::Spaces(4);
 
+/// How long edits are parsed for before the parse is left to a background thread.
+const PARSE_TIMEOUT: Duration = Duration::from_millis(10);
+
 pub const SCRATCH_BUFFER_NAME: &str = "[scratch]";
 
+/// Syntax trees parsed in the background, see [`Document::parse_in_background`].
+pub struct ParsedSyntax {
+    /// The parsed trees, or `None` if the parse was cancelled or failed.
+    syntax: Option<Syntax>,
+    /// The cancellation flag of the parse, to tell whether it is still the current one.
+    cancel: Arc<AtomicUsize>,
+}
+
+/// Diff hunks computed in the background, see [`Document::diff_in_background`].
+pub struct DiffHunks {
+    /// The hunks, or `None` if the diff failed.
+    hunks: Option<Vec<Hunk>>,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Mode {
     Normal = 0,
@@ -83,6 +110,13 @@ pub struct Document {
     pub(crate) id: DocumentId,
     text: Rope,
     pub(crate) selections: HashMap<ViewId, Selection>,
+    /// Previous selections of each view, see [`Document::selection_backward`].
+    selection_history: HashMap<ViewId, SelectionHistory>,
+    marks: Marks,
+    /// Folded ranges of each view.
+    folds: HashMap<ViewId, Folds>,
+    /// The snippet whose tabstops are being visited.
+    snippet: Option<ActiveSnippet>,
 
     path: Option<PathBuf>,
     encoding: &'static encoding::Encoding,
@@ -93,11 +127,24 @@ pub struct Document {
 
     /// Current indent style.
     pub indent_style: IndentStyle,
+    /// Whether to soft wrap long lines, unless the language overrides it.
+    soft_wrap: bool,
 
     /// The document's default line ending.
     pub line_ending: LineEnding,
 
     syntax: Option<Syntax>,
+    /// Whether the syntax trees were edited but couldn't be parsed in time, in which case
+    /// they are parsed in the background.
+    parse_pending: bool,
+    /// Set to cancel the background parse that is running, if any.
+    parse_cancel: Option<Arc<AtomicUsize>>,
+    /// The byte ranges whose injections couldn't be parsed in time, which are parsed in the
+    /// background along with the syntax trees.
+    pending_injections: Vec<ops::Range<usize>>,
+    /// Whether the document was opened in large-file mode, which skips parsing, indent
+    /// detection, the diff gutter and the language server.
+    large_file: bool,
     // /// Corresponding language scope name. Usually `source.<lang>`.
     pub(crate) language: Option<Arc<LanguageConfiguration>>,
 
@@ -119,6 +166,16 @@ pub struct Document {
 
     diagnostics: Vec<Diagnostic>,
     language_server: Option<Arc<helix_lsp::Client>>,
+
+    /// The version of the file in version control that the text is compared to.
+    diff_base: Option<Rope>,
+    /// The lines changed since the diff base.
+    diff_hunks: Vec<Hunk>,
+    /// Whether the text or the diff base changed since the diff hunks were computed, in which
+    /// case they are computed again in the background.
+    diff_pending: bool,
+    /// Whether the diff hunks are being computed in the background.
+    diff_running: bool,
 }
 
 use std::{fmt, mem};
@@ -128,11 +185,18 @@ impl fmt::Debug for Document {
             .field("id", &self.id)
             .field("text", &self.text)
             .field("selections", &self.selections)
+            .field("selection_history", &self.selection_history)
+            .field("marks", &self.marks)
+            .field("folds", &self.folds)
+            .field("snippet", &self.snippet)
             .field("path", &self.path)
             .field("encoding", &self.encoding)
             .field("mode", &self.mode)
             .field("restore_cursor", &self.restore_cursor)
+            .field("soft_wrap", &self.soft_wrap)
             .field("syntax", &self.syntax)
+            .field("parse_pending", &self.parse_pending)
+            .field("large_file", &self.large_file)
             .field("language", &self.language)
             .field("changes", &self.changes)
             .field("old_state", &self.old_state)
@@ -141,6 +205,7 @@ impl fmt::Debug for Document {
             .field("version", &self.version)
             .field("modified_since_accessed", &self.modified_since_accessed)
             .field("diagnostics", &self.diagnostics)
+            .field("diff_hunks", &self.diff_hunks)
             // .field("language_server", &self.language_server)
             .finish()
     }
@@ -344,11 +409,20 @@ impl Document {
             encoding,
             text,
             selections: HashMap::default(),
+            selection_history: HashMap::default(),
+            marks: Marks::default(),
+            folds: HashMap::default(),
+            snippet: None,
             indent_style: DEFAULT_INDENT,
+            soft_wrap: false,
             line_ending: DEFAULT_LINE_ENDING,
             mode: Mode::Normal,
             restore_cursor: false,
             syntax: None,
+            parse_pending: false,
+            parse_cancel: None,
+            pending_injections: Vec::new(),
+            large_file: false,
             language: None,
             changes,
             old_state,
@@ -359,16 +433,22 @@ impl Document {
             last_saved_revision: 0,
             modified_since_accessed: false,
             language_server: None,
+            diff_base: None,
+            diff_hunks: Vec::new(),
+            diff_pending: false,
+            diff_running: false,
         }
     }
 
     // TODO: async fn?
     /// Create a new document from `path`. Encoding is auto-detected, but it can be manually
-    /// overwritten with the `encoding` parameter.
+    /// overwritten with the `encoding` parameter. Files larger than `large_file_threshold`
+    /// bytes are opened in large-file mode, see [`Document::is_large_file`].
     pub fn open(
         path: &Path,
         encoding: Option<&'static encoding::Encoding>,
         config_loader: Option<Arc<syntax::Loader>>,
+        large_file_threshold: Option<usize>,
     ) -> Result<Self, Error> {
         // Open the file if it exists, otherwise assume it is a new file (and thus empty).
         let (rope, encoding) = if path.exists() {
@@ -381,9 +461,20 @@ impl Document {
         };
 
         let mut doc = Self::from(rope, Some(encoding));
+        doc.large_file = doc.text.len_bytes() > large_file_threshold.unwrap_or(usize::MAX);
 
         // set the path and try detecting the language
         doc.set_path(Some(path))?;
+
+        // restore the undo history if it was persisted for the text on disk
+        if let Some(history) = doc
+            .path()
+            .and_then(|path| std::fs::read(history::cache_path(path)).ok())
+            .and_then(|bytes| History::deserialize(&bytes, doc.text()))
+        {
+            doc.last_saved_revision = history.current_revision();
+            doc.history.set(history);
+        }
         if let Some(loader) = config_loader {
             doc.detect_language(loader);
         }
@@ -459,6 +550,19 @@ impl Document {
 
         let language_server = self.language_server.clone();
 
+        // Persist the undo history along with the text. Pending changes aren't part of the
+        // history yet, so it wouldn't describe the saved text.
+        let history = if self.changes.is_empty() {
+            let history = self.history.take();
+            let bytes = history.serialize(&text);
+            self.history.set(history);
+            bytes
+                .map_err(|err| log::warn!("failed to serialize undo history: {}", err))
+                .ok()
+        } else {
+            None
+        };
+
         // mark changes up to now as saved
         self.reset_modified();
 
@@ -483,9 +587,22 @@ impl Document {
                 }
             }
 
-            let mut file = File::create(path).await?;
+            let mut file = File::create(&path).await?;
             to_writer(&mut file, encoding, &text).await?;
 
+            if let Some(history) = history {
+                let history_path = history::cache_path(&path);
+                let result = async {
+                    if let Some(parent) = history_path.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    tokio::fs::write(&history_path, history).await
+                };
+                if let Err(err) = result.await {
+                    log::warn!("failed to persist undo history: {}", err);
+                }
+            }
+
             if let Some(language_server) = language_server {
                 if !language_server.is_initialized() {
                     return Ok(());
@@ -514,16 +631,23 @@ impl Document {
     /// Detect the indentation used in the file, or otherwise defaults to the language indentation
     /// configured in `languages.toml`, with a fallback to 4 space indentation if it isn't
     /// specified. Line ending is likewise auto-detected, and will fallback to the default OS
-    /// line ending.
+    /// line ending. Large files always use the language indentation.
     pub fn detect_indent_and_line_ending(&mut self) {
-        self.indent_style = auto_detect_indent_style(&self.text).unwrap_or_else(|| {
-            self.language_config()
-                .and_then(|config| config.indent.as_ref())
-                .map_or(DEFAULT_INDENT, |config| IndentStyle::from_str(&config.unit))
-        });
+        self.indent_style = if self.large_file {
+            self.language_indent_style()
+        } else {
+            auto_detect_indent_style(&self.text).unwrap_or_else(|| self.language_indent_style())
+        };
         self.line_ending = auto_detect_line_ending(&self.text).unwrap_or(DEFAULT_LINE_ENDING);
     }
 
+    /// The indentation configured for the language, with a fallback to 4 spaces.
+    fn language_indent_style(&self) -> IndentStyle {
+        self.language_config()
+            .and_then(|config| config.indent.as_ref())
+            .map_or(DEFAULT_INDENT, |config| IndentStyle::from_str(&config.unit))
+    }
+
     /// Reload the document from its path.
     pub fn reload(&mut self, view_id: ViewId) -> Result<(), Error> {
         let encoding = &self.encoding;
@@ -539,8 +663,16 @@ impl Document {
 
         // Calculate the difference between the buffer and source text, and apply it.
         // This is not considered a modification of the contents of the file regardless
-        // of the encoding.
-        let transaction = helix_core::diff::compare_ropes(self.text(), &rope);
+        // of the encoding. Large files are replaced as a whole, as diffing them would be too slow.
+        let transaction = if self.large_file {
+            let end = self.text.len_chars();
+            Transaction::change(
+                self.text(),
+                std::iter::once((0, end, Some(rope.to_string().into()))),
+            )
+        } else {
+            helix_core::diff::compare_ropes(self.text(), &rope)
+        };
         self.apply(&transaction, view_id);
         self.append_changes_to_history(view_id);
         self.reset_modified();
@@ -570,19 +702,85 @@ impl Document {
         // if parent doesn't exist we still want to open the document
         // and error out when document is saved
         self.path = path;
+        self.load_diff_base();
 
         Ok(())
     }
 
+    /// Loads the version of the file in version control, to show the lines changed since.
+    /// Large files have no diff base, as diffing them would be too slow.
+    pub fn load_diff_base(&mut self) {
+        let diff_base = if self.large_file {
+            None
+        } else {
+            self.path().and_then(|path| helix_vcs::get_diff_base(path))
+        };
+        self.set_diff_base(diff_base);
+    }
+
+    /// Sets the version of the file that the text is compared to. The diff hunks are computed
+    /// in the background, see [`Document::diff_in_background`].
+    pub fn set_diff_base(&mut self, diff_base: Option<Vec<u8>>) {
+        self.diff_base = diff_base
+            .and_then(|bytes| from_reader(&mut bytes.as_slice(), Some(self.encoding)).ok())
+            .map(|(rope, _)| rope);
+        self.diff_pending = self.diff_base.is_some();
+        if self.diff_base.is_none() {
+            self.diff_hunks.clear();
+        }
+    }
+
+    /// If the text or the diff base changed, returns a future that computes the diff hunks on
+    /// a blocking thread, unless it is already being done. The result is installed with
+    /// [`Document::set_diff_hunks`].
+    pub fn diff_in_background(
+        &mut self,
+    ) -> Option<impl Future<Output = DiffHunks> + Send + 'static> {
+        if !self.diff_pending || self.diff_running {
+            return None;
+        }
+        let diff_base = self.diff_base.clone()?;
+        let text = self.text.clone();
+        self.diff_pending = false;
+        self.diff_running = true;
+
+        Some(async move {
+            let hunks = tokio::task::spawn_blocking(move || {
+                helix_core::diff::compare_lines(&diff_base, &text)
+            })
+            .await
+            .ok();
+            DiffHunks { hunks }
+        })
+    }
+
+    /// Replaces the diff hunks with the ones computed in the background, unless the text or
+    /// the diff base changed since the diff started.
+    pub fn set_diff_hunks(&mut self, diff: DiffHunks) {
+        self.diff_running = false;
+        if self.diff_pending || self.diff_base.is_none() {
+            return;
+        }
+        if let Some(hunks) = diff.hunks {
+            self.diff_hunks = hunks;
+        }
+    }
+
     /// Set the programming language for the file and load associated data (e.g. highlighting)
-    /// if it exists.
+    /// if it exists. Large files are not parsed, see [`Document::enable_highlighting`].
     pub fn set_language(
         &mut self,
         language_config: Option<Arc<helix_core::syntax::LanguageConfiguration>>,
         loader: Option<Arc<helix_core::syntax::Loader>>,
     ) {
+        self.cancel_parse();
+
         if let (Some(language_config), Some(loader)) = (language_config, loader) {
-            if let Some(highlight_config) = language_config.highlight_config(&loader.scopes()) {
+            if self.large_file {
+                self.syntax = None;
+            } else if let Some(highlight_config) =
+                language_config.highlight_config(&loader.scopes())
+            {
                 let syntax = Syntax::new(&self.text, highlight_config, loader);
                 self.syntax = Some(syntax);
             }
@@ -594,6 +792,121 @@ impl Document {
         };
     }
 
+    /// Parse the document to highlight it, even if it was opened in large-file mode.
+    pub fn enable_highlighting(&mut self, loader: Arc<helix_core::syntax::Loader>) {
+        let highlight_config = self
+            .language
+            .as_ref()
+            .and_then(|config| config.highlight_config(&loader.scopes()));
+        if let Some(highlight_config) = highlight_config {
+            self.cancel_parse();
+            self.syntax = Some(Syntax::new(&self.text, highlight_config, loader));
+        }
+    }
+
+    /// Stops parsing the syntax trees in the background.
+    fn cancel_parse(&mut self) {
+        self.parse_pending = false;
+        self.pending_injections.clear();
+        if let Some(cancel) = self.parse_cancel.take() {
+            cancel.store(1, Ordering::Relaxed);
+        }
+    }
+
+    /// If the last edits or the visible injections couldn't be parsed in time, returns a future
+    /// that parses the syntax trees on a blocking thread, unless it is already being done. The
+    /// result is installed with [`Document::set_parsed_syntax`].
+    pub fn parse_in_background(
+        &mut self,
+    ) -> Option<impl Future<Output = ParsedSyntax> + Send + 'static> {
+        if !self.parse_pending || self.parse_cancel.is_some() {
+            return None;
+        }
+        let mut syntax = self.syntax.clone()?;
+        let text = self.text.clone();
+        let injections = mem::take(&mut self.pending_injections);
+        let cancel = Arc::new(AtomicUsize::new(0));
+        self.parse_cancel = Some(cancel.clone());
+
+        Some(async move {
+            let flag = cancel.clone();
+            let syntax = tokio::task::spawn_blocking(move || {
+                syntax.parse(&text, 0..0, None, Some(&flag)).ok()?;
+                for range in injections {
+                    syntax.parse(&text, range, None, Some(&flag)).ok()?;
+                }
+                Some(syntax)
+            })
+            .await
+            // the parse panicked, which still ends it
+            .unwrap_or(None);
+            ParsedSyntax { syntax, cancel }
+        })
+    }
+
+    /// Replaces the syntax trees with the ones parsed in the background, unless the document
+    /// was edited since the parse started.
+    pub fn set_parsed_syntax(&mut self, parsed: ParsedSyntax) {
+        if !matches!(&self.parse_cancel, Some(cancel) if Arc::ptr_eq(cancel, &parsed.cancel)) {
+            return;
+        }
+        self.parse_cancel = None;
+        self.parse_pending = false;
+        if let Some(syntax) = parsed.syntax {
+            self.syntax = Some(syntax);
+        }
+    }
+
+    /// Parses the injections within the char range `range`, usually the visible lines, if
+    /// they weren't parsed since the last edit. Injections that can't be parsed in time are
+    /// left to the background, and nothing is done while the syntax trees are parsed there.
+    pub fn parse_injections(&mut self, range: ops::Range<usize>) {
+        if self.parse_pending {
+            return;
+        }
+        let text = &self.text;
+        let range = text.char_to_byte(range.start)..text.char_to_byte(range.end);
+        if let Some(syntax) = &mut self.syntax {
+            // the edited trees are kept if the parse fails
+            if !syntax.is_parsed(range.clone())
+                && syntax
+                    .parse(text, range.clone(), Some(PARSE_TIMEOUT), None)
+                    .is_err()
+            {
+                self.parse_pending = true;
+                self.pending_injections.push(range);
+            }
+        }
+    }
+
+    /// Parses the syntax trees if the last edits were left to the background, along with the
+    /// injections around the selections of `view_id`. Commands that change the text based on
+    /// the syntax trees call this first, as only rendering may use trees that were edited but
+    /// not parsed again.
+    pub fn finish_parse(&mut self, view_id: ViewId) {
+        let text = &self.text;
+        let selection = &self.selections[&view_id];
+        let from = selection
+            .iter()
+            .map(|range| range.from())
+            .min()
+            .unwrap_or(0);
+        let to = selection.iter().map(|range| range.to()).max().unwrap_or(0);
+        let range = text.char_to_byte(from)..text.char_to_byte(to);
+
+        match &self.syntax {
+            Some(syntax) if !syntax.is_parsed(range.clone()) => (),
+            _ => return,
+        }
+        // the background parse is of no use once the trees are parsed here
+        self.cancel_parse();
+        if let Some(syntax) = &mut self.syntax {
+            if let Err(err) = syntax.parse(&self.text, range, None, None) {
+                log::error!("Failed to parse the syntax trees: {:?}", err);
+            }
+        }
+    }
+
     /// Set the programming language for the file if you know the name (scope) but don't have the
     /// [`syntax::LanguageConfiguration`] for it.
     pub fn set_language2(&mut self, scope: &str, config_loader: Arc<syntax::Loader>) {
@@ -610,8 +923,112 @@ impl Document {
     /// Select text within the [`Document`].
     pub fn set_selection(&mut self, view_id: ViewId, selection: Selection) {
         // TODO: use a transaction?
-        self.selections
-            .insert(view_id, selection.ensure_invariants(self.text().slice(..)));
+        let selection = selection.ensure_invariants(self.text().slice(..));
+        // open the folds that would hide the primary cursor
+        if let Some(folds) = self.folds.get_mut(&view_id) {
+            let text = self.text.slice(..);
+            folds.reveal(text, selection.primary().cursor_line(text));
+        }
+        self.selections.insert(view_id, selection);
+    }
+
+    /// Record the selection of the view in its selection history. This is done once per
+    /// command, the selections set while a command or a prompt runs aren't recorded.
+    pub fn record_selection(&mut self, view_id: ViewId) {
+        if let Some(selection) = self.selections.get(&view_id) {
+            self.selection_history
+                .entry(view_id)
+                .or_default()
+                .record(selection);
+        }
+    }
+
+    /// Go back `count` selections in the selection history of the view.
+    pub fn selection_backward(&mut self, view_id: ViewId, count: usize) -> bool {
+        let selection = match self.selections.get(&view_id) {
+            Some(selection) => selection,
+            None => return false,
+        };
+        let history = self.selection_history.entry(view_id).or_default();
+        match history.backward(selection, count) {
+            Some(selection) => {
+                self.selections.insert(view_id, selection.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go forward `count` selections in the selection history of the view.
+    pub fn selection_forward(&mut self, view_id: ViewId, count: usize) -> bool {
+        let history = self.selection_history.entry(view_id).or_default();
+        match history.forward(count) {
+            Some(selection) => {
+                self.selections.insert(view_id, selection.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the snippet whose tabstops are visited by [`Document::goto_snippet_tabstop`].
+    pub fn set_snippet(&mut self, snippet: Option<ActiveSnippet>) {
+        self.snippet = snippet;
+    }
+
+    /// Select the next or previous tabstop of the active snippet. The snippet is done
+    /// once its last tabstop is selected.
+    pub fn goto_snippet_tabstop(&mut self, view_id: ViewId, direction: Direction) -> bool {
+        let snippet = match &mut self.snippet {
+            Some(snippet) => snippet,
+            None => return false,
+        };
+        let selection = match direction {
+            Direction::Forward => snippet.forward(),
+            Direction::Backward => snippet.backward(),
+        };
+        let selection = match selection {
+            Some(selection) => selection.clone(),
+            None => return false,
+        };
+        if snippet.is_done() {
+            self.snippet = None;
+        }
+        self.set_selection(view_id, selection);
+        true
+    }
+
+    /// Delete the placeholder of the current snippet tabstop if it is still selected, so
+    /// that typing replaces it.
+    pub fn clear_snippet_placeholder(&mut self, view_id: ViewId) {
+        let tabstop = match &self.snippet {
+            Some(snippet) => snippet.current(),
+            None => return,
+        };
+        let selection = self.selection(view_id);
+        let selected = tabstop.len() == selection.len()
+            && tabstop
+                .iter()
+                .zip(selection.iter())
+                .all(|(tabstop, range)| {
+                    !tabstop.is_empty()
+                        && tabstop.from() == range.from()
+                        && tabstop.to() == range.to()
+                });
+        if selected {
+            let transaction = Transaction::change(
+                self.text(),
+                tabstop.iter().map(|range| (range.from(), range.to(), None)),
+            );
+            self.apply(&transaction, view_id);
+        }
+    }
+
+    /// Forget the selection, the selection history and the folds of a view.
+    pub fn remove_view(&mut self, view_id: ViewId) {
+        self.selections.remove(&view_id);
+        self.selection_history.remove(&view_id);
+        self.folds.remove(&view_id);
     }
 
     /// Apply a [`Transaction`] to the [`Document`] to change its text.
@@ -629,6 +1046,16 @@ impl Document {
                     // Ensure all selections accross all views still adhere to invariants.
                     .ensure_invariants(self.text.slice(..));
             }
+            for history in self.selection_history.values_mut() {
+                history.map(transaction.changes(), self.text.slice(..));
+            }
+            self.marks.map(transaction.changes(), self.text.slice(..));
+            for folds in self.folds.values_mut() {
+                folds.map(transaction.changes(), self.text.slice(..));
+            }
+            if let Some(snippet) = &mut self.snippet {
+                snippet.map(transaction.changes());
+            }
 
             // if specified, the current selection should instead be replaced by transaction.selection
             if let Some(selection) = transaction.selection() {
@@ -652,14 +1079,25 @@ impl Document {
                 });
             }
 
-            // update tree-sitter syntax tree
+            // update tree-sitter syntax tree, the injections are parsed for the visible
+            // lines with `parse_injections`
+            if let Some(cancel) = self.parse_cancel.take() {
+                cancel.store(1, Ordering::Relaxed);
+            }
+            // the pending injections are requested again for the edited text
+            self.pending_injections.clear();
             if let Some(syntax) = &mut self.syntax {
-                // TODO: no unwrap
-                syntax
-                    .update(&old_doc, &self.text, transaction.changes())
-                    .unwrap();
+                syntax.edit(&old_doc, transaction.changes());
+                // once a parse took too long, leave the next ones to the background as well
+                if !self.parse_pending {
+                    self.parse_pending = syntax
+                        .parse(&self.text, 0..0, Some(PARSE_TIMEOUT), None)
+                        .is_err();
+                }
             }
 
+            self.diff_pending = self.diff_base.is_some();
+
             // map state.diagnostics over changes::map_pos too
             for diagnostic in &mut self.diagnostics {
                 use helix_core::Assoc;
@@ -751,6 +1189,10 @@ impl Document {
         } else {
             self.history.get_mut().later(uk)
         };
+        self.apply_history_transactions(view_id, txns)
+    }
+
+    fn apply_history_transactions(&mut self, view_id: ViewId, txns: Vec<Transaction>) -> bool {
         let mut success = false;
         for txn in txns {
             if self.apply_impl(&txn, view_id) {
@@ -774,6 +1216,12 @@ impl Document {
         self.earlier_later_impl(view_id, uk, false)
     }
 
+    /// Jump to a specific revision in the undo tree of the [`Document`].
+    pub fn jump_to_revision(&mut self, view_id: ViewId, revision: usize) -> bool {
+        let txns = self.history.get_mut().jump_to(revision);
+        self.apply_history_transactions(view_id, txns)
+    }
+
     /// Commit pending changes to history
     pub fn append_changes_to_history(&mut self, view_id: ViewId) {
         if self.changes.is_empty() {
@@ -792,9 +1240,29 @@ impl Document {
 
         let mut history = self.history.take();
         history.commit_revision(&transaction, &old_state);
+        self.prune_history(&mut history);
         self.history.set(history);
     }
 
+    /// Sets the bounds on the size of the undo history, pruning it if needed.
+    pub fn set_history_limits(&mut self, limits: history::Limits) {
+        let mut history = self.history.take();
+        history.set_limits(limits);
+        self.prune_history(&mut history);
+        self.history.set(history);
+    }
+
+    fn prune_history(&mut self, history: &mut History) {
+        if let Some(mapping) = history.prune() {
+            // If the saved revision was pruned, there's no way back to the saved text.
+            self.last_saved_revision = mapping
+                .get(self.last_saved_revision)
+                .copied()
+                .flatten()
+                .unwrap_or(usize::MAX);
+        }
+    }
+
     pub fn id(&self) -> DocumentId {
         self.id
     }
@@ -842,6 +1310,28 @@ impl Document {
         self.language.as_deref()
     }
 
+    /// The [`LanguageConfiguration`] of the language at the char range `from..to`. This is the
+    /// language of the innermost injection covering the range, or the document's language.
+    pub fn language_config_at(&self, from: usize, to: usize) -> Option<Arc<LanguageConfiguration>> {
+        let text = self.text.slice(..);
+        self.syntax
+            .as_ref()
+            .and_then(|syntax| {
+                syntax
+                    .language_config_for_byte_range(text.char_to_byte(from), text.char_to_byte(to))
+            })
+            .or_else(|| self.language.clone())
+    }
+
+    /// The pairs to auto-close in the document, depending on its language.
+    pub fn auto_pairs(&self) -> &AutoPairs {
+        static DEFAULT_AUTO_PAIRS: Lazy<AutoPairs> = Lazy::new(AutoPairs::default);
+
+        self.language_config()
+            .and_then(|config| config.auto_pairs.as_ref())
+            .unwrap_or(&DEFAULT_AUTO_PAIRS)
+    }
+
     /// Current document version, incremented at each change.
     pub fn version(&self) -> i32 {
         self.version
@@ -859,6 +1349,12 @@ impl Document {
         self.syntax.as_ref()
     }
 
+    /// Whether the document was opened in large-file mode: it isn't parsed unless
+    /// highlighting is enabled, and no language server is attached to it.
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
     /// Tab size in columns.
     pub fn tab_width(&self) -> usize {
         self.language_config()
@@ -866,6 +1362,18 @@ impl Document {
             .map_or(4, |config| config.tab_width) // fallback to 4 columns
     }
 
+    /// Whether long lines are soft wrapped.
+    pub fn soft_wrap(&self) -> bool {
+        self.language_config()
+            .and_then(|config| config.soft_wrap)
+            .unwrap_or(self.soft_wrap)
+    }
+
+    /// Sets whether to soft wrap long lines if the language doesn't say otherwise.
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) {
+        self.soft_wrap = soft_wrap;
+    }
+
     /// Returns a string containing a single level of indentation.
     ///
     /// TODO: we might not need this function anymore, since the information
@@ -903,6 +1411,24 @@ impl Document {
         &self.selections
     }
 
+    /// The folds of a view.
+    pub fn folds(&self, view_id: ViewId) -> &Folds {
+        static NO_FOLDS: Folds = Folds::new();
+        self.folds.get(&view_id).unwrap_or(&NO_FOLDS)
+    }
+
+    pub fn folds_mut(&mut self, view_id: ViewId) -> &mut Folds {
+        self.folds.entry(view_id).or_default()
+    }
+
+    pub fn marks(&self) -> &Marks {
+        &self.marks
+    }
+
+    pub fn marks_mut(&mut self) -> &mut Marks {
+        &mut self.marks
+    }
+
     pub fn relative_path(&self) -> Option<PathBuf> {
         self.path
             .as_deref()
@@ -922,6 +1448,12 @@ impl Document {
         lsp::VersionedTextDocumentIdentifier::new(self.url().unwrap(), self.version)
     }
 
+    /// The lines changed since the version of the file in version control, ordered by
+    /// position.
+    pub fn diff_hunks(&self) -> &[Hunk] {
+        &self.diff_hunks
+    }
+
     #[inline]
     pub fn diagnostics(&self) -> &[Diagnostic] {
         &self.diagnostics
@@ -1101,6 +1633,212 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_diff_in_background() {
+        use helix_core::diff::compare_lines;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut doc = Document::from(Rope::from("a\nb\nc\n"), None);
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::point(0));
+        let diff_base = Rope::from("a\nc\n");
+        doc.set_diff_base(Some(b"a\nc\n".to_vec()));
+
+        // the diff runs in the background, one at a time
+        let diff = doc.diff_in_background().unwrap();
+        assert!(doc.diff_in_background().is_none());
+        doc.set_diff_hunks(runtime.block_on(diff));
+        let hunks = compare_lines(&diff_base, doc.text());
+        assert!(!hunks.is_empty());
+        assert_eq!(doc.diff_hunks(), hunks);
+        assert!(doc.diff_in_background().is_none());
+
+        // a diff that started before an edit is discarded, and a new one runs
+        let transaction = Transaction::insert(doc.text(), doc.selection(view), "x\n".into());
+        doc.apply(&transaction, view);
+        let diff = doc.diff_in_background().unwrap();
+        let transaction = Transaction::insert(doc.text(), doc.selection(view), "y\n".into());
+        doc.apply(&transaction, view);
+        assert!(doc.diff_in_background().is_none());
+        doc.set_diff_hunks(runtime.block_on(diff));
+        assert_eq!(doc.diff_hunks(), hunks);
+
+        let diff = doc.diff_in_background().unwrap();
+        doc.set_diff_hunks(runtime.block_on(diff));
+        let hunks = compare_lines(&diff_base, doc.text());
+        assert_eq!(doc.diff_hunks(), hunks);
+
+        // without a diff base, there is nothing to diff
+        doc.set_diff_base(None);
+        assert!(doc.diff_hunks().is_empty());
+        assert!(doc.diff_in_background().is_none());
+    }
+
+    #[test]
+    fn test_selection_history_select_regex() {
+        use helix_core::{search::RopeRegex, selection::select_on_matches};
+
+        let mut doc = Document::from(Rope::from("hello world\nhello there\n"), None);
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::point(0));
+        doc.record_selection(view);
+        let start = doc.selection(view).clone();
+
+        // a motion
+        doc.set_selection(view, Selection::single(0, 24));
+        doc.record_selection(view);
+        let before = doc.selection(view).clone();
+
+        // `select_regex` selects the matches again on every key typed in its prompt
+        for input in ["h", "he", "hel", "hello"] {
+            doc.set_selection(view, before.clone());
+            let text = doc.text().slice(..);
+            let regex = RopeRegex::new(input).unwrap();
+            let selection = select_on_matches(text, doc.selection(view), &regex).unwrap();
+            doc.set_selection(view, selection);
+        }
+        let matches = doc.selection(view).clone();
+        assert_eq!(matches.len(), 2);
+
+        assert!(doc.selection_backward(view, 1));
+        assert_eq!(doc.selection(view), &before);
+        assert!(doc.selection_backward(view, 1));
+        assert_eq!(doc.selection(view), &start);
+        assert!(doc.selection_forward(view, 2));
+        assert_eq!(doc.selection(view), &matches);
+    }
+
+    #[test]
+    fn test_set_parsed_syntax() {
+        let mut doc = Document::from(Rope::from("hello"), None);
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::point(0));
+
+        // the result of the current background parse is installed
+        let cancel = Arc::new(AtomicUsize::new(0));
+        doc.parse_pending = true;
+        doc.parse_cancel = Some(cancel.clone());
+        doc.set_parsed_syntax(ParsedSyntax {
+            syntax: None,
+            cancel,
+        });
+        assert!(!doc.parse_pending);
+        assert!(doc.parse_cancel.is_none());
+
+        // an edit cancels the background parse, whose result is then discarded
+        let cancel = Arc::new(AtomicUsize::new(0));
+        doc.parse_pending = true;
+        doc.parse_cancel = Some(cancel.clone());
+        doc.pending_injections.push(0..5);
+        let transaction = Transaction::insert(doc.text(), doc.selection(view), "x".into());
+        doc.apply(&transaction, view);
+        assert_eq!(cancel.load(Ordering::Relaxed), 1);
+        assert!(doc.parse_cancel.is_none());
+        assert!(doc.pending_injections.is_empty());
+        doc.set_parsed_syntax(ParsedSyntax {
+            syntax: None,
+            cancel,
+        });
+        assert!(doc.parse_pending);
+    }
+
+    #[test]
+    fn test_parse_in_background() {
+        use helix_core::syntax::{Configuration, Loader};
+
+        // set runtime path so we can find the grammar
+        let mut runtime = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        runtime.push("../runtime");
+        std::env::set_var("HELIX_RUNTIME", runtime.to_str().unwrap());
+
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            roots = []
+            "#,
+        )
+        .unwrap();
+        let loader = Arc::new(Loader::new(config));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut doc = Document::from(Rope::from("fn main() {}\n"), None);
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::point(0));
+        doc.set_language2("source.rust", loader);
+        assert!(doc.syntax().unwrap().is_parsed(0..0));
+
+        let insert = |doc: &mut Document, text: &str| {
+            let transaction = Transaction::insert(doc.text(), doc.selection(view), text.into());
+            doc.apply(&transaction, view);
+        };
+        let first_kind = |doc: &Document| {
+            let root = doc.syntax().unwrap().tree().root_node();
+            root.child(0).unwrap().kind().to_string()
+        };
+
+        // once a parse timed out, the next edits are parsed in the background
+        doc.parse_pending = true;
+        insert(&mut doc, "// x\n");
+        assert!(!doc.syntax().unwrap().is_parsed(0..0));
+        let parse = doc.parse_in_background().unwrap();
+        assert!(doc.parse_in_background().is_none());
+        doc.set_parsed_syntax(runtime.block_on(parse));
+        assert!(!doc.parse_pending);
+        assert!(doc.syntax().unwrap().is_parsed(0..0));
+        assert_eq!(first_kind(&doc), "line_comment");
+
+        // a parse that started before an edit is discarded, and a new one is started
+        doc.parse_pending = true;
+        insert(&mut doc, "x;\n");
+        let parse = doc.parse_in_background().unwrap();
+        insert(&mut doc, "struct A;\n");
+        doc.set_parsed_syntax(runtime.block_on(parse));
+        assert!(doc.parse_pending);
+        assert!(!doc.syntax().unwrap().is_parsed(0..0));
+
+        let parse = doc.parse_in_background().unwrap();
+        doc.set_parsed_syntax(runtime.block_on(parse));
+        assert!(!doc.parse_pending);
+        assert!(doc.syntax().unwrap().is_parsed(0..0));
+        assert_eq!(first_kind(&doc), "struct_item");
+
+        // commands editing the text from the trees finish the parse instead of waiting for it
+        doc.parse_pending = true;
+        insert(&mut doc, "enum B {}\n");
+        let parse = doc.parse_in_background().unwrap();
+        doc.finish_parse(view);
+        assert!(!doc.parse_pending);
+        assert!(doc.syntax().unwrap().is_parsed(0..0));
+        assert_eq!(first_kind(&doc), "enum_item");
+        doc.set_parsed_syntax(runtime.block_on(parse));
+        assert!(doc.parse_cancel.is_none());
+    }
+
+    #[test]
+    fn test_large_file() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let len = std::fs::metadata(&path).unwrap().len() as usize;
+
+        let mut doc = Document::open(&path, None, None, Some(len - 1)).unwrap();
+        assert!(doc.is_large_file());
+        assert!(doc.diff_in_background().is_none());
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::point(0));
+        doc.reload(view).unwrap();
+        assert_eq!(
+            doc.text().to_string(),
+            std::fs::read_to_string(&path).unwrap()
+        );
+        assert!(!doc.is_modified());
+        let doc = Document::open(&path, None, None, Some(len)).unwrap();
+        assert!(!doc.is_large_file());
+        let doc = Document::open(&path, None, None, None).unwrap();
+        assert!(!doc.is_large_file());
+    }
+
     macro_rules! test_decode {
         ($label:expr, $label_override:expr) => {
             let encoding = encoding::Encoding::for_label($label_override.as_bytes()).unwrap();
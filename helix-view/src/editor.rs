@@ -1,5 +1,6 @@
 use crate::{
     clipboard::{get_clipboard_provider, ClipboardProvider},
+    diff::{DiffPair, Side},
     document::{Mode, SCRATCH_BUFFER_NAME},
     graphics::{CursorKind, Rect},
     info::Info,
@@ -25,7 +26,7 @@ use anyhow::{bail, Error};
 
 pub use helix_core::diagnostic::Severity;
 pub use helix_core::register::Registers;
-use helix_core::syntax;
+use helix_core::{history, search::RopeRegex, syntax};
 use helix_core::{Position, Selection};
 
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize};
@@ -111,6 +112,18 @@ pub struct Config {
     /// Search configuration.
     #[serde(default)]
     pub search: SearchConfig,
+    /// Bounds on the undo history of each document. Unbounded by default.
+    pub undo: history::Limits,
+    /// Whether to soft wrap lines that are longer than the view is wide, instead of
+    /// scrolling horizontally. Defaults to false.
+    pub soft_wrap: bool,
+    /// Whether to colour bracket pairs by their nesting depth with the `rainbow` theme
+    /// scopes. Defaults to false.
+    pub rainbow_brackets: bool,
+    /// Size in bytes above which files are opened in large-file mode: they are not parsed,
+    /// their indentation isn't detected, they aren't diffed against version control and no
+    /// language server is started for them. Defaults to 50 MiB.
+    pub large_file_threshold: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -221,6 +234,10 @@ impl Default for Config {
             cursor_shape: CursorShapeConfig::default(),
             true_color: false,
             search: SearchConfig::default(),
+            undo: history::Limits::default(),
+            soft_wrap: false,
+            rainbow_brackets: false,
+            large_file_threshold: 50 * 1024 * 1024,
         }
     }
 }
@@ -269,6 +286,13 @@ pub struct Editor {
 
     pub idle_timer: Pin<Box<Sleep>>,
     pub last_motion: Option<Motion>,
+    /// The views compared side by side with `:diff`.
+    pub diff: Option<DiffPair>,
+    /// The regex of the last search, whose matches are highlighted until cleared.
+    pub search_highlight: Option<RopeRegex>,
+    /// Global marks in files that aren't open. The global marks of open documents are kept in
+    /// the documents, so that they follow edits, and move here when the document is closed.
+    pub global_marks: BTreeMap<char, (PathBuf, Selection)>,
 
     pub exit_code: i32,
 }
@@ -310,6 +334,9 @@ impl Editor {
             autoinfo: None,
             idle_timer: Box::pin(sleep(config.idle_timeout)),
             last_motion: None,
+            diff: None,
+            search_highlight: None,
+            global_marks: BTreeMap::new(),
             config,
             exit_code: 0,
         }
@@ -363,6 +390,11 @@ impl Editor {
 
     /// Launch a language server for a given document
     fn launch_language_server(ls: &mut helix_lsp::Registry, doc: &mut Document) -> Option<()> {
+        // sending the whole text of a large file to a language server is too slow
+        if doc.is_large_file() {
+            return None;
+        }
+
         // try to find a language server based on the language name
         let language_server = doc.language.as_ref().and_then(|language| {
             ls.get(language)
@@ -398,6 +430,23 @@ impl Editor {
         Some(())
     }
 
+    /// Parses the injections within the lines visible in each view, they aren't parsed
+    /// when documents are edited.
+    pub fn parse_injections(&mut self) {
+        for (view, _) in self.tree.views() {
+            let doc = &self.documents[&view.doc];
+            let text = doc.text();
+            let last_line = (view.last_line(doc) + 1).min(text.len_lines());
+            let range =
+                text.line_to_char(view.offset.row.min(last_line))..text.line_to_char(last_line);
+
+            self.documents
+                .get_mut(&view.doc)
+                .unwrap()
+                .parse_injections(range);
+        }
+    }
+
     fn _refresh(&mut self) {
         for (view, _) in self.tree.views_mut() {
             let doc = &self.documents[&view.doc];
@@ -409,6 +458,7 @@ impl Editor {
         let view = self.tree.get_mut(current_view);
         view.doc = doc_id;
         view.offset = Position::default();
+        view.top_row = 0;
 
         let doc = self.documents.get_mut(&doc_id).unwrap();
 
@@ -510,6 +560,8 @@ impl Editor {
         self.next_document_id =
             DocumentId(unsafe { NonZeroUsize::new_unchecked(self.next_document_id.0.get() + 1) });
         doc.id = id;
+        doc.set_history_limits(self.config.undo);
+        doc.set_soft_wrap(self.config.soft_wrap);
         self.documents.insert(id, doc);
         id
     }
@@ -536,10 +588,28 @@ impl Editor {
         let id = if let Some(id) = id {
             id
         } else {
-            let mut doc = Document::open(&path, None, Some(self.syn_loader.clone()))?;
+            let mut doc = Document::open(
+                &path,
+                None,
+                Some(self.syn_loader.clone()),
+                Some(self.config.large_file_threshold),
+            )?;
 
             let _ = Self::launch_language_server(&mut self.language_servers, &mut doc);
 
+            // restore the global marks that point into the file
+            let names: Vec<_> = self
+                .global_marks
+                .iter()
+                .filter(|(_, (mark_path, _))| *mark_path == path)
+                .map(|(name, _)| *name)
+                .collect();
+            for name in names {
+                let (_, selection) = self.global_marks.remove(&name).unwrap();
+                let selection = selection.ensure_invariants(doc.text().slice(..));
+                doc.marks_mut().set(name, selection);
+            }
+
             self.new_document(doc)
         };
 
@@ -550,11 +620,7 @@ impl Editor {
     pub fn close(&mut self, id: ViewId) {
         let view = self.tree.get(self.tree.focus);
         // remove selection
-        self.documents
-            .get_mut(&view.doc)
-            .unwrap()
-            .selections
-            .remove(&id);
+        self.documents.get_mut(&view.doc).unwrap().remove_view(id);
 
         self.tree.remove(id);
         self._refresh();
@@ -595,7 +661,17 @@ impl Editor {
             self.close(view_id);
         }
 
-        self.documents.remove(&doc_id);
+        if let Some(doc) = self.documents.remove(&doc_id) {
+            // keep the global marks of the file, to reopen it when going to them
+            if let Some(path) = doc.path() {
+                for (name, selection) in doc.marks().iter() {
+                    if helix_core::marks::is_global(name) {
+                        self.global_marks
+                            .insert(name, (path.clone(), selection.clone()));
+                    }
+                }
+            }
+        }
 
         // If the document we removed was visible in all views, we will have no more views. We don't
         // want to close the editor just for a simple buffer close, so we need to create a new view
@@ -648,6 +724,50 @@ impl Editor {
         self.tree.is_empty()
     }
 
+    /// Compares the documents of the `left` and `right` views side by side, replacing the
+    /// previous comparison.
+    pub fn diff_views(&mut self, left: ViewId, right: ViewId) {
+        let left_doc = &self.documents[&self.tree.get(left).doc];
+        let right_doc = &self.documents[&self.tree.get(right).doc];
+        self.diff = Some(DiffPair::new((left, left_doc), (right, right_doc)));
+        self.update_diff();
+    }
+
+    /// Marks the documents of `:diff` to be compared again after edits, see
+    /// [`DiffPair::diff_in_background`], and scrolls the view paired with the focused one to
+    /// the matching lines. The comparison ends once either view is closed or shows another
+    /// document.
+    pub fn update_diff(&mut self) {
+        let diff = match &mut self.diff {
+            Some(diff) => diff,
+            None => return,
+        };
+
+        let tree = &self.tree;
+        let is_valid = [Side::Left, Side::Right].into_iter().all(|side| {
+            let view = diff.view(side);
+            tree.contains(view) && tree.get(view).doc == diff.doc(side)
+        });
+        if !is_valid {
+            self.diff = None;
+            return;
+        }
+
+        diff.update(
+            &self.documents[&diff.doc(Side::Left)],
+            &self.documents[&diff.doc(Side::Right)],
+        );
+
+        if let Some(side) = diff.side(self.tree.focus) {
+            let row = diff.map_line(side, self.tree.get(self.tree.focus).offset.row);
+            // the hunks may lag behind the documents
+            let len_lines = self.documents[&diff.doc(side.other())].text().len_lines();
+            let view = self.tree.get_mut(diff.view(side.other()));
+            view.offset.row = row.min(len_lines.saturating_sub(1));
+            view.top_row = 0;
+        }
+    }
+
     pub fn ensure_cursor_in_view(&mut self, id: ViewId) {
         let view = self.tree.get_mut(id);
         let doc = &self.documents[&view.doc];
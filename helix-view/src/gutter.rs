@@ -36,6 +36,47 @@ pub fn diagnostic<'doc>(
     })
 }
 
+/// Marks the lines added, modified and removed since the version of the file in version
+/// control.
+pub fn diff<'doc>(
+    doc: &'doc Document,
+    _view: &View,
+    theme: &Theme,
+    _config: &Config,
+    _is_focused: bool,
+    _width: usize,
+) -> GutterFn<'doc> {
+    let added = theme.get("diff.plus");
+    let modified = theme.get("diff.delta");
+    let removed = theme.get("diff.minus");
+    let hunks = doc.diff_hunks();
+
+    Box::new(move |line: usize, _selected: bool, out: &mut String| {
+        // the first hunk starting at or after the line, or the one before it covering it
+        let i = hunks.partition_point(|hunk| hunk.after.start < line);
+        let hunk = match hunks.get(i) {
+            Some(hunk) if hunk.after.start == line => hunk,
+            _ => match i.checked_sub(1).map(|i| &hunks[i]) {
+                Some(hunk) if hunk.after.end > line => hunk,
+                _ => return None,
+            },
+        };
+
+        if hunk.is_pure_removal() {
+            // the lines were removed above this one
+            write!(out, "▔").unwrap();
+            Some(removed)
+        } else {
+            write!(out, "▍").unwrap();
+            Some(if hunk.is_pure_insertion() {
+                added
+            } else {
+                modified
+            })
+        }
+    })
+}
+
 pub fn line_number<'doc>(
     doc: &'doc Document,
     view: &View,
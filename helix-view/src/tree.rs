@@ -283,6 +283,17 @@ impl Tree {
         }
     }
 
+    /// Whether `index` is a view of the tree.
+    pub fn contains(&self, index: ViewId) -> bool {
+        matches!(
+            self.nodes.get(index),
+            Some(Node {
+                content: Content::View(_),
+                ..
+            })
+        )
+    }
+
     pub fn get_mut(&mut self, index: ViewId) -> &mut View {
         match &mut self.nodes[index] {
             Node {
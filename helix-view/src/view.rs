@@ -1,15 +1,9 @@
-use std::borrow::Cow;
-
 use crate::{
     graphics::Rect,
     gutter::{self, Gutter},
     Document, DocumentId, ViewId,
 };
-use helix_core::{
-    graphemes::{grapheme_width, RopeGraphemes},
-    line_ending::line_end_char_index,
-    visual_coords_at_pos, Position, RopeSlice, Selection,
-};
+use helix_core::{wrap::Rows, Position, RopeSlice, Selection};
 
 type Jump = (DocumentId, Selection);
 
@@ -64,13 +58,21 @@ impl JumpList {
     }
 }
 
-const GUTTERS: &[(Gutter, usize)] = &[(gutter::diagnostic, 1), (gutter::line_number, 5)];
+const GUTTERS: &[(Gutter, usize)] = &[
+    (gutter::diagnostic, 1),
+    (gutter::line_number, 5),
+    (gutter::diff, 1),
+];
 
 #[derive(Debug)]
 pub struct View {
     pub id: ViewId,
     pub doc: DocumentId,
     pub offset: Position,
+    /// The row of the first line on screen (`offset.row`) that the view starts at. It is only
+    /// non-zero when that line is soft wrapped, so that lines taller than the view can be
+    /// scrolled through.
+    pub top_row: usize,
     pub area: Rect,
     pub jumps: JumpList,
     /// the last accessed file before the current one
@@ -90,6 +92,7 @@ impl View {
             id: ViewId::default(),
             doc,
             offset: Position::new(0, 0),
+            top_row: 0,
             area: Rect::default(), // will get calculated upon inserting into tree
             jumps: JumpList::new((doc, Selection::point(0))), // TODO: use actual sel
             last_accessed_doc: None,
@@ -113,22 +116,45 @@ impl View {
         self.area.clip_left(offset).clip_bottom(1) // -1 for statusline
     }
 
-    //
+    /// The width lines are soft wrapped to, if they are.
+    fn wrap_width(&self, doc: &Document) -> Option<usize> {
+        doc.soft_wrap().then(|| self.inner_area().width as usize)
+    }
+
+    /// The rows `doc` is displayed on in this view, skipping folded lines and soft wrapping
+    /// long lines.
+    pub fn rows<'a>(&self, doc: &'a Document) -> Rows<'a> {
+        Rows::new(
+            doc.text().slice(..),
+            doc.folds(self.id),
+            self.wrap_width(doc),
+            doc.tab_width(),
+        )
+    }
+
+    /// The line and the row within the line that the view starts at, and the column it is
+    /// scrolled to, so that the cursor is in view with `scrolloff` rows and columns around it.
+    /// Returns `None` if the view doesn't need to scroll.
     pub fn offset_coords_to_in_view(
         &self,
         doc: &Document,
         scrolloff: usize,
-    ) -> Option<(usize, usize)> {
-        let cursor = doc
-            .selection(self.id)
-            .primary()
-            .cursor(doc.text().slice(..));
-
-        let Position { col, row: line } =
-            visual_coords_at_pos(doc.text().slice(..), cursor, doc.tab_width());
+    ) -> Option<(usize, usize, usize)> {
+        let text = doc.text().slice(..);
+        let cursor = doc.selection(self.id).primary().cursor(text);
+
+        let rows = self.rows(doc);
+        let (line, row, col) = rows.coords_at_pos(cursor);
+        // a cursor within a fold is shown on the first line of the fold
+        let lines = rows.visible_lines();
+        let cursor = if lines.is_hidden(line) {
+            (lines.visible(line), 0)
+        } else {
+            (line, row)
+        };
 
         let inner_area = self.inner_area();
-        let last_line = (self.offset.row + inner_area.height as usize).saturating_sub(1);
+        let height = inner_area.height as usize;
 
         // - 1 so we have at least one gap in the middle.
         // a height of 6 with padding of 3 on each side will keep shifting the view back and forth
@@ -137,17 +163,38 @@ impl View {
 
         let last_col = self.offset.col + inner_area.width.saturating_sub(1) as usize;
 
-        let row = if line > last_line.saturating_sub(scrolloff) {
-            // scroll down
-            self.offset.row + line - (last_line.saturating_sub(scrolloff))
-        } else if line < self.offset.row + scrolloff {
-            // scroll up
-            line.saturating_sub(scrolloff)
-        } else {
-            self.offset.row
+        // the number of rows between the top of the view and the cursor, `height` if the cursor
+        // is below the view
+        let top = (self.offset.row, self.top_row);
+        let distance = (cursor >= top).then(|| {
+            rows.iter_from(top.0, top.1)
+                .take(height)
+                .position(|coords| coords == cursor)
+                .unwrap_or(height)
+        });
+
+        let (line, row) = match distance {
+            Some(distance) if distance > height.saturating_sub(1 + scrolloff) => {
+                // scroll down
+                rows.iter_back_from(cursor.0, cursor.1)
+                    .take(height.saturating_sub(scrolloff))
+                    .last()
+                    .unwrap_or(cursor)
+            }
+            Some(distance) if distance >= scrolloff => top,
+            _ => {
+                // scroll up
+                rows.iter_back_from(cursor.0, cursor.1)
+                    .take(scrolloff + 1)
+                    .last()
+                    .unwrap_or(cursor)
+            }
         };
 
-        let col = if col > last_col.saturating_sub(scrolloff) {
+        let col = if rows.is_wrapped() {
+            // soft wrapped lines never scroll horizontally
+            0
+        } else if col > last_col.saturating_sub(scrolloff) {
             // scroll right
             self.offset.col + col - (last_col.saturating_sub(scrolloff))
         } else if col < self.offset.col + scrolloff {
@@ -156,16 +203,17 @@ impl View {
         } else {
             self.offset.col
         };
-        if row == self.offset.row && col == self.offset.col {
+        if (line, row) == top && col == self.offset.col {
             None
         } else {
-            Some((row, col))
+            Some((line, row, col))
         }
     }
 
     pub fn ensure_cursor_in_view(&mut self, doc: &Document, scrolloff: usize) {
-        if let Some((row, col)) = self.offset_coords_to_in_view(doc, scrolloff) {
-            self.offset.row = row;
+        if let Some((line, row, col)) = self.offset_coords_to_in_view(doc, scrolloff) {
+            self.offset.row = line;
+            self.top_row = row;
             self.offset.col = col;
         }
     }
@@ -177,12 +225,16 @@ impl View {
     /// Calculates the last visible line on screen
     #[inline]
     pub fn last_line(&self, doc: &Document) -> usize {
-        let height = self.inner_area().height;
-        std::cmp::min(
-            // Saturating subs to make it inclusive zero indexing.
-            (self.offset.row + height as usize).saturating_sub(1),
-            doc.text().len_lines().saturating_sub(1),
-        )
+        let height = self.inner_area().height.max(1) as usize;
+        // count the rows on screen, skipping folded lines and wrapping long ones
+        self.rows(doc)
+            .iter_from(self.offset.row, self.top_row)
+            .take(height)
+            .last()
+            .map_or_else(
+                || doc.text().len_lines().saturating_sub(1),
+                |(line, _)| line,
+            )
     }
 
     /// Translates a document position to an absolute position in the terminal.
@@ -194,41 +246,31 @@ impl View {
         text: RopeSlice,
         pos: usize,
     ) -> Option<Position> {
-        let line = text.char_to_line(pos);
+        let rows = Rows::new(
+            text,
+            doc.folds(self.id),
+            self.wrap_width(doc),
+            doc.tab_width(),
+        );
+        let (line, row, col) = rows.coords_at_pos(pos);
 
-        if line < self.offset.row || line > self.last_line(doc) {
+        if line < self.offset.row || rows.visible_lines().is_hidden(line) {
             // Line is not visible on screen
             return None;
         }
 
-        let line_start = text.line_to_char(line);
-        let line_slice = text.slice(line_start..pos);
-        let mut col = 0;
-        let tab_width = doc.tab_width();
-
-        for grapheme in RopeGraphemes::new(line_slice) {
-            if grapheme == "\t" {
-                col += tab_width;
-            } else {
-                let grapheme = Cow::from(grapheme);
-                col += grapheme_width(&grapheme);
-            }
-        }
-
+        let height = self.inner_area().height as usize;
+        let row = rows
+            .iter_from(self.offset.row, self.top_row)
+            .take(height)
+            .position(|coords| coords == (line, row))?;
         // It is possible for underflow to occur if the buffer length is larger than the terminal width.
-        let row = line.saturating_sub(self.offset.row);
         let col = col.saturating_sub(self.offset.col);
 
         Some(Position::new(row, col))
     }
 
-    pub fn text_pos_at_screen_coords(
-        &self,
-        text: &RopeSlice,
-        row: u16,
-        column: u16,
-        tab_width: usize,
-    ) -> Option<usize> {
+    pub fn text_pos_at_screen_coords(&self, rows: &Rows, row: u16, column: u16) -> Option<usize> {
         let inner = self.inner_area();
         // 1 for status
         if row < inner.top() || row >= inner.bottom() {
@@ -239,39 +281,22 @@ impl View {
             return None;
         }
 
-        let line_number = (row - inner.y) as usize + self.offset.row;
-
-        if line_number > text.len_lines() - 1 {
-            return Some(text.len_chars());
-        }
-
-        let mut pos = text.line_to_char(line_number);
-
-        let current_line = text.line(line_number);
+        let (line, line_row) = match rows
+            .iter_from(self.offset.row, self.top_row)
+            .nth((row - inner.y) as usize)
+        {
+            Some(coords) => coords,
+            None => return Some(rows.text().len_chars()),
+        };
 
         let target = (column - inner.x) as usize + self.offset.col;
-        let mut selected = 0;
-
-        for grapheme in RopeGraphemes::new(current_line) {
-            if selected >= target {
-                break;
-            }
-            if grapheme == "\t" {
-                selected += tab_width;
-            } else {
-                let width = grapheme_width(&Cow::from(grapheme));
-                selected += width;
-            }
-            pos += grapheme.chars().count();
-        }
-
-        Some(pos.min(line_end_char_index(&text.slice(..), line_number)))
+        Some(rows.pos_at_coords(line, line_row, target))
     }
 
     /// Translates a screen position to position in the text document.
     /// Returns a usize typed position in bounds of the text if found in this view, None if out of view.
     pub fn pos_at_screen_coords(&self, doc: &Document, row: u16, column: u16) -> Option<usize> {
-        self.text_pos_at_screen_coords(&doc.text().slice(..), row, column, doc.tab_width())
+        self.text_pos_at_screen_coords(&self.rows(doc), row, column)
     }
     // pub fn traverse<F>(&self, text: RopeSlice, start: usize, end: usize, fun: F)
     // where
@@ -298,8 +323,8 @@ impl View {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use helix_core::Rope;
-    const OFFSET: u16 = 7; // 1 diagnostic + 5 linenr + 1 gutter
+    use helix_core::{fold::Folds, Rope};
+    const OFFSET: u16 = 8; // 1 diagnostic + 5 linenr + 1 diff + 1 gutter
                            // const OFFSET: u16 = GUTTERS.iter().map(|(_, width)| *width as u16).sum();
 
     #[test]
@@ -308,44 +333,45 @@ mod tests {
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("abc\n\tdef");
         let text = rope.slice(..);
+        let rows = Rows::new(text, &Folds::default(), None, 4);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 40, 2, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 40, 2), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 40, 41, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 40, 41), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 0, 2, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 0, 2), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 0, 49, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 0, 49), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 0, 41, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 0, 41), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 40, 81, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 40, 81), None);
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 78, 41, 4), None);
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 78, 41), None);
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 3, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 3),
             Some(3)
         );
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 40, 80, 4), Some(3));
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 40, 80), Some(3));
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 41, 40 + OFFSET + 1, 4),
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET + 1),
             Some(5)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 41, 40 + OFFSET + 4, 4),
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET + 4),
             Some(5)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 41, 40 + OFFSET + 7, 4),
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET + 7),
             Some(8)
         );
 
-        assert_eq!(view.text_pos_at_screen_coords(&text, 41, 80, 4), Some(8));
+        assert_eq!(view.text_pos_at_screen_coords(&rows, 41, 80), Some(8));
     }
 
     #[test]
@@ -354,29 +380,30 @@ mod tests {
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("Hi! こんにちは皆さん");
         let text = rope.slice(..);
+        let rows = Rows::new(text, &Folds::default(), None, 4);
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET),
             Some(0)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 5, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 5),
             Some(5)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 6, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 6),
             Some(5)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 7, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 7),
             Some(6)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 8, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 8),
             Some(6)
         );
     }
@@ -387,30 +414,107 @@ mod tests {
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("Hèl̀l̀ò world!");
         let text = rope.slice(..);
+        let rows = Rows::new(text, &Folds::default(), None, 4);
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET),
             Some(0)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 1, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 1),
             Some(1)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 2, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 2),
             Some(3)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 3, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 3),
             Some(5)
         );
 
         assert_eq!(
-            view.text_pos_at_screen_coords(&text, 40, 40 + OFFSET + 4, 4),
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET + 4),
             Some(7)
         );
     }
+
+    #[test]
+    fn test_text_pos_at_screen_coords_folded() {
+        let mut view = View::new(DocumentId::default());
+        view.area = Rect::new(40, 40, 40, 40);
+        let rope = Rope::from_str("a\nb\nc\nd\n");
+        let text = rope.slice(..);
+        let mut folds = Folds::default();
+        // hide "b" and "c" behind "a"
+        folds.add(0..5);
+        let rows = Rows::new(text, &folds, None, 4);
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&rows, 40, 40 + OFFSET),
+            Some(0)
+        );
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn test_text_pos_at_screen_coords_wrapped() {
+        let mut view = View::new(DocumentId::default());
+        view.area = Rect::new(40, 40, 40, 40);
+        let rope = Rope::from_str("  a b c d\nxy");
+        let text = rope.slice(..);
+        // "  a b " | "  c d"
+        let rows = Rows::new(text, &Folds::default(), Some(6), 4);
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET + 2),
+            Some(6)
+        );
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&rows, 41, 40 + OFFSET),
+            Some(6)
+        );
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&rows, 42, 40 + OFFSET + 1),
+            Some(11)
+        );
+    }
+
+    #[test]
+    fn test_scroll_within_wrapped_line() {
+        let mut view = View::new(DocumentId::default());
+        view.area = Rect::new(0, 0, 10 + OFFSET, 4);
+        let height = view.inner_area().height as usize;
+        // a single line wrapping into 20 rows of "abcd abcd "
+        let mut doc = Document::from(Rope::from("abcd ".repeat(40)), None);
+        doc.set_soft_wrap(true);
+        let text = doc.text().clone();
+
+        doc.set_selection(view.id, Selection::point(195));
+        view.ensure_cursor_in_view(&doc, 0);
+        assert_eq!(view.offset.row, 0);
+        assert_eq!(view.top_row, 20 - height);
+        assert_eq!(
+            view.screen_coords_at_pos(&doc, text.slice(..), 195),
+            Some(Position::new(height - 1, 5))
+        );
+        assert_eq!(view.screen_coords_at_pos(&doc, text.slice(..), 0), None);
+        assert_eq!(
+            view.pos_at_screen_coords(&doc, 0, OFFSET),
+            Some((20 - height) * 10)
+        );
+
+        doc.set_selection(view.id, Selection::point(0));
+        view.ensure_cursor_in_view(&doc, 0);
+        assert_eq!(view.top_row, 0);
+    }
 }
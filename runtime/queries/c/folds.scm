@@ -0,0 +1,15 @@
+[
+  (function_definition)
+  (struct_specifier)
+  (enum_specifier)
+  (union_specifier)
+  (if_statement)
+  (for_statement)
+  (while_statement)
+  (do_statement)
+  (switch_statement)
+  (case_statement)
+  (preproc_if)
+  (preproc_ifdef)
+  (comment)
+] @fold
@@ -11,3 +11,23 @@
   body: (_) @class.inside) @class.around
 
 (parameter_declaration) @parameter.inside
+
+[
+  (parameter_list)
+  (argument_list)
+] @arguments.around
+
+(type_definition
+  type: (_) @type.inside) @type.around
+
+(field_declaration
+  type: (_) @entry.inside) @entry.around
+
+(enumerator) @entry.around
+
+(initializer_pair
+  value: (_) @entry.inside) @entry.around
+
+(compound_statement) @block.around
+
+(comment) @comment.inside @comment.around
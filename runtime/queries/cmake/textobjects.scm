@@ -1,3 +1,8 @@
 (macro_def) @function.around
 
 (argument) @parameter.inside
+
+[
+  (bracket_comment)
+  (line_comment)
+] @comment.inside @comment.around
@@ -0,0 +1,7 @@
+; inherits: c
+
+[
+  (class_specifier)
+  (namespace_definition)
+  (lambda_expression)
+] @fold
@@ -5,3 +5,8 @@
 
 (class_specifier
   body: (_) @class.inside) @class.around
+
+[
+  (template_parameter_list)
+  (template_argument_list)
+] @arguments.around
@@ -1 +1,3 @@
 (function_definition) @function.around
+
+(comment) @comment.inside @comment.around
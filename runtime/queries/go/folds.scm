@@ -0,0 +1,15 @@
+[
+  (function_declaration)
+  (method_declaration)
+  (func_literal)
+  (type_declaration)
+  (import_declaration)
+  (const_declaration)
+  (var_declaration)
+  (if_statement)
+  (for_statement)
+  (expression_switch_statement)
+  (type_switch_statement)
+  (select_statement)
+  (comment)
+] @fold
@@ -19,3 +19,30 @@
 
 (argument_list
   (_) @parameter.inside)
+
+(
+  (function_declaration
+    name: (identifier) @_name
+    body: (block)? @test.inside) @test.around
+  (#match? @_name "^Test")
+)
+
+[
+  (parameter_list)
+  (argument_list)
+  (type_parameter_list)
+  (type_arguments)
+] @arguments.around
+
+(type_declaration
+  (type_spec
+    type: (_) @type.inside)) @type.around
+
+(field_declaration
+  type: (_) @entry.inside) @entry.around
+
+(keyed_element) @entry.around
+
+(block) @block.around
+
+(comment) @comment.inside @comment.around
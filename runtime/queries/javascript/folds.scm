@@ -0,0 +1,18 @@
+[
+  (function_declaration)
+  (generator_function_declaration)
+  (arrow_function)
+  (class_declaration)
+  (method_definition)
+  (object)
+  (array)
+  (if_statement)
+  (for_statement)
+  (for_in_statement)
+  (while_statement)
+  (switch_statement)
+  (try_statement)
+  (import_statement)
+  (template_string)
+  (comment)
+] @fold
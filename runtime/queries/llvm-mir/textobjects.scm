@@ -1,3 +1,8 @@
 (basic_block) @function.around
 
 (argument) @parameter.inside
+
+[
+  (comment)
+  (multiline_comment)
+] @comment.inside @comment.around
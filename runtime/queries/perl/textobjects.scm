@@ -6,3 +6,5 @@
 
 (argument 
   (_) @parameter.inside)
+
+(comments) @comment.inside @comment.around
@@ -28,3 +28,21 @@
     (variadic_parameter)
     (property_promotion_parameter)
   ] @parameter.inside)
+
+(
+  (method_declaration
+    name: (name) @_name
+    body: (_) @test.inside) @test.around
+  (#match? @_name "^test")
+)
+
+[
+  (formal_parameters)
+  (arguments)
+] @arguments.around
+
+(array_element_initializer) @entry.around
+
+(compound_statement) @block.around
+
+(comment) @comment.inside @comment.around
@@ -0,0 +1,67 @@
+[
+  (list)
+  (tuple)
+  (dictionary)
+  (set)
+
+  (import_from_statement)
+
+  (parenthesized_expression)
+  (generator_expression)
+  (list_comprehension)
+  (set_comprehension)
+  (dictionary_comprehension)
+
+  (tuple_pattern)
+  (list_pattern)
+  (argument_list)
+  (parameters)
+  (binary_operator)
+] @indent
+
+; Blocks aren't closed by a token, so the statements they belong to are extended to
+; the following lines that are indented more.
+[
+  (if_statement)
+  (for_statement)
+  (while_statement)
+  (with_statement)
+  (try_statement)
+  (function_definition)
+  (class_definition)
+] @indent @extend
+
+[
+  (elif_clause)
+  (else_clause)
+  (except_clause)
+  (finally_clause)
+] @indent @outdent @extend
+
+; Dedent after statements that end a block.
+[
+  (return_statement)
+  (break_statement)
+  (continue_statement)
+  (raise_statement)
+  (pass_statement)
+] @extend.prevent-once
+
+[
+  ")"
+  "]"
+  "}"
+] @outdent
+
+; Align hanging arguments with the first one.
+(argument_list
+  "(" @open
+  .
+  (_) @anchor
+  (#same-line? @open @anchor)) @align
+
+(parameters
+  "(" @open
+  .
+  (_) @anchor
+  (#same-line? @open @anchor)) @align
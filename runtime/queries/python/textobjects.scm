@@ -12,3 +12,28 @@
 
 (argument_list
   (_) @parameter.inside)
+
+(
+  (function_definition
+    name: (identifier) @_name
+    body: (block)? @test.inside) @test.around
+  (#match? @_name "^test")
+)
+
+[
+  (parameters)
+  (lambda_parameters)
+  (argument_list)
+] @arguments.around
+
+(type) @type.inside @type.around
+
+(pair
+  value: (_) @entry.inside) @entry.around
+
+(keyword_argument
+  value: (_) @entry.inside) @entry.around
+
+(block) @block.around
+
+(comment) @comment.inside @comment.around
@@ -7,3 +7,5 @@
 ;----------
 
 (function body: (_) @function.inside) @function.around
+
+(comment) @comment.inside @comment.around
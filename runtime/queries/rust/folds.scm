@@ -0,0 +1,17 @@
+[
+  (function_item)
+  (impl_item)
+  (trait_item)
+  (struct_item)
+  (enum_item)
+  (union_item)
+  (mod_item)
+  (macro_definition)
+  (use_declaration)
+  (match_expression)
+  (if_expression)
+  (for_expression)
+  (while_expression)
+  (loop_expression)
+  (block_comment)
+] @fold
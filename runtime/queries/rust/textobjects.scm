@@ -24,3 +24,49 @@
 
 (arguments
   (_) @parameter.inside)
+
+(
+  (attribute_item (meta_item (identifier) @_test_attribute))
+  .
+  (function_item
+    body: (_) @test.inside) @test.around
+  (#eq? @_test_attribute "test")
+)
+
+[
+  (parameters)
+  (closure_parameters)
+  (arguments)
+  (type_parameters)
+  (type_arguments)
+] @arguments.around
+
+(type_item
+  type: (_) @type.inside) @type.around
+
+(let_declaration
+  type: (_) @type.inside @type.around)
+
+(parameter
+  type: (_) @type.inside @type.around)
+
+(function_item
+  return_type: (_) @type.inside @type.around)
+
+(field_declaration
+  type: (_) @entry.inside) @entry.around
+
+(field_initializer
+  value: (_) @entry.inside) @entry.around
+
+(enum_variant) @entry.around
+
+(match_arm
+  value: (_) @entry.inside) @entry.around
+
+(block) @block.around
+
+[
+  (line_comment)
+  (block_comment)
+] @comment.inside @comment.around
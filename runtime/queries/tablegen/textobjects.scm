@@ -5,3 +5,8 @@
   body: (_) @class.inside) @class.around
 
 (_ argument: _ @parameter.inside)
+
+[
+  (comment)
+  (multiline_comment)
+] @comment.inside @comment.around
@@ -0,0 +1,6 @@
+; inherits: javascript
+
+[
+  (interface_declaration)
+  (enum_declaration)
+] @fold
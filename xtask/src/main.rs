@@ -16,18 +16,25 @@ pub mod helpers {
         Highlight,
         TextObjects,
         AutoIndent,
+        Folds,
     }
 
     impl TsFeature {
         pub fn all() -> &'static [Self] {
-            &[Self::Highlight, Self::TextObjects, Self::AutoIndent]
+            &[
+                Self::Highlight,
+                Self::TextObjects,
+                Self::AutoIndent,
+                Self::Folds,
+            ]
         }
 
-        pub fn runtime_filename(&self) -> &'static str {
+        pub fn runtime_filenames(&self) -> &'static [&'static str] {
             match *self {
-                Self::Highlight => "highlights.scm",
-                Self::TextObjects => "textobjects.scm",
-                Self::AutoIndent => "indents.toml",
+                Self::Highlight => &["highlights.scm"],
+                Self::TextObjects => &["textobjects.scm"],
+                Self::AutoIndent => &["indents.toml", "indents.scm"],
+                Self::Folds => &["folds.scm"],
             }
         }
     }
@@ -41,6 +48,7 @@ pub mod helpers {
                     Self::Highlight => "Syntax Highlighting",
                     Self::TextObjects => "Treesitter Textobjects",
                     Self::AutoIndent => "Auto Indent",
+                    Self::Folds => "Code Folding",
                 }
             )
         }
@@ -51,8 +59,9 @@ pub mod helpers {
     pub fn ts_lang_support(feat: TsFeature) -> Vec<String> {
         let queries_dir = path::ts_queries();
 
-        find_files(&queries_dir, feat.runtime_filename())
+        feat.runtime_filenames()
             .iter()
+            .flat_map(|filename| find_files(&queries_dir, filename))
             .map(|f| {
                 // .../helix/runtime/queries/python/highlights.scm
                 let tail = f.strip_prefix(&queries_dir).unwrap(); // python/highlights.scm